@@ -0,0 +1,721 @@
+//! Baked scene cache, a `(SceneView, GLTFBuffer)` written next to the glTF so later
+//! launches skip parsing, tangent generation and image decoding.
+//!
+//! [`bake`] writes `<file>.gfcache`, [`load`] hands it back while the hashes of the glTF,
+//! every file it references and the [`LoadOption`] still match.
+
+use std::collections::BTreeMap;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use glam::Mat4;
+use goth_gltf::{FilterMode, MinFilter, PrimitiveMode, Sampler, SamplerWrap};
+use memmap2::Mmap;
+use rayon::prelude::*;
+use snafu::{ensure, OptionExt, ResultExt, Snafu};
+use wgpu::TextureFormat;
+use xxhash_rust::xxh3::xxh3_64;
+
+use super::gltf::{
+    self, load_gltf_uncached, uri_path, AlphaMode, CameraProjection, Clearcoat, DecodedImage,
+    GLTFBuffer, GltfSource, ImageData, Index, LoadOption, Material, MaterialKey, Mesh, Node,
+    PerNodeBuffer, SceneCamera, SceneSelect, SceneView, Sheen, Specular, TextureData,
+    TextureTransform, Transmission, Volume,
+};
+
+mod texture;
+
+/// Bumped whenever the layout of the cache or of the baked types changes, or when the
+/// baked values themselves were wrong. 2 fixed the order of nested node transforms, 3 added
+/// the `TEXCOORD_1` stream.
+pub const VERSION: u32 = 3;
+const MAGIC: &[u8; 8] = b"GFSCACHE";
+const EXTENSION: &str = "gfcache";
+
+#[derive(Debug, Snafu)]
+pub enum Error {
+    FileReadFailed {
+        path: String,
+        source: std::io::Error,
+    },
+    FileWriteFailed {
+        path: String,
+        source: std::io::Error,
+    },
+    GltfLoadFailed {
+        source: gltf::Error,
+    },
+    NotACache,
+    VersionMismatch {
+        version: u32,
+    },
+    Truncated,
+    /// An enum tag, string or texture format the baker never writes
+    InvalidData,
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct BakeOption {
+    /// BC1 or BC3 compress RGBA8 images whose size is a multiple of 4, lossy and
+    /// the device needs `TEXTURE_COMPRESSION_BC` to load the cache
+    pub compress: bool,
+}
+
+/// `model.glb` caches to `model.glb.gfcache`.
+pub fn cache_path(path: &Path) -> PathBuf {
+    let mut file_name = path.file_name().unwrap_or_default().to_os_string();
+    file_name.push(".");
+    file_name.push(EXTENSION);
+    path.with_file_name(file_name)
+}
+
+/// Load the glTF at `path` and write its cache, mip chains are generated for every decoded image.
+pub fn bake(path: &Path, option: &LoadOption, bake_option: &BakeOption) -> Result<PathBuf, Error> {
+    let (mut scene_view, mut scene_buffer) =
+        load_gltf_uncached(path, option.clone(), &mut |_| {}).context(GltfLoadFailedSnafu)?;
+
+    scene_view.images.par_iter_mut().for_each(|image| {
+        if let Some(decoded) = &mut image.decoded {
+            texture::generate_mips(decoded);
+            if bake_option.compress {
+                texture::compress(decoded);
+            }
+        }
+    });
+    // only KTX2 images still read their source bytes
+    let mut shared_data = vec![];
+    for image in &mut scene_view.images {
+        if image.decoded.is_some() {
+            image.range = 0..0;
+        } else {
+            let start = shared_data.len();
+            shared_data.extend_from_slice(&scene_buffer.shared_data[image.range.clone()]);
+            image.range = start..shared_data.len();
+        }
+    }
+    scene_buffer.shared_data = shared_data;
+
+    let mut out = MAGIC.to_vec();
+    VERSION.write(&mut out);
+    hash_file(path)?.write(&mut out);
+    option_hash(option).write(&mut out);
+    let mut dependencies = vec![];
+    for uri in dependencies_of(path)? {
+        let hash = hash_file(&uri_path(&uri, path).context(GltfLoadFailedSnafu)?)?;
+        dependencies.push((uri, hash));
+    }
+    dependencies.write(&mut out);
+    scene_view.write(&mut out);
+    scene_buffer.write(&mut out);
+
+    let cache = cache_path(path);
+    std::fs::write(&cache, out).context(FileWriteFailedSnafu {
+        path: cache.to_string_lossy(),
+    })?;
+    Ok(cache)
+}
+
+/// The cache of the glTF at `path`, `None` when there is none or it is stale.
+/// A broken cache is only logged, the caller falls back to the glTF.
+pub fn load(path: &Path, option: &LoadOption) -> Option<(SceneView, GLTFBuffer)> {
+    let cache = cache_path(path);
+    if !cache.exists() {
+        return None;
+    }
+    match try_load(path, &cache, option) {
+        Ok(loaded) => loaded,
+        Err(err) => {
+            log::warn!("Ignoring the scene cache {}: {err}", cache.display());
+            None
+        }
+    }
+}
+
+fn try_load(
+    path: &Path,
+    cache: &Path,
+    option: &LoadOption,
+) -> Result<Option<(SceneView, GLTFBuffer)>, Error> {
+    let bytes = map_file(cache)?;
+    let mut reader = Reader { bytes: &bytes };
+    ensure!(reader.take(MAGIC.len())? == MAGIC, NotACacheSnafu);
+    let version = u32::read(&mut reader)?;
+    ensure!(version == VERSION, VersionMismatchSnafu { version });
+    if u64::read(&mut reader)? != hash_file(path)? || u64::read(&mut reader)? != option_hash(option)
+    {
+        return Ok(None);
+    }
+    for (uri, hash) in Vec::<(String, u64)>::read(&mut reader)? {
+        let dependency = uri_path(&uri, path).context(GltfLoadFailedSnafu)?;
+        if !dependency.exists() || hash_file(&dependency)? != hash {
+            return Ok(None);
+        }
+    }
+    Ok(Some((
+        SceneView::read(&mut reader)?,
+        GLTFBuffer::read(&mut reader)?,
+    )))
+}
+
+fn map_file(path: &Path) -> Result<Mmap, Error> {
+    let context = |_: &mut std::io::Error| FileReadFailedSnafu {
+        path: path.to_string_lossy(),
+    };
+    let file = std::fs::File::open(path).with_context(context)?;
+    // SAFETY: the file must not be truncated while mapped, assets are not written while loading
+    unsafe { Mmap::map(&file) }.with_context(context)
+}
+
+fn hash_file(path: &Path) -> Result<u64, Error> {
+    Ok(xxh3_64(&map_file(path)?))
+}
+
+/// Only the options that change what is loaded.
+pub(crate) fn option_hash(option: &LoadOption) -> u64 {
+    let mut out = vec![];
+    option.gen_tbn.write(&mut out);
+    match &option.scene {
+        SceneSelect::Default => 0u8.write(&mut out),
+        SceneSelect::Index(i) => {
+            1u8.write(&mut out);
+            i.write(&mut out);
+        }
+        SceneSelect::Name(name) => {
+            2u8.write(&mut out);
+            name.write(&mut out);
+        }
+    }
+    xxh3_64(&out)
+}
+
+/// Uris of the buffers and images living in their own files.
+fn dependencies_of(path: &Path) -> Result<Vec<String>, Error> {
+    let source = GltfSource::open(path, true, &mut |_| {}).context(GltfLoadFailedSnafu)?;
+    let gltf_info = source.gltf();
+    let buffers = gltf_info.buffers.iter().filter_map(|i| i.uri.as_ref());
+    let images = gltf_info.images.iter().filter_map(|i| i.uri.as_ref());
+    Ok(buffers
+        .chain(images)
+        .filter(|uri| !uri.starts_with("data:"))
+        .cloned()
+        .collect())
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], Error> {
+        ensure!(len <= self.bytes.len(), TruncatedSnafu);
+        let (taken, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Ok(taken)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], Error> {
+        let mut array = [0; N];
+        array.copy_from_slice(self.take(N)?);
+        Ok(array)
+    }
+
+    fn tag(&mut self) -> Result<u8, Error> {
+        u8::read(self)
+    }
+}
+
+/// Little endian binary form of a cached type.
+trait Bake: Sized {
+    fn write(&self, out: &mut Vec<u8>);
+    fn read(reader: &mut Reader) -> Result<Self, Error>;
+}
+
+macro_rules! bake_number {
+    ($($ty:ty),*) => {$(
+        impl Bake for $ty {
+            fn write(&self, out: &mut Vec<u8>) {
+                out.extend_from_slice(&self.to_le_bytes());
+            }
+
+            fn read(reader: &mut Reader) -> Result<Self, Error> {
+                Ok(Self::from_le_bytes(reader.array()?))
+            }
+        }
+    )*};
+}
+
+bake_number!(u8, u32, u64, f32);
+
+/// Structs baked field by field in the listed order.
+macro_rules! bake_struct {
+    ($ty:ty { $($field:ident),* $(,)? }) => {
+        impl Bake for $ty {
+            fn write(&self, out: &mut Vec<u8>) {
+                $(self.$field.write(out);)*
+            }
+
+            fn read(reader: &mut Reader) -> Result<Self, Error> {
+                Ok(Self {
+                    $($field: Bake::read(reader)?,)*
+                })
+            }
+        }
+    };
+}
+
+/// Fieldless enums baked as the index of their variant.
+macro_rules! bake_enum {
+    ($ty:ty { $($variant:path),* $(,)? }) => {
+        impl Bake for $ty {
+            fn write(&self, out: &mut Vec<u8>) {
+                let variants = [$($variant),*];
+                (variants.iter().position(|i| i == self).unwrap_or_default() as u8).write(out);
+            }
+
+            fn read(reader: &mut Reader) -> Result<Self, Error> {
+                let variants = [$($variant),*];
+                variants
+                    .into_iter()
+                    .nth(reader.tag()? as usize)
+                    .context(InvalidDataSnafu)
+            }
+        }
+    };
+}
+
+impl Bake for usize {
+    fn write(&self, out: &mut Vec<u8>) {
+        (*self as u64).write(out);
+    }
+
+    fn read(reader: &mut Reader) -> Result<Self, Error> {
+        usize::try_from(u64::read(reader)?)
+            .ok()
+            .context(InvalidDataSnafu)
+    }
+}
+
+impl Bake for bool {
+    fn write(&self, out: &mut Vec<u8>) {
+        (*self as u8).write(out);
+    }
+
+    fn read(reader: &mut Reader) -> Result<Self, Error> {
+        match reader.tag()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => InvalidDataSnafu.fail(),
+        }
+    }
+}
+
+impl Bake for String {
+    fn write(&self, out: &mut Vec<u8>) {
+        self.len().write(out);
+        out.extend_from_slice(self.as_bytes());
+    }
+
+    fn read(reader: &mut Reader) -> Result<Self, Error> {
+        let len = usize::read(reader)?;
+        String::from_utf8(reader.take(len)?.to_vec())
+            .ok()
+            .context(InvalidDataSnafu)
+    }
+}
+
+impl<T: Bake> Bake for Option<T> {
+    fn write(&self, out: &mut Vec<u8>) {
+        self.is_some().write(out);
+        if let Some(value) = self {
+            value.write(out);
+        }
+    }
+
+    fn read(reader: &mut Reader) -> Result<Self, Error> {
+        Ok(match bool::read(reader)? {
+            true => Some(T::read(reader)?),
+            false => None,
+        })
+    }
+}
+
+/// Element by element, byte buffers go through [`write_bytes`] instead.
+impl<T: Bake> Bake for Vec<T> {
+    fn write(&self, out: &mut Vec<u8>) {
+        self.len().write(out);
+        self.iter().for_each(|i| i.write(out));
+    }
+
+    fn read(reader: &mut Reader) -> Result<Self, Error> {
+        let len = usize::read(reader)?;
+        // every element takes at least a byte, a bogus length fails before allocating
+        ensure!(len <= reader.bytes.len(), TruncatedSnafu);
+        (0..len).map(|_| T::read(reader)).collect()
+    }
+}
+
+impl<K: Bake + Ord, V: Bake> Bake for BTreeMap<K, V> {
+    fn write(&self, out: &mut Vec<u8>) {
+        self.len().write(out);
+        for (key, value) in self {
+            key.write(out);
+            value.write(out);
+        }
+    }
+
+    fn read(reader: &mut Reader) -> Result<Self, Error> {
+        Ok(Vec::<(K, V)>::read(reader)?.into_iter().collect())
+    }
+}
+
+impl<A: Bake, B: Bake> Bake for (A, B) {
+    fn write(&self, out: &mut Vec<u8>) {
+        self.0.write(out);
+        self.1.write(out);
+    }
+
+    fn read(reader: &mut Reader) -> Result<Self, Error> {
+        Ok((A::read(reader)?, B::read(reader)?))
+    }
+}
+
+impl<const N: usize> Bake for [f32; N] {
+    fn write(&self, out: &mut Vec<u8>) {
+        self.iter().for_each(|i| i.write(out));
+    }
+
+    fn read(reader: &mut Reader) -> Result<Self, Error> {
+        let mut array = [0.0; N];
+        for i in &mut array {
+            *i = f32::read(reader)?;
+        }
+        Ok(array)
+    }
+}
+
+impl Bake for Range<usize> {
+    fn write(&self, out: &mut Vec<u8>) {
+        self.start.write(out);
+        self.end.write(out);
+    }
+
+    fn read(reader: &mut Reader) -> Result<Self, Error> {
+        Ok(usize::read(reader)?..usize::read(reader)?)
+    }
+}
+
+impl Bake for Mat4 {
+    fn write(&self, out: &mut Vec<u8>) {
+        self.to_cols_array().write(out);
+    }
+
+    fn read(reader: &mut Reader) -> Result<Self, Error> {
+        Ok(Mat4::from_cols_array(&Bake::read(reader)?))
+    }
+}
+
+fn write_bytes(bytes: &[u8], out: &mut Vec<u8>) {
+    bytes.len().write(out);
+    out.extend_from_slice(bytes);
+}
+
+fn read_bytes(reader: &mut Reader) -> Result<Vec<u8>, Error> {
+    let len = usize::read(reader)?;
+    Ok(reader.take(len)?.to_vec())
+}
+
+impl Bake for GLTFBuffer {
+    fn write(&self, out: &mut Vec<u8>) {
+        write_bytes(&self.positions, out);
+        write_bytes(&self.tangent, out);
+        write_bytes(&self.normal, out);
+        self.texcoord.len().write(out);
+        self.texcoord.iter().for_each(|i| write_bytes(i, out));
+        write_bytes(&self.index, out);
+        write_bytes(&self.shared_data, out);
+        write_bytes(&self.bi_tangent, out);
+    }
+
+    fn read(reader: &mut Reader) -> Result<Self, Error> {
+        Ok(Self {
+            positions: read_bytes(reader)?,
+            tangent: read_bytes(reader)?,
+            normal: read_bytes(reader)?,
+            texcoord: (0..usize::read(reader)?)
+                .map(|_| read_bytes(reader))
+                .collect::<Result<_, _>>()?,
+            index: read_bytes(reader)?,
+            shared_data: read_bytes(reader)?,
+            bi_tangent: read_bytes(reader)?,
+        })
+    }
+}
+
+impl Bake for DecodedImage {
+    fn write(&self, out: &mut Vec<u8>) {
+        self.width.write(out);
+        self.height.write(out);
+        self.format.write(out);
+        self.mip_level_count.write(out);
+        write_bytes(&self.data, out);
+    }
+
+    fn read(reader: &mut Reader) -> Result<Self, Error> {
+        Ok(Self {
+            width: Bake::read(reader)?,
+            height: Bake::read(reader)?,
+            format: Bake::read(reader)?,
+            mip_level_count: Bake::read(reader)?,
+            data: read_bytes(reader)?,
+        })
+    }
+}
+
+bake_enum!(TextureFormat {
+    TextureFormat::Rgba8Unorm,
+    TextureFormat::Rgba8UnormSrgb,
+    TextureFormat::Rgba16Float,
+    TextureFormat::Bc1RgbaUnorm,
+    TextureFormat::Bc1RgbaUnormSrgb,
+    TextureFormat::Bc3RgbaUnorm,
+    TextureFormat::Bc3RgbaUnormSrgb,
+});
+
+bake_enum!(MaterialKey {
+    MaterialKey::BaseColor,
+    MaterialKey::MetallicRoughness,
+    MaterialKey::Normal,
+    MaterialKey::Emissive,
+    MaterialKey::Occlusion,
+});
+
+bake_enum!(AlphaMode {
+    AlphaMode::Opaque,
+    AlphaMode::Mask,
+    AlphaMode::Blend,
+});
+
+impl Bake for PrimitiveMode {
+    fn write(&self, out: &mut Vec<u8>) {
+        let tag: u8 = match self {
+            PrimitiveMode::Points => 0,
+            PrimitiveMode::Lines => 1,
+            PrimitiveMode::LineLoop => 2,
+            PrimitiveMode::LineStrip => 3,
+            PrimitiveMode::Triangles => 4,
+            PrimitiveMode::TriangleStrip => 5,
+            PrimitiveMode::TriangleFan => 6,
+        };
+        tag.write(out);
+    }
+
+    fn read(reader: &mut Reader) -> Result<Self, Error> {
+        Ok(match reader.tag()? {
+            0 => PrimitiveMode::Points,
+            1 => PrimitiveMode::Lines,
+            2 => PrimitiveMode::LineLoop,
+            3 => PrimitiveMode::LineStrip,
+            4 => PrimitiveMode::Triangles,
+            5 => PrimitiveMode::TriangleStrip,
+            6 => PrimitiveMode::TriangleFan,
+            _ => return InvalidDataSnafu.fail(),
+        })
+    }
+}
+
+impl Bake for FilterMode {
+    fn write(&self, out: &mut Vec<u8>) {
+        matches!(self, FilterMode::Linear).write(out);
+    }
+
+    fn read(reader: &mut Reader) -> Result<Self, Error> {
+        Ok(match bool::read(reader)? {
+            true => FilterMode::Linear,
+            false => FilterMode::Nearest,
+        })
+    }
+}
+
+impl Bake for SamplerWrap {
+    fn write(&self, out: &mut Vec<u8>) {
+        let tag: u8 = match self {
+            SamplerWrap::ClampToEdge => 0,
+            SamplerWrap::MirroredRepeat => 1,
+            SamplerWrap::Repeat => 2,
+        };
+        tag.write(out);
+    }
+
+    fn read(reader: &mut Reader) -> Result<Self, Error> {
+        Ok(match reader.tag()? {
+            0 => SamplerWrap::ClampToEdge,
+            1 => SamplerWrap::MirroredRepeat,
+            2 => SamplerWrap::Repeat,
+            _ => return InvalidDataSnafu.fail(),
+        })
+    }
+}
+
+impl Bake for CameraProjection {
+    fn write(&self, out: &mut Vec<u8>) {
+        match *self {
+            CameraProjection::Perspective {
+                yfov,
+                aspect_ratio,
+                znear,
+                zfar,
+            } => {
+                0u8.write(out);
+                (yfov, aspect_ratio).write(out);
+                (znear, zfar).write(out);
+            }
+            CameraProjection::Orthographic {
+                xmag,
+                ymag,
+                znear,
+                zfar,
+            } => {
+                1u8.write(out);
+                [xmag, ymag, znear, zfar].write(out);
+            }
+        }
+    }
+
+    fn read(reader: &mut Reader) -> Result<Self, Error> {
+        Ok(match reader.tag()? {
+            0 => {
+                let (yfov, aspect_ratio) = Bake::read(reader)?;
+                let (znear, zfar) = Bake::read(reader)?;
+                CameraProjection::Perspective {
+                    yfov,
+                    aspect_ratio,
+                    znear,
+                    zfar,
+                }
+            }
+            1 => {
+                let [xmag, ymag, znear, zfar] = Bake::read(reader)?;
+                CameraProjection::Orthographic {
+                    xmag,
+                    ymag,
+                    znear,
+                    zfar,
+                }
+            }
+            _ => return InvalidDataSnafu.fail(),
+        })
+    }
+}
+
+bake_struct!(MinFilter { mode, mipmap });
+bake_struct!(Sampler {
+    mag_filter,
+    min_filter,
+    wrap_s,
+    wrap_t,
+});
+bake_struct!(SceneView {
+    nodes,
+    meshes,
+    materials,
+    images,
+    samplers,
+    variants,
+    scene_id,
+    cameras,
+});
+bake_struct!(SceneCamera {
+    node_id,
+    name,
+    projection,
+    transform,
+});
+bake_struct!(PerNodeBuffer { transform });
+bake_struct!(Node {
+    id,
+    name,
+    meshes,
+    per_node_info,
+    instances,
+    children,
+});
+bake_struct!(Mesh {
+    id,
+    index,
+    vertex_count,
+    vertex_type_size,
+    positions,
+    normals,
+    tangents,
+    bi_tangents,
+    uv0,
+    uv1,
+    mode,
+    mat,
+    variant_materials,
+});
+bake_struct!(Index {
+    indices,
+    count,
+    type_size,
+});
+bake_struct!(Material {
+    textures,
+    alpha_mode,
+    alpha_cutoff,
+    double_sided,
+    unlit,
+    ior,
+    clearcoat,
+    sheen,
+    transmission,
+    specular,
+    volume,
+});
+bake_struct!(Clearcoat {
+    factor,
+    texture,
+    roughness_factor,
+    roughness_texture,
+    normal_texture,
+});
+bake_struct!(Sheen {
+    color_factor,
+    color_texture,
+    roughness_factor,
+    roughness_texture,
+});
+bake_struct!(Transmission { factor, texture });
+bake_struct!(Specular {
+    factor,
+    texture,
+    color_factor,
+    color_texture,
+});
+bake_struct!(Volume {
+    thickness_factor,
+    thickness_texture,
+    attenuation_distance,
+    attenuation_color,
+});
+bake_struct!(TextureData {
+    image_id,
+    factor,
+    tex_coord,
+    sampler,
+    scale,
+    strength,
+    transform,
+});
+bake_struct!(TextureTransform {
+    offset,
+    rotation,
+    scale,
+});
+bake_struct!(ImageData {
+    name,
+    range,
+    mime,
+    target_format,
+    decoded,
+});
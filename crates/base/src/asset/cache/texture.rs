@@ -0,0 +1,333 @@
+//! Mip chains and block compression of decoded images at bake time.
+
+use half::f16;
+use wgpu::TextureFormat;
+
+use crate::asset::gltf::DecodedImage;
+
+/// Append every level below the first, box filtered in linear space.
+pub(super) fn generate_mips(image: &mut DecodedImage) {
+    if image.mip_level_count > 1 {
+        return;
+    }
+    let Some(pixel_format) = PixelFormat::of(image.format) else {
+        return;
+    };
+    let (mut width, mut height) = (image.width as usize, image.height as usize);
+    let mut level = pixel_format.unpack(&image.data);
+    while width > 1 || height > 1 {
+        let (next_width, next_height) = ((width / 2).max(1), (height / 2).max(1));
+        let mut next = Vec::with_capacity(next_width * next_height);
+        for y in 0..next_height {
+            for x in 0..next_width {
+                let sample = |dx: usize, dy: usize| {
+                    let sx = (x * 2 + dx).min(width - 1);
+                    let sy = (y * 2 + dy).min(height - 1);
+                    level[sy * width + sx]
+                };
+                let texels = [sample(0, 0), sample(1, 0), sample(0, 1), sample(1, 1)];
+                next.push([0, 1, 2, 3].map(|c| texels.iter().map(|t| t[c]).sum::<f32>() / 4.0));
+            }
+        }
+        pixel_format.pack(&next, &mut image.data);
+        image.mip_level_count += 1;
+        (width, height, level) = (next_width, next_height, next);
+    }
+}
+
+/// BC1, or BC3 when any texel is translucent, for RGBA8 images with a size divisible by 4.
+pub(super) fn compress(image: &mut DecodedImage) {
+    let bc_format = match image.format {
+        TextureFormat::Rgba8Unorm | TextureFormat::Rgba8UnormSrgb => image.format,
+        _ => return,
+    };
+    if (image.width | image.height) & 3 != 0 {
+        return;
+    }
+    let translucent = image.data.chunks_exact(4).any(|texel| texel[3] != 255);
+    let srgb = bc_format.is_srgb();
+    image.format = match (translucent, srgb) {
+        (false, false) => TextureFormat::Bc1RgbaUnorm,
+        (false, true) => TextureFormat::Bc1RgbaUnormSrgb,
+        (true, false) => TextureFormat::Bc3RgbaUnorm,
+        (true, true) => TextureFormat::Bc3RgbaUnormSrgb,
+    };
+
+    let mut compressed = vec![];
+    let mut level_start = 0;
+    for level in 0..image.mip_level_count {
+        let width = (image.width >> level).max(1) as usize;
+        let height = (image.height >> level).max(1) as usize;
+        let texels = &image.data[level_start..level_start + width * height * 4];
+        level_start += width * height * 4;
+        for block_y in (0..height).step_by(4) {
+            for block_x in (0..width).step_by(4) {
+                // levels below 4x4 repeat their edge texels to fill the block
+                let block: [[u8; 4]; 16] = std::array::from_fn(|i| {
+                    let x = (block_x + i % 4).min(width - 1);
+                    let y = (block_y + i / 4).min(height - 1);
+                    let offset = (y * width + x) * 4;
+                    [0, 1, 2, 3].map(|c| texels[offset + c])
+                });
+                if translucent {
+                    alpha_block(&block, &mut compressed);
+                }
+                color_block(&block, &mut compressed);
+            }
+        }
+    }
+    image.data = compressed;
+}
+
+#[derive(Clone, Copy)]
+enum PixelFormat {
+    Rgba8,
+    Rgba8Srgb,
+    Rgba16Float,
+}
+
+impl PixelFormat {
+    fn of(format: TextureFormat) -> Option<Self> {
+        match format {
+            TextureFormat::Rgba8Unorm => Some(Self::Rgba8),
+            TextureFormat::Rgba8UnormSrgb => Some(Self::Rgba8Srgb),
+            TextureFormat::Rgba16Float => Some(Self::Rgba16Float),
+            _ => None,
+        }
+    }
+
+    fn unpack(self, data: &[u8]) -> Vec<[f32; 4]> {
+        match self {
+            Self::Rgba8 => data
+                .chunks_exact(4)
+                .map(|t| [0, 1, 2, 3].map(|c| t[c] as f32 / 255.0))
+                .collect(),
+            Self::Rgba8Srgb => data
+                .chunks_exact(4)
+                .map(|t| {
+                    let c = |c: usize| srgb_to_linear(t[c] as f32 / 255.0);
+                    [c(0), c(1), c(2), t[3] as f32 / 255.0]
+                })
+                .collect(),
+            Self::Rgba16Float => bytemuck::cast_slice::<u8, f16>(data)
+                .chunks_exact(4)
+                .map(|t| [0, 1, 2, 3].map(|c| t[c].to_f32()))
+                .collect(),
+        }
+    }
+
+    fn pack(self, texels: &[[f32; 4]], out: &mut Vec<u8>) {
+        let unorm = |v: f32| (v.clamp(0.0, 1.0) * 255.0).round() as u8;
+        for texel in texels {
+            match self {
+                Self::Rgba8 => out.extend(texel.map(unorm)),
+                Self::Rgba8Srgb => {
+                    let [r, g, b, a] = *texel;
+                    out.extend([r, g, b].map(|c| unorm(linear_to_srgb(c))));
+                    out.push(unorm(a));
+                }
+                Self::Rgba16Float => {
+                    out.extend(texel.iter().flat_map(|c| f16::from_f32(*c).to_le_bytes()))
+                }
+            }
+        }
+    }
+}
+
+fn srgb_to_linear(c: f32) -> f32 {
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn linear_to_srgb(c: f32) -> f32 {
+    if c <= 0.0031308 {
+        c * 12.92
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+/// BC1 block from the color bounding box, always in 4 color mode.
+fn color_block(block: &[[u8; 4]; 16], out: &mut Vec<u8>) {
+    let mut min = [255u8; 3];
+    let mut max = [0u8; 3];
+    for texel in block {
+        for c in 0..3 {
+            min[c] = min[c].min(texel[c]);
+            max[c] = max[c].max(texel[c]);
+        }
+    }
+    let to_565 =
+        |c: [u8; 3]| ((c[0] as u16 >> 3) << 11) | ((c[1] as u16 >> 2) << 5) | (c[2] as u16 >> 3);
+    let from_565 = |c: u16| {
+        let r = (c >> 11) & 31;
+        let g = (c >> 5) & 63;
+        let b = c & 31;
+        [
+            (r << 3) | (r >> 2),
+            (g << 2) | (g >> 4),
+            (b << 3) | (b >> 2),
+        ]
+        .map(|i| i as i32)
+    };
+    let (c0, c1) = (to_565(max), to_565(min));
+    let mut indices = 0u32;
+    if c0 > c1 {
+        let (e0, e1) = (from_565(c0), from_565(c1));
+        let palette: [[i32; 3]; 4] = [
+            e0,
+            e1,
+            [0, 1, 2].map(|c| (2 * e0[c] + e1[c]) / 3),
+            [0, 1, 2].map(|c| (e0[c] + 2 * e1[c]) / 3),
+        ];
+        for (i, texel) in block.iter().enumerate() {
+            indices |= nearest(&palette, |p| {
+                (0..3).map(|c| (p[c] - texel[c] as i32).pow(2)).sum()
+            }) << (i * 2);
+        }
+    }
+    out.extend(c0.to_le_bytes());
+    out.extend(c1.to_le_bytes());
+    out.extend(indices.to_le_bytes());
+}
+
+/// BC4 style alpha block of BC3, always in 8 value mode.
+fn alpha_block(block: &[[u8; 4]; 16], out: &mut Vec<u8>) {
+    let a0 = block.iter().map(|t| t[3]).max().unwrap_or(255);
+    let a1 = block.iter().map(|t| t[3]).min().unwrap_or(255);
+    let mut indices = 0u64;
+    if a0 > a1 {
+        let (a0, a1) = (a0 as i32, a1 as i32);
+        let palette: [i32; 8] = std::array::from_fn(|i| match i {
+            0 => a0,
+            1 => a1,
+            i => ((8 - i as i32) * a0 + (i as i32 - 1) * a1) / 7,
+        });
+        for (i, texel) in block.iter().enumerate() {
+            let index = nearest(&palette, |p| (p - texel[3] as i32).abs());
+            indices |= (index as u64) << (i * 3);
+        }
+    }
+    out.extend([a0, a1]);
+    out.extend(&indices.to_le_bytes()[..6]);
+}
+
+fn nearest<T>(palette: &[T], distance: impl Fn(&T) -> i32) -> u32 {
+    (0..palette.len())
+        .min_by_key(|i| distance(&palette[*i]))
+        .unwrap_or_default() as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn image(
+        width: u32,
+        height: u32,
+        format: TextureFormat,
+        texel: impl Fn(u32, u32) -> [u8; 4],
+    ) -> DecodedImage {
+        let data = (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .flat_map(|(x, y)| texel(x, y))
+            .collect();
+        DecodedImage {
+            width,
+            height,
+            format,
+            mip_level_count: 1,
+            data,
+        }
+    }
+
+    #[test]
+    fn solid_block_is_one_endpoint() {
+        let mut red = image(4, 4, TextureFormat::Rgba8Unorm, |_, _| [255, 0, 0, 255]);
+        compress(&mut red);
+        assert_eq!(red.format, TextureFormat::Bc1RgbaUnorm);
+        assert_eq!(red.data, [0x00, 0xf8, 0x00, 0xf8, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn opaque_block_is_bc1() {
+        // left half white, right half black, texel 0 sits in the lowest bits of each row
+        let mut split = image(4, 4, TextureFormat::Rgba8UnormSrgb, |x, _| {
+            if x < 2 {
+                [255; 4]
+            } else {
+                [0, 0, 0, 255]
+            }
+        });
+        compress(&mut split);
+        assert_eq!(split.format, TextureFormat::Bc1RgbaUnormSrgb);
+        assert_eq!(split.data, [0xff, 0xff, 0x00, 0x00, 0x50, 0x50, 0x50, 0x50]);
+    }
+
+    #[test]
+    fn translucent_block_is_bc3() {
+        let mut fade = image(4, 4, TextureFormat::Rgba8UnormSrgb, |x, _| {
+            [255, 255, 255, if x < 2 { 255 } else { 0 }]
+        });
+        compress(&mut fade);
+        assert_eq!(fade.format, TextureFormat::Bc3RgbaUnormSrgb);
+        // alpha endpoints and 3 bit indices, 0b001_001_000_000 for every row
+        let alpha = [255, 0, 0x40, 0x02, 0x24, 0x40, 0x02, 0x24];
+        // a single white endpoint
+        let color = [0xff, 0xff, 0xff, 0xff, 0, 0, 0, 0];
+        assert_eq!(fade.data, [alpha, color].concat());
+    }
+
+    #[test]
+    fn compress_skips_unaligned_and_float_images() {
+        let mut unaligned = image(6, 4, TextureFormat::Rgba8Unorm, |_, _| [0; 4]);
+        compress(&mut unaligned);
+        assert_eq!(unaligned.format, TextureFormat::Rgba8Unorm);
+        assert_eq!(unaligned.data.len(), 6 * 4 * 4);
+
+        let mut float = image(4, 2, TextureFormat::Rgba16Float, |_, _| [0; 4]);
+        compress(&mut float);
+        assert_eq!(float.format, TextureFormat::Rgba16Float);
+    }
+
+    #[test]
+    fn every_level_is_compressed() {
+        let mut checker = image(8, 8, TextureFormat::Rgba8Unorm, |x, y| {
+            [((x + y) % 2 * 255) as u8, 0, 0, 255]
+        });
+        generate_mips(&mut checker);
+        assert_eq!(checker.mip_level_count, 4);
+        compress(&mut checker);
+        // 2x2 blocks at 8x8, then one block for each of 4x4, 2x2 and 1x1
+        assert_eq!(checker.data.len(), (4 + 1 + 1 + 1) * 8);
+    }
+
+    #[test]
+    fn mips_average_in_linear_space() {
+        let texel = |x, _| if x == 0 { [0, 0, 0, 0] } else { [255; 4] };
+        let mut linear = image(2, 2, TextureFormat::Rgba8Unorm, texel);
+        generate_mips(&mut linear);
+        assert_eq!(linear.mip_level_count, 2);
+        assert_eq!(linear.data[16..], [128; 4]);
+
+        // half black and half white is 0.5 linear, alpha is never sRGB encoded
+        let mut srgb = image(2, 2, TextureFormat::Rgba8UnormSrgb, texel);
+        generate_mips(&mut srgb);
+        assert_eq!(srgb.data[16..], [188, 188, 188, 128]);
+    }
+
+    #[test]
+    fn mips_stop_at_one_texel() {
+        let mut wide = image(4, 1, TextureFormat::Rgba8Unorm, |_, _| [7; 4]);
+        generate_mips(&mut wide);
+        assert_eq!(wide.mip_level_count, 3);
+        assert_eq!(wide.data, [7; (4 + 2 + 1) * 4]);
+
+        // already mipmapped images are kept
+        generate_mips(&mut wide);
+        assert_eq!(wide.mip_level_count, 3);
+    }
+}
@@ -1,15 +1,34 @@
+use std::borrow::Cow;
 use std::ops::Range;
+use std::path::PathBuf;
+use std::sync::mpsc;
+use std::thread;
 use std::{collections::BTreeMap, path::Path};
 
 use base64::{DecodeError, Engine};
-use glam::{Mat4, Quat, Vec3};
+use glam::{Mat3, Mat4, Quat, Vec2, Vec3};
 use goth_gltf::{
     default_extensions, ComponentType, Gltf, NodeTransform, NormalTextureInfo,
     OcclusionTextureInfo, PrimitiveMode, Sampler, TextureInfo,
 };
+use half::f16;
+use image::ImageError;
+use rayon::prelude::*;
 use snafu::{Backtrace, OptionExt, ResultExt, Snafu};
 use wgpu::TextureFormat;
 
+#[cfg(feature = "draco")]
+mod draco;
+mod ext;
+mod source;
+
+#[cfg(feature = "draco")]
+use draco::read_draco_primitive;
+use ext::GltfExt;
+pub use source::GltfSource;
+
+use super::{cache, ktx2};
+
 pub trait SInto<T>: Sized {
     fn t_into(self) -> T;
 }
@@ -56,6 +75,26 @@ pub enum Error {
     UTF8Err {
         source: std::string::FromUtf8Error,
     },
+    DracoDecodeFailed {
+        mesh_id: usize,
+    },
+    /// The decoded streams don't add up to the accessors of the primitive
+    DracoOutputSize {
+        mesh_id: usize,
+        expected: usize,
+        decoded: usize,
+    },
+    DracoNotEnabled {
+        mesh_id: usize,
+    },
+    /// `EXT_mesh_gpu_instancing` attributes are only read as floats
+    InstanceAttributeType {
+        node_id: usize,
+    },
+    ImageDecodeFailed {
+        image_id: usize,
+        source: ImageError,
+    },
 
     FailedToGetU8Data,
 }
@@ -63,9 +102,82 @@ pub enum Error {
 #[derive(Debug, Default)]
 pub struct SceneView {
     pub nodes: BTreeMap<usize, Node>,
+    /// Every primitive once, however many nodes use it
+    pub meshes: BTreeMap<MeshKey, Mesh>,
     pub materials: Vec<Material>,
     pub images: Vec<ImageData>,
     pub samplers: Vec<Sampler>,
+    /// `KHR_materials_variants` names, a variant is referred to by its index here
+    pub variants: Vec<String>,
+    /// Index of the loaded glTF scene
+    pub scene_id: usize,
+    pub cameras: Vec<SceneCamera>,
+}
+
+/// A camera node of the loaded scene.
+#[derive(Debug, Clone)]
+pub struct SceneCamera {
+    pub node_id: usize,
+    pub name: Option<String>,
+    pub projection: CameraProjection,
+    /// World transform of the node, the camera looks down its -Z axis with +Y up
+    pub transform: Mat4,
+}
+
+/// glTF camera projection, angles in radians.
+#[derive(Debug, Clone, Copy)]
+pub enum CameraProjection {
+    Perspective {
+        yfov: f32,
+        aspect_ratio: Option<f32>,
+        znear: f32,
+        /// Infinite projection when `None`
+        zfar: Option<f32>,
+    },
+    Orthographic {
+        xmag: f32,
+        ymag: f32,
+        znear: f32,
+        zfar: f32,
+    },
+}
+
+impl From<&ext::CameraExt> for CameraProjection {
+    fn from(value: &ext::CameraExt) -> Self {
+        match (&value.perspective, &value.orthographic) {
+            (Some(i), _) => Self::Perspective {
+                yfov: i.yfov,
+                aspect_ratio: i.aspect_ratio,
+                znear: i.znear,
+                zfar: i.zfar,
+            },
+            (None, Some(i)) => Self::Orthographic {
+                xmag: i.xmag,
+                ymag: i.ymag,
+                znear: i.znear,
+                zfar: i.zfar,
+            },
+            // Neither is set in a valid file, keep the spec's example perspective
+            (None, None) => Self::Perspective {
+                yfov: 0.66,
+                aspect_ratio: None,
+                znear: 0.01,
+                zfar: None,
+            },
+        }
+    }
+}
+
+impl SceneView {
+    /// The primitives drawn by `node`.
+    pub fn node_meshes<'a>(&'a self, node: &'a Node) -> impl Iterator<Item = &'a Mesh> {
+        node.meshes.iter().filter_map(|i| self.meshes.get(i))
+    }
+
+    /// Index of the material variant called `name`.
+    pub fn variant(&self, name: &str) -> Option<usize> {
+        self.variants.iter().position(|i| i == name)
+    }
 }
 
 #[derive(Debug, Default, Clone)]
@@ -79,6 +191,25 @@ pub struct GLTFBuffer {
     pub bi_tangent: Vec<u8>,
 }
 
+impl GLTFBuffer {
+    /// Stream of `TEXCOORD_<set>`.
+    fn uv_set(&mut self, set: usize) -> &mut Vec<u8> {
+        if self.texcoord.len() <= set {
+            self.texcoord.resize(set + 1, Default::default());
+        }
+        &mut self.texcoord[set]
+    }
+
+    /// Zeros for a primitive without `TEXCOORD_<set>`, keeping the stream aligned with
+    /// positions.
+    fn zeroed_uv(&mut self, set: usize, vertex_count: usize) -> Range<usize> {
+        let tex_buffer = self.uv_set(set);
+        let start = tex_buffer.len();
+        tex_buffer.resize(start + vertex_count * 8, 0);
+        start..tex_buffer.len()
+    }
+}
+
 #[repr(C)]
 #[derive(Copy, Clone, Debug, Default, bytemuck::Pod, bytemuck::Zeroable)]
 pub struct PerNodeBuffer {
@@ -89,11 +220,28 @@ pub struct PerNodeBuffer {
 pub struct Node {
     pub id: usize,
     pub name: Option<String>,
-    pub meshes: Vec<Mesh>,
+    /// Keys into [`SceneView::meshes`]
+    pub meshes: Vec<MeshKey>,
     pub per_node_info: PerNodeBuffer,
+    /// `EXT_mesh_gpu_instancing` transforms relative to the node, empty when drawn once
+    pub instances: Vec<Mat4>,
     pub children: Vec<usize>,
 }
 
+impl Node {
+    /// World transform of every instance of the node's meshes.
+    pub fn instance_transforms(&self) -> Vec<Mat4> {
+        let transform = self.per_node_info.transform;
+        if self.instances.is_empty() {
+            return vec![transform];
+        }
+        self.instances.iter().map(|i| transform * *i).collect()
+    }
+}
+
+/// glTF mesh id and primitive index of a [`Mesh`].
+pub type MeshKey = (usize, usize);
+
 #[derive(Debug, Default)]
 pub struct Mesh {
     pub id: usize,
@@ -105,8 +253,12 @@ pub struct Mesh {
     pub tangents: Option<Range<usize>>,
     pub bi_tangents: Option<Range<usize>>,
     pub uv0: Option<Range<usize>>,
+    /// `TEXCOORD_1`, in `GLTFBuffer::texcoord[1]`
+    pub uv1: Option<Range<usize>>,
     pub mode: PrimitiveMode,
     pub mat: Option<usize>,
+    /// Variant index to material index, variants missing here use `mat`
+    pub variant_materials: BTreeMap<usize, usize>,
 }
 
 #[derive(Debug, Default)]
@@ -168,8 +320,12 @@ impl<'a> SFrom<&goth_gltf::Sampler> for wgpu::SamplerDescriptor<'a> {
                 .map(|i| i.mipmap.as_ref().unwrap_or(&goth_gltf::FilterMode::Linear))
                 .unwrap_or(&goth_gltf::FilterMode::Linear))
             .t_into(),
+            // NEAREST and LINEAR min filters don't use mips at all
+            lod_max_clamp: match val.min_filter {
+                Some(goth_gltf::MinFilter { mipmap: None, .. }) => 0.0,
+                _ => 32.0,
+            },
             // lod_min_clamp: todo!(),
-            // lod_max_clamp: todo!(),
             // compare: todo!(),
             // anisotropy_clamp: todo!(),
             // border_color: todo!(),
@@ -197,7 +353,117 @@ impl SFrom<&goth_gltf::FilterMode> for wgpu::FilterMode {
     }
 }
 
-pub type Material = BTreeMap<MaterialKey, TextureData>;
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AlphaMode {
+    #[default]
+    Opaque,
+    /// Fragments with alpha below `alpha_cutoff` are discarded
+    Mask,
+    /// Drawn after the opaque geometry with alpha blending
+    Blend,
+}
+
+impl SFrom<&goth_gltf::AlphaMode> for AlphaMode {
+    fn f_from(value: &goth_gltf::AlphaMode) -> Self {
+        match value {
+            goth_gltf::AlphaMode::Opaque => Self::Opaque,
+            goth_gltf::AlphaMode::Mask => Self::Mask,
+            goth_gltf::AlphaMode::Blend => Self::Blend,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Material {
+    pub textures: BTreeMap<MaterialKey, TextureData>,
+    pub alpha_mode: AlphaMode,
+    pub alpha_cutoff: f32,
+    pub double_sided: bool,
+    /// `KHR_materials_unlit`, shaded with its base color only
+    pub unlit: bool,
+    /// `KHR_materials_ior`, 1.5 when absent
+    pub ior: f32,
+    pub clearcoat: Option<Clearcoat>,
+    pub sheen: Option<Sheen>,
+    pub transmission: Option<Transmission>,
+    pub specular: Option<Specular>,
+    pub volume: Option<Volume>,
+}
+
+impl Default for Material {
+    fn default() -> Self {
+        Self {
+            textures: Default::default(),
+            alpha_mode: Default::default(),
+            alpha_cutoff: 0.5,
+            double_sided: false,
+            unlit: false,
+            ior: 1.5,
+            clearcoat: None,
+            sheen: None,
+            transmission: None,
+            specular: None,
+            volume: None,
+        }
+    }
+}
+
+/// `KHR_materials_clearcoat`
+#[derive(Debug)]
+pub struct Clearcoat {
+    pub factor: f32,
+    /// Red channel
+    pub texture: Option<TextureData>,
+    pub roughness_factor: f32,
+    /// Green channel
+    pub roughness_texture: Option<TextureData>,
+    pub normal_texture: Option<TextureData>,
+}
+
+/// `KHR_materials_sheen`
+#[derive(Debug)]
+pub struct Sheen {
+    pub color_factor: [f32; 3],
+    pub color_texture: Option<TextureData>,
+    pub roughness_factor: f32,
+    /// Alpha channel
+    pub roughness_texture: Option<TextureData>,
+}
+
+/// `KHR_materials_transmission`
+#[derive(Debug)]
+pub struct Transmission {
+    pub factor: f32,
+    /// Red channel
+    pub texture: Option<TextureData>,
+}
+
+/// `KHR_materials_specular`
+#[derive(Debug)]
+pub struct Specular {
+    pub factor: f32,
+    /// Alpha channel
+    pub texture: Option<TextureData>,
+    pub color_factor: [f32; 3],
+    pub color_texture: Option<TextureData>,
+}
+
+/// `KHR_materials_volume`
+#[derive(Debug)]
+pub struct Volume {
+    pub thickness_factor: f32,
+    /// Green channel
+    pub thickness_texture: Option<TextureData>,
+    /// Infinity when the medium doesn't absorb
+    pub attenuation_distance: f32,
+    pub attenuation_color: [f32; 3],
+}
+
+impl Material {
+    pub fn get(&self, key: &MaterialKey) -> Option<&TextureData> {
+        self.textures.get(key)
+    }
+}
 
 #[derive(Hash, PartialEq, Eq, Debug, PartialOrd, Ord)]
 pub enum MaterialKey {
@@ -206,7 +472,6 @@ pub enum MaterialKey {
     Normal,
     Emissive,
     Occlusion,
-    Other(String),
 }
 
 #[derive(Debug)]
@@ -215,6 +480,32 @@ pub struct ImageData {
     pub range: Range<usize>,
     pub mime: String,
     pub target_format: TextureFormat,
+    /// Decoded pixels, `None` for KTX2 images which are transcoded at upload
+    pub decoded: Option<DecodedImage>,
+}
+
+/// Pixels of an image, decoded once however many textures use it.
+#[derive(Debug)]
+pub struct DecodedImage {
+    pub width: u32,
+    pub height: u32,
+    /// `Rgba16Float` for images with more than 8 bits per channel, linear even when
+    /// the image is sRGB. `target_format` of the image otherwise, or a BC format from a baked cache.
+    pub format: TextureFormat,
+    /// Levels stored in `data` one after another, the upload generates the rest when it is 1
+    pub mip_level_count: u32,
+    pub data: Vec<u8>,
+}
+
+/// Progress of [`load_gltf_with_progress`].
+#[derive(Debug, Clone, Copy)]
+pub enum LoadProgress {
+    /// Bytes of the glTF, its buffers and its image files read so far
+    BytesRead(u64),
+    ImageDecoded {
+        decoded: usize,
+        total: usize,
+    },
 }
 
 #[derive(Debug, Default)]
@@ -223,205 +514,388 @@ pub struct TextureData {
     pub factor: [f32; 4],
     pub tex_coord: usize,
     pub sampler: usize,
-    //TODO scale,etc
+    /// `scale` of a normal texture
+    pub scale: Option<f32>,
+    /// `strength` of an occlusion texture
+    pub strength: Option<f32>,
+    /// `KHR_texture_transform`, identity when the extension is absent
+    pub transform: TextureTransform,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TextureTransform {
+    pub offset: [f32; 2],
+    /// Radians, counter-clockwise in UV space
+    pub rotation: f32,
+    pub scale: [f32; 2],
+}
+
+impl Default for TextureTransform {
+    fn default() -> Self {
+        Self {
+            offset: [0.0; 2],
+            rotation: 0.0,
+            scale: [1.0; 2],
+        }
+    }
+}
+
+impl TextureTransform {
+    /// `translation * rotation * scale`, applied to `vec3(uv, 1.0)`.
+    pub fn matrix(&self) -> Mat3 {
+        Mat3::from_translation(Vec2::from(self.offset))
+            * Mat3::from_angle(-self.rotation)
+            * Mat3::from_scale(Vec2::from(self.scale))
+    }
+}
+
+impl From<&goth_gltf::extensions::KhrTextureTransform> for TextureTransform {
+    fn from(value: &goth_gltf::extensions::KhrTextureTransform) -> Self {
+        Self {
+            offset: value.offset,
+            rotation: value.rotation,
+            scale: value.scale,
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
-struct SuperTextureInfo<E: goth_gltf::Extensions> {
+struct SuperTextureInfo {
     pub index: usize,
     pub tex_coord: usize,
     pub scale: Option<f32>,
     pub strength: Option<f32>,
-    pub extensions: E::TextureInfoExtensions,
+    pub transform: Option<TextureTransform>,
 }
 
-impl<E: goth_gltf::Extensions> From<&TextureInfo<E>> for SuperTextureInfo<E> {
-    fn from(value: &TextureInfo<E>) -> Self {
+type TextureInfoExtensions =
+    <default_extensions::Extensions as goth_gltf::Extensions>::TextureInfoExtensions;
+
+impl SuperTextureInfo {
+    fn new(index: usize, tex_coord: usize, extensions: &TextureInfoExtensions) -> Self {
+        let transform = extensions.khr_texture_transform.as_ref();
         Self {
-            index: value.index,
-            tex_coord: value.tex_coord,
+            index,
+            // the transform may point the texture at another uv set
+            tex_coord: transform.and_then(|i| i.tex_coord).unwrap_or(tex_coord),
             scale: None,
             strength: None,
-            extensions: value.extensions.clone(),
+            transform: transform.map(Into::into),
         }
     }
 }
 
-impl<E: goth_gltf::Extensions> From<&NormalTextureInfo<E>> for SuperTextureInfo<E> {
-    fn from(value: &NormalTextureInfo<E>) -> Self {
+impl From<&TextureInfo<default_extensions::Extensions>> for SuperTextureInfo {
+    fn from(value: &TextureInfo<default_extensions::Extensions>) -> Self {
+        Self::new(value.index, value.tex_coord, &value.extensions)
+    }
+}
+
+impl From<&NormalTextureInfo<default_extensions::Extensions>> for SuperTextureInfo {
+    fn from(value: &NormalTextureInfo<default_extensions::Extensions>) -> Self {
         Self {
-            index: value.index,
-            tex_coord: value.tex_coord,
             scale: Some(value.scale),
-            strength: None,
-            extensions: value.extensions.clone(),
+            ..Self::new(value.index, value.tex_coord, &value.extensions)
         }
     }
 }
 
-impl<E: goth_gltf::Extensions> From<&OcclusionTextureInfo<E>> for SuperTextureInfo<E> {
-    fn from(value: &OcclusionTextureInfo<E>) -> Self {
+impl From<&OcclusionTextureInfo<default_extensions::Extensions>> for SuperTextureInfo {
+    fn from(value: &OcclusionTextureInfo<default_extensions::Extensions>) -> Self {
         Self {
-            index: value.index,
-            tex_coord: value.tex_coord,
-            scale: None,
             strength: Some(value.strength),
-            extensions: value.extensions.clone(),
+            ..Self::new(value.index, value.tex_coord, &value.extensions)
         }
     }
 }
 
-struct ImageLoader<'a, E: goth_gltf::Extensions, P: AsRef<Path>> {
-    gltf_info: &'a Gltf<E>,
+impl From<&ext::TextureInfoExt> for SuperTextureInfo {
+    fn from(value: &ext::TextureInfoExt) -> Self {
+        let transform = value.extensions.khr_texture_transform.as_ref();
+        Self {
+            index: value.index,
+            tex_coord: transform
+                .and_then(|i| i.tex_coord)
+                .or(value.tex_coord)
+                .unwrap_or_default(),
+            scale: value.scale,
+            strength: None,
+            transform: transform.map(|i| TextureTransform {
+                offset: i.offset.unwrap_or([0.0; 2]),
+                rotation: i.rotation.unwrap_or_default(),
+                scale: i.scale.unwrap_or([1.0; 2]),
+            }),
+        }
+    }
+}
+
+struct ImageLoader<'a, P: AsRef<Path>> {
+    gltf_info: &'a Gltf<default_extensions::Extensions>,
     path: &'a P,
-    buffer_map: &'a BTreeMap<usize, &'a [u8]>,
+    source: &'a GltfSource,
     buffer_out: &'a mut Vec<u8>,
     image_out: &'a mut Vec<ImageData>,
     // tex_range_map: BTreeMap<usize, Range<usize>>,
 }
-impl<'a, E: goth_gltf::Extensions, P: AsRef<Path>> ImageLoader<'a, E, P> {
+impl<'a, P: AsRef<Path>> ImageLoader<'a, P> {
     fn new(
-        gltf_info: &'a Gltf<E>,
+        gltf_info: &'a Gltf<default_extensions::Extensions>,
         path: &'a P,
-        buffer_map: &'a BTreeMap<usize, &'a [u8]>,
+        source: &'a GltfSource,
         buffer_out: &'a mut Vec<u8>,
         image_out: &'a mut Vec<ImageData>,
     ) -> Self {
         Self {
             gltf_info,
             path,
-            buffer_map,
+            source,
             buffer_out,
             image_out,
         }
     }
 
+    /// `srgb` is false for textures holding linear data.
+    fn texture_data(
+        &mut self,
+        texture_info: &SuperTextureInfo,
+        color_factor: [f32; 4],
+        srgb: bool,
+    ) -> TextureData {
+        let texture = &self.gltf_info.textures[texture_info.index];
+        // KHR_texture_basisu may leave `source` empty or pointing at a png fallback
+        let source = texture
+            .extensions
+            .khr_texture_basisu
+            .as_ref()
+            .map(|i| i.source)
+            .or(texture.source);
+        //HACK insert image target format, ktx2 only uses it when the dfd has no transfer function
+        if !srgb {
+            if let Some(id) = source {
+                self.image_out[id].target_format = wgpu::TextureFormat::Rgba8Unorm;
+            }
+        }
+
+        if texture_info.tex_coord > 1 {
+            log::warn!(
+                "Texture {} reads TEXCOORD_{}, only TEXCOORD_0 and TEXCOORD_1 are loaded",
+                texture_info.index,
+                texture_info.tex_coord
+            );
+        }
+
+        TextureData {
+            image_id: source,
+            factor: color_factor,
+            tex_coord: texture_info.tex_coord,
+            sampler: texture.sampler.unwrap_or_default(),
+            scale: texture_info.scale,
+            strength: texture_info.strength,
+            transform: texture_info.transform.unwrap_or_default(),
+        }
+    }
+
+    /// Texture of an extension, its factors live in the typed extension struct.
+    fn ext_texture(
+        &mut self,
+        texture_info: &Option<ext::TextureInfoExt>,
+        srgb: bool,
+    ) -> Option<TextureData> {
+        texture_info
+            .as_ref()
+            .map(|i| self.texture_data(&i.into(), [1.0; 4], srgb))
+    }
+
     fn load_texture(
         &mut self,
-        texture_info: &Option<SuperTextureInfo<E>>,
+        texture_info: &Option<SuperTextureInfo>,
         color_factor: [f32; 4],
         key: MaterialKey,
         mat_out: &mut Material,
     ) -> Result<(), Error> {
         let tex_data = if let Some(texture_info) = texture_info {
-            let texture = &self.gltf_info.textures[texture_info.index];
-            //HACK insert image target format
-            if key == MaterialKey::Normal {
-                if let Some(id) = texture.source {
-                    let img = &mut self.image_out[id];
-                    if img.mime != "ktx" {
-                        img.target_format = wgpu::TextureFormat::Rgba8Unorm;
-                    }
-                }
-            }
-
-            TextureData {
-                image_id: texture.source,
-                factor: color_factor,
-                tex_coord: texture_info.tex_coord,
-                sampler: texture.sampler.unwrap_or_default(),
-            }
+            // only base color and emissive are sRGB, the rest hold linear data
+            let srgb = matches!(key, MaterialKey::BaseColor | MaterialKey::Emissive);
+            self.texture_data(texture_info, color_factor, srgb)
         } else {
             TextureData {
                 factor: color_factor,
                 ..Default::default()
             }
         };
-        mat_out.insert(key, tex_data);
+        mat_out.textures.insert(key, tex_data);
         Ok(())
     }
 
-    fn prepare_images(&mut self) -> Result<(), Error> {
+    fn prepare_images(&mut self, bytes_read: &mut dyn FnMut(u64)) -> Result<(), Error> {
         for image in &self.gltf_info.images {
             let range = if let Some(ref uri) = image.uri {
                 let data = read_uri_data(uri, self.path)?;
+                bytes_read(data.len() as u64);
                 let start = self.buffer_out.len();
                 self.buffer_out.extend(data);
                 start..self.buffer_out.len()
             } else if let Some(view) = image.buffer_view {
-                let view = &self.gltf_info.buffer_views[view];
-                get_raw_data_via_buffer_view(0, view, self.buffer_map, None, None, self.buffer_out)?
+                let start = self.buffer_out.len();
+                self.buffer_out
+                    .extend_from_slice(self.source.buffer_view(view)?);
+                start..self.buffer_out.len()
             } else {
                 return Err(Error::FailedGetBuffer);
             };
             self.image_out.push(ImageData {
                 name: image.name.clone().or(image.uri.clone()),
                 range,
-                mime: image.mime_type.clone().unwrap_or("image/png".to_string()),
+                mime: image
+                    .mime_type
+                    .clone()
+                    .unwrap_or_else(|| guess_mime(image.uri.as_deref())),
                 target_format: wgpu::TextureFormat::Rgba8UnormSrgb,
+                decoded: None,
             })
         }
         Ok(())
     }
 }
 
-struct PrimitiveBufferReader<'a, E: goth_gltf::Extensions> {
-    gltf_info: &'a goth_gltf::Gltf<E>,
-    buffer_map: &'a BTreeMap<usize, &'a [u8]>,
+struct PrimitiveBufferReader<'a> {
+    source: &'a GltfSource,
 }
 
-impl<'a, E: goth_gltf::Extensions> PrimitiveBufferReader<'a, E> {
-    fn new(gltf_info: &'a goth_gltf::Gltf<E>, buffer_map: &'a BTreeMap<usize, &'a [u8]>) -> Self {
-        Self {
-            gltf_info,
-            buffer_map,
-        }
+impl<'a> PrimitiveBufferReader<'a> {
+    fn new(source: &'a GltfSource) -> Self {
+        Self { source }
     }
 
+    /// Append the accessor to `buffer_out`, this is the only copy of a tightly packed
+    /// accessor since [`GltfSource::accessor`] borrows it from the source.
     fn get_raw_buffer(
         &mut self,
         access_id: usize,
         buffer_out: &mut Vec<u8>,
     ) -> Result<(Range<usize>, usize, usize), Error> {
         let accessor = self
-            .gltf_info
+            .source
+            .gltf()
             .accessors
             .get(access_id)
             .context(FailedGetBufferSnafu)?;
-
-        let buffer_view_id = accessor.buffer_view.context(FailedGetBufferSnafu)?;
-        let buffer_view = &self.gltf_info.buffer_views[buffer_view_id];
-        let range: Range<usize> =
-            load_buffer_view_raw_data(accessor, buffer_view, self.buffer_map, buffer_out)?;
+        let start = buffer_out.len();
+        buffer_out.extend_from_slice(&self.source.accessor(access_id)?);
 
         Ok((
-            range,
+            start..buffer_out.len(),
             accessor.count,
             accessor.component_type.byte_size() * accessor.accessor_type.num_components(),
         ))
     }
+
+    /// Elements of an accessor read straight from the source, the bytes may be unaligned.
+    fn read<T: bytemuck::Pod, const N: usize>(
+        &mut self,
+        access_id: usize,
+    ) -> Result<Vec<[T; N]>, Error> {
+        let bytes = self.source.accessor(access_id)?;
+        let values: Vec<T> = bytes
+            .chunks_exact(std::mem::size_of::<T>())
+            .map(bytemuck::pod_read_unaligned)
+            .collect();
+        Ok(values
+            .chunks_exact(N)
+            .map(|slice| <[T; N]>::try_from(slice).unwrap())
+            .collect())
+    }
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
 pub struct LoadOption {
     pub gen_tbn: bool,
+    pub scene: SceneSelect,
+    /// Memory map the file and its `.bin` buffers instead of reading them, see [`GltfSource`]
+    pub mmap: bool,
+    /// Always parse the glTF, even when a valid baked cache sits next to it
+    pub ignore_cache: bool,
+}
+
+/// The glTF scene [`load_gltf`] reads, unknown indices and names fall back to the default scene.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
+pub enum SceneSelect {
+    /// The file's `scene`, or the first scene when it has none
+    #[default]
+    Default,
+    Index(usize),
+    Name(String),
 }
 
 pub fn load_gltf<P: AsRef<Path>>(
     path: P,
     option: LoadOption,
 ) -> Result<(SceneView, GLTFBuffer), Error> {
-    let gltf_bytes = std::fs::read(&path).context(FileReadFailedSnafu {
-        path: path.as_ref().to_string_lossy(),
-    })?;
-    let (gltf_info, embedded_buffer) =
-        Gltf::<default_extensions::Extensions>::from_bytes(&gltf_bytes)
-            .context(JsonDeSerFailedSnafu)?;
-    //Prepare buffer data
-    let mut buffer_map: BTreeMap<usize, &[u8]> = new_buffer_map_with_embedded(embedded_buffer);
-    let mut buffer_vec: Vec<Vec<u8>> = vec![];
-    load_model_buffers(&gltf_info, &mut buffer_vec, &path)?;
-    insert_external_buffers(&buffer_vec, &mut buffer_map);
+    load_gltf_with_progress(path, option, &mut |_| {})
+}
 
+/// [`load_gltf`] reporting the bytes read as the files come in, then every decoded image.
+/// A valid cache from [`cache::bake`] is loaded instead of the glTF and reports nothing.
+pub fn load_gltf_with_progress<P: AsRef<Path>>(
+    path: P,
+    option: LoadOption,
+    progress: &mut dyn FnMut(LoadProgress),
+) -> Result<(SceneView, GLTFBuffer), Error> {
+    if !option.ignore_cache {
+        if let Some(loaded) = cache::load(path.as_ref(), &option) {
+            return Ok(loaded);
+        }
+    }
+    load_gltf_uncached(path, option, progress)
+}
+
+pub(super) fn load_gltf_uncached<P: AsRef<Path>>(
+    path: P,
+    option: LoadOption,
+    progress: &mut dyn FnMut(LoadProgress),
+) -> Result<(SceneView, GLTFBuffer), Error> {
+    let mut read = 0;
+    let mut bytes_read = |len: u64| {
+        read += len;
+        progress(LoadProgress::BytesRead(read));
+    };
+    let source = GltfSource::open(&path, option.mmap, &mut bytes_read)?;
+    let gltf_info = source.gltf();
+    let gltf_ext = GltfExt::from_bytes(source.file_bytes())?;
     let mut gltf_buffer_out = GLTFBuffer::default();
 
-    let mut scene_view_out = SceneView::default();
-    let scene = gltf_info.scenes.get(0).context(DefaultSceneNotFoundSnafu)?;
+    let mut scene_view_out = SceneView {
+        variants: gltf_ext.variant_names(),
+        ..Default::default()
+    };
+    let default_scene = Some(gltf_info.scene)
+        .filter(|i| *i < gltf_info.scenes.len())
+        .unwrap_or_default();
+    let selected = match &option.scene {
+        SceneSelect::Default => None,
+        SceneSelect::Index(i) => Some(*i).filter(|i| *i < gltf_info.scenes.len()),
+        SceneSelect::Name(name) => gltf_info
+            .scenes
+            .iter()
+            .position(|i| i.name.as_ref() == Some(name)),
+    };
+    if selected.is_none() && !matches!(option.scene, SceneSelect::Default) {
+        log::warn!(
+            "Scene {:?} not found, loading the default scene",
+            option.scene
+        );
+    }
+    scene_view_out.scene_id = selected.unwrap_or(default_scene);
+    let scene = gltf_info
+        .scenes
+        .get(scene_view_out.scene_id)
+        .context(DefaultSceneNotFoundSnafu)?;
     for node_id in &scene.nodes {
         insert_node(
-            &gltf_info,
+            gltf_info,
+            &gltf_ext,
             node_id,
-            &buffer_map,
+            &source,
             &mut scene_view_out,
             &mut gltf_buffer_out,
             &Node::default(),
@@ -429,16 +903,27 @@ pub fn load_gltf<P: AsRef<Path>>(
     }
 
     let mut image_loader = ImageLoader::new(
-        &gltf_info,
+        gltf_info,
         &path,
-        &buffer_map,
+        &source,
         &mut gltf_buffer_out.shared_data,
         &mut scene_view_out.images,
     );
-    image_loader.prepare_images()?;
-
-    for mat in &gltf_info.materials {
-        let mut mat_out = Material::new();
+    image_loader.prepare_images(&mut bytes_read)?;
+
+    for (material_id, mat) in gltf_info.materials.iter().enumerate() {
+        let mut mat_out = Material {
+            alpha_mode: (&mat.alpha_mode).t_into(),
+            alpha_cutoff: mat.alpha_cutoff,
+            double_sided: mat.double_sided,
+            unlit: mat.extensions.khr_materials_unlit.is_some(),
+            ior: mat
+                .extensions
+                .khr_materials_ior
+                .as_ref()
+                .map_or(1.5, |i| i.ior),
+            ..Default::default()
+        };
         let pbr = &mat.pbr_metallic_roughness;
         image_loader.load_texture(
             &pbr.base_color_texture.as_ref().map(Into::into),
@@ -469,27 +954,175 @@ pub fn load_gltf<P: AsRef<Path>>(
                 &mut mat_out,
             )?;
         }
-        //TODO emissive
+        // strength of KHR_materials_emissive_strength goes in the 4th component
+        let emissive_strength = mat
+            .extensions
+            .khr_materials_emissive_strength
+            .as_ref()
+            .map_or(1.0, |i| i.emissive_strength);
+        let [r, g, b] = mat.emissive_factor;
+        image_loader.load_texture(
+            &mat.emissive_texture.as_ref().map(Into::into),
+            [r, g, b, emissive_strength],
+            MaterialKey::Emissive,
+            &mut mat_out,
+        )?;
+
+        if let Some(ext) = gltf_ext.material(material_id) {
+            load_material_ext(&mut image_loader, ext, &mut mat_out);
+        }
 
         scene_view_out.materials.push(mat_out)
     }
 
-    for sampler in gltf_info.samplers {
-        scene_view_out.samplers.push(sampler);
+    for sampler in &gltf_info.samplers {
+        scene_view_out.samplers.push(sampler.clone());
     }
 
     if option.gen_tbn {
-        for node in scene_view_out.nodes.iter_mut() {
-            for mesh in node.1.meshes.iter_mut() {
-                mesh.gen_tbn(&mut gltf_buffer_out);
-            }
+        for mesh in scene_view_out.meshes.values_mut() {
+            mesh.gen_tbn(&mut gltf_buffer_out);
         }
     }
 
+    // after the materials, they pick the linear or sRGB target format
+    decode_images(
+        &mut scene_view_out.images,
+        &gltf_buffer_out.shared_data,
+        progress,
+    )?;
+
     Ok((scene_view_out, gltf_buffer_out))
 }
 
+/// Decode every non KTX2 image on the rayon pool, progress is reported from the calling thread.
+fn decode_images(
+    images: &mut [ImageData],
+    shared_data: &[u8],
+    progress: &mut dyn FnMut(LoadProgress),
+) -> Result<(), Error> {
+    let total = images.len();
+    let (sender, decoded) = mpsc::channel();
+    thread::scope(|s| {
+        let decoding = s.spawn(move || {
+            images
+                .par_iter_mut()
+                .enumerate()
+                .map_with(sender, |sender, (image_id, image)| {
+                    if image.mime != ktx2::MIME && image.decoded.is_none() {
+                        let source = &shared_data[image.range.clone()];
+                        image.decoded = Some(
+                            decode_image(source, image.target_format)
+                                .context(ImageDecodeFailedSnafu { image_id })?,
+                        );
+                    }
+                    let _ = sender.send(());
+                    Ok(())
+                })
+                .collect::<Result<(), Error>>()
+        });
+        // ends once every worker dropped its sender
+        for (count, _) in decoded.iter().enumerate() {
+            progress(LoadProgress::ImageDecoded {
+                decoded: count + 1,
+                total,
+            });
+        }
+        decoding
+            .join()
+            .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+    })
+}
+
+fn decode_image(source: &[u8], target_format: TextureFormat) -> Result<DecodedImage, ImageError> {
+    let img = image::load_from_memory(source)?;
+    let (width, height) = (img.width(), img.height());
+    let color = img.color();
+    if color.bytes_per_pixel() / color.channel_count() == 1 {
+        return Ok(DecodedImage {
+            width,
+            height,
+            format: target_format,
+            mip_level_count: 1,
+            data: img.into_rgba8().into_raw(),
+        });
+    }
+
+    // sRGB has no 16 bit float format, decode it here so the sampler reads linear values
+    let srgb = target_format.is_srgb();
+    let data: Vec<f16> = img
+        .into_rgba32f()
+        .pixels()
+        .flat_map(|pixel| {
+            let [r, g, b, a] = pixel.0;
+            let to_linear = |c: f32| {
+                if !srgb {
+                    c
+                } else if c <= 0.04045 {
+                    c / 12.92
+                } else {
+                    ((c + 0.055) / 1.055).powf(2.4)
+                }
+            };
+            [to_linear(r), to_linear(g), to_linear(b), a].map(f16::from_f32)
+        })
+        .collect();
+    Ok(DecodedImage {
+        width,
+        height,
+        format: TextureFormat::Rgba16Float,
+        mip_level_count: 1,
+        data: bytemuck::cast_slice(&data).to_vec(),
+    })
+}
+
+fn load_material_ext<P: AsRef<Path>>(
+    image_loader: &mut ImageLoader<P>,
+    ext: &ext::MaterialExtensions,
+    mat_out: &mut Material,
+) {
+    mat_out.clearcoat = ext.khr_materials_clearcoat.as_ref().map(|i| Clearcoat {
+        factor: i.clearcoat_factor.unwrap_or_default(),
+        texture: image_loader.ext_texture(&i.clearcoat_texture, false),
+        roughness_factor: i.clearcoat_roughness_factor.unwrap_or_default(),
+        roughness_texture: image_loader.ext_texture(&i.clearcoat_roughness_texture, false),
+        normal_texture: image_loader.ext_texture(&i.clearcoat_normal_texture, false),
+    });
+    mat_out.sheen = ext.khr_materials_sheen.as_ref().map(|i| Sheen {
+        color_factor: i.sheen_color_factor.unwrap_or_default(),
+        color_texture: image_loader.ext_texture(&i.sheen_color_texture, true),
+        roughness_factor: i.sheen_roughness_factor.unwrap_or_default(),
+        roughness_texture: image_loader.ext_texture(&i.sheen_roughness_texture, false),
+    });
+    mat_out.transmission = ext
+        .khr_materials_transmission
+        .as_ref()
+        .map(|i| Transmission {
+            factor: i.transmission_factor.unwrap_or_default(),
+            texture: image_loader.ext_texture(&i.transmission_texture, false),
+        });
+    mat_out.specular = ext.khr_materials_specular.as_ref().map(|i| Specular {
+        factor: i.specular_factor.unwrap_or(1.0),
+        texture: image_loader.ext_texture(&i.specular_texture, false),
+        color_factor: i.specular_color_factor.unwrap_or([1.0; 3]),
+        color_texture: image_loader.ext_texture(&i.specular_color_texture, true),
+    });
+    mat_out.volume = ext.khr_materials_volume.as_ref().map(|i| Volume {
+        thickness_factor: i.thickness_factor.unwrap_or_default(),
+        thickness_texture: image_loader.ext_texture(&i.thickness_texture, false),
+        attenuation_distance: i.attenuation_distance.unwrap_or(f32::INFINITY),
+        attenuation_color: i.attenuation_color.unwrap_or([1.0; 3]),
+    });
+}
+
 impl Mesh {
+    /// Material of the mesh under a `KHR_materials_variants` variant, `None` for the default.
+    pub fn material(&self, variant: Option<usize>) -> Option<usize> {
+        variant
+            .and_then(|i| self.variant_materials.get(&i).copied())
+            .or(self.mat)
+    }
+
     fn gen_tbn(&mut self, buffer: &mut GLTFBuffer) {
         let has_tangent = self.tangents.is_some();
         let indices: Vec<[u32; 1]> = check_and_cast(&buffer.index, &self.index.indices);
@@ -526,13 +1159,27 @@ impl Mesh {
             // Luckily, the place I found this equation provided
             // the solution!
             let r = 1.0 / (delta_uv1.x * delta_uv2.y - delta_uv1.y * delta_uv2.x);
+            // Degenerate uvs (e.g. untextured meshes), any frame around the face normal will do
+            let (tangent, bitangent) = if r.is_finite() {
+                (
+                    (delta_pos1 * delta_uv2.y - delta_pos2 * delta_uv1.y) * r,
+                    // We flip the bitangent to enable right-handed normal
+                    // maps with wgpu texture coordinate system
+                    (delta_pos2 * delta_uv1.x - delta_pos1 * delta_uv2.x) * -r,
+                )
+            } else {
+                let tangent = delta_pos1.normalize_or_zero();
+                (
+                    tangent,
+                    delta_pos1
+                        .cross(delta_pos2)
+                        .cross(tangent)
+                        .normalize_or_zero(),
+                )
+            };
             if !has_tangent {
-                let tangent = (delta_pos1 * delta_uv2.y - delta_pos2 * delta_uv1.y) * r;
                 tangent_buffer.push(tangent.extend(1.0).to_array());
             }
-            // We flip the bitangent to enable right-handed normal
-            // maps with wgpu texture coordinate system
-            let bitangent = (delta_pos2 * delta_uv1.x - delta_pos1 * delta_uv2.x) * -r;
 
             bi_tangent_buffer.push(bitangent.to_array());
         }
@@ -550,102 +1197,32 @@ impl Mesh {
     }
 }
 
-fn load_model_buffers<P: AsRef<Path>>(
-    gltf_info: &Gltf<default_extensions::Extensions>,
-    buffer_vec: &mut Vec<Vec<u8>>,
-    path: P,
-) -> Result<(), Error> {
-    for (index, buffer) in gltf_info.buffers.iter().enumerate() {
-        if buffer
-            .extensions
-            .ext_meshopt_compression
-            .as_ref()
-            .map(|ext| ext.fallback)
-            .unwrap_or(false)
-        {
-            continue;
-        }
-
-        match &buffer.uri {
-            Some(uri) => {
-                buffer_vec.insert(index, read_uri_data(uri, &path)?);
-            }
-            None => continue,
-        };
-    }
-    Ok(())
-}
-
-fn load_buffer_view_raw_data<E: goth_gltf::Extensions>(
-    accessor: &goth_gltf::Accessor,
-    buffer_view: &goth_gltf::BufferView<E>,
-    buffer_map: &BTreeMap<usize, &[u8]>,
-    buffer_out: &mut Vec<u8>,
-) -> Result<Range<usize>, Error> {
-    let offset = accessor.byte_offset;
-    let type_size = accessor.component_type.byte_size() * accessor.accessor_type.num_components();
-
-    let length = accessor.byte_length(buffer_view);
-
-    get_raw_data_via_buffer_view(
-        offset,
-        buffer_view,
-        buffer_map,
-        Some(length),
-        Some(type_size),
-        buffer_out,
-    )
-}
-
-fn get_raw_data_via_buffer_view<E: goth_gltf::Extensions>(
+/// `length` bytes of a buffer view starting at `offset`, the stride is only removed, and
+/// the bytes copied, when the elements are not tightly packed.
+fn buffer_view_bytes<'a, E: goth_gltf::Extensions>(
     mut offset: usize,
     buffer_view: &goth_gltf::BufferView<E>,
-    buffer_map: &BTreeMap<usize, &[u8]>,
-    possible_length: Option<usize>,
-    type_size: Option<usize>,
-    buffer_out: &mut Vec<u8>,
-) -> Result<Range<usize>, Error> {
+    buffer: &'a [u8],
+    length: usize,
+    type_size: usize,
+) -> Result<Cow<'a, [u8]>, Error> {
+    // the last element of an interleaved accessor ends before its stride does
+    let length = length.min(buffer_view.byte_length.saturating_sub(offset));
     offset += buffer_view.byte_offset;
-    let stride = buffer_view.byte_stride;
-    let buffer_id = buffer_view.buffer;
-    // load buffer data
-    let buffer = buffer_map.get(&buffer_id).context(FailedGetBufferSnafu)?;
-    let length = possible_length
-        .unwrap_or(buffer_view.byte_length)
-        .min(buffer_view.byte_length);
-    let buffer = &buffer[offset..offset + length];
-
-    let buffer = if let Some(stride) = stride {
-        let type_size = type_size.context(FailedGetBufferSnafu)?;
-        buffer
-            .chunks(stride)
-            .map(|i| &i[0..type_size])
-            .flat_map(|i| i.iter())
-            .copied()
-            .collect()
-    } else {
-        buffer.to_vec()
-    };
-    let buffer_start = buffer_out.len();
-    buffer_out.extend(buffer);
-
-    Ok(buffer_start..buffer_out.len())
-}
-
-fn new_buffer_map_with_embedded(buffer: Option<&[u8]>) -> BTreeMap<usize, &[u8]> {
-    let mut buffer_map: BTreeMap<usize, &[u8]> = BTreeMap::new();
-    if let Some(buffer) = buffer {
-        buffer_map.insert(0_usize, buffer);
-    }
-    buffer_map
-}
-
-fn insert_external_buffers<'a>(
-    buffer_vec: &'a [Vec<u8>],
-    buffer_map: &mut BTreeMap<usize, &'a [u8]>,
-) {
-    for i in buffer_vec.iter().enumerate() {
-        buffer_map.insert(i.0, i.1);
+    let buffer = buffer
+        .get(offset..offset + length)
+        .context(FailedGetBufferSnafu)?;
+
+    match buffer_view.byte_stride {
+        Some(stride) if stride != type_size => Ok(Cow::Owned(
+            buffer
+                .chunks(stride)
+                .filter_map(|i| i.get(0..type_size))
+                .flatten()
+                .copied()
+                .collect(),
+        )),
+        _ => Ok(Cow::Borrowed(buffer)),
     }
 }
 
@@ -666,40 +1243,102 @@ fn node_transform_to_matrix(n_transform: &NodeTransform) -> Mat4 {
     }
 }
 
+fn guess_mime(uri: Option<&str>) -> String {
+    match uri {
+        Some(uri) if uri.ends_with(".ktx2") => ktx2::MIME,
+        Some(uri) if uri.ends_with(".jpg") || uri.ends_with(".jpeg") => "image/jpeg",
+        _ => "image/png",
+    }
+    .to_string()
+}
+
 fn read_uri_data(uri: &str, path: impl AsRef<Path>) -> Result<Vec<u8>, Error> {
-    if uri.starts_with("data") {
+    if uri.starts_with("data:") {
         let (_mime_type, data) = uri.split_once(',').context(Base64MIMENotFoundSnafu)?;
         log::warn!("Loading buffers from embedded base64 is inefficient. Consider moving the buffers into a seperate file.");
         base64::engine::general_purpose::STANDARD
             .decode(data)
             .context(Base64DecodeFailedSnafu)
     } else {
-        let mut path = std::path::PathBuf::from(path.as_ref());
-        path.set_file_name(urlencoding::decode(uri).context(UTF8ErrSnafu)?.into_owned());
+        let path = uri_path(uri, path)?;
         std::fs::read(&path).context(FileReadFailedSnafu {
             path: path.to_string_lossy(),
         })
     }
 }
 
+/// Path of a relative uri next to the glTF at `path`.
+pub(super) fn uri_path(uri: &str, path: impl AsRef<Path>) -> Result<PathBuf, Error> {
+    let mut path = PathBuf::from(path.as_ref());
+    path.set_file_name(urlencoding::decode(uri).context(UTF8ErrSnafu)?.into_owned());
+    Ok(path)
+}
+
+#[cfg(not(feature = "draco"))]
+fn read_draco_primitive(
+    _gltf_info: &Gltf<default_extensions::Extensions>,
+    mesh_id: usize,
+    _primitive: &goth_gltf::Primitive,
+    _draco: &ext::KhrDracoMeshCompression,
+    _source: &GltfSource,
+    _gltf_buffer_out: &mut GLTFBuffer,
+) -> Result<Mesh, Error> {
+    DracoNotEnabledSnafu { mesh_id }.fail()
+}
+
 fn insert_node(
     gltf_info: &Gltf<default_extensions::Extensions>,
+    gltf_ext: &GltfExt,
     node_id: &usize,
-    buffer_map: &BTreeMap<usize, &[u8]>,
+    source: &GltfSource,
     scene_view_out: &mut SceneView,
     gltf_buffer_out: &mut GLTFBuffer,
     parent: &Node,
 ) -> Result<(), Error> {
     let node: &goth_gltf::Node<default_extensions::Extensions> = &gltf_info.nodes[*node_id];
     let transform = node_transform_to_matrix(&node.transform());
-    let transform = transform * parent.per_node_info.transform;
+    let transform = parent.per_node_info.transform * transform;
+
+    if let Some(camera) = node.camera.and_then(|i| gltf_ext.camera(i)) {
+        scene_view_out.cameras.push(SceneCamera {
+            node_id: *node_id,
+            name: node.name.clone(),
+            projection: camera.into(),
+            transform,
+        });
+    }
 
     let node_out = if let Some(mesh_id) = node.mesh {
         let mesh = &gltf_info.meshes[mesh_id];
 
         let mut meshes_out = Vec::new();
-        for primitive in &mesh.primitives {
-            let mut primitive_reader = PrimitiveBufferReader::new(gltf_info, buffer_map);
+        for (primitive_id, primitive) in mesh.primitives.iter().enumerate() {
+            let key = (mesh_id, primitive_id);
+            meshes_out.push(key);
+            // Shared meshes are read once and referenced by every node using them
+            if scene_view_out.meshes.contains_key(&key) {
+                continue;
+            }
+
+            let primitive_ext = gltf_ext.primitive(mesh_id, primitive_id);
+            let variant_materials = primitive_ext
+                .map(|i| i.variant_materials())
+                .unwrap_or_default();
+            if let Some(draco) = primitive_ext.and_then(|i| i.khr_draco_mesh_compression.as_ref()) {
+                let mut mesh_out = read_draco_primitive(
+                    gltf_info,
+                    mesh_id,
+                    primitive,
+                    draco,
+                    source,
+                    gltf_buffer_out,
+                )?;
+                mesh_out.variant_materials = variant_materials;
+                scene_view_out.meshes.insert(key, mesh_out);
+                continue;
+            }
+
+            let mut primitive_reader = PrimitiveBufferReader::new(source);
 
             let index_accessor =
                 &gltf_info.accessors[primitive.indices.context(NoIndexFoundSnafu { mesh_id })?];
@@ -710,12 +1349,8 @@ fn insert_node(
                     &mut gltf_buffer_out.index,
                 )?,
                 ComponentType::UnsignedShort => {
-                    let mut output = vec![];
-                    primitive_reader.get_raw_buffer(
-                        primitive.indices.context(IndexTypeSnafu { mesh_id })?,
-                        &mut output,
-                    )?;
-                    let index: Vec<[u16; 1]> = check_and_cast(&output, &(0..output.len()));
+                    let index: Vec<[u16; 1]> = primitive_reader
+                        .read(primitive.indices.context(IndexTypeSnafu { mesh_id })?)?;
                     let new_indices: Vec<u32> = index.iter().map(|i| i[0] as u32).collect();
                     let count = new_indices.len();
                     let type_size = 4;
@@ -758,11 +1393,20 @@ fn insert_node(
                     .attributes
                     .texcoord_0
                     .and_then(|texcoord| {
-                        gltf_buffer_out.texcoord.resize(1, Default::default());
-                        let tex_buffer = gltf_buffer_out.texcoord.get_mut(0)?;
+                        let tex_buffer = gltf_buffer_out.uv_set(0);
                         primitive_reader.get_raw_buffer(texcoord, tex_buffer).ok()
                     })
-                    .map(|i| i.0),
+                    .map(|i| i.0)
+                    .or_else(|| Some(gltf_buffer_out.zeroed_uv(0, positions.1))),
+                uv1: primitive
+                    .attributes
+                    .texcoord_1
+                    .and_then(|texcoord| {
+                        let tex_buffer = gltf_buffer_out.uv_set(1);
+                        primitive_reader.get_raw_buffer(texcoord, tex_buffer).ok()
+                    })
+                    .map(|i| i.0)
+                    .or_else(|| Some(gltf_buffer_out.zeroed_uv(1, positions.1))),
                 tangents: primitive
                     .attributes
                     .tangent
@@ -775,17 +1419,24 @@ fn insert_node(
                 index,
                 mode: primitive.mode,
                 mat: primitive.material,
+                variant_materials,
                 bi_tangents: None,
             };
 
-            meshes_out.push(mesh_out);
+            scene_view_out.meshes.insert(key, mesh_out);
         }
 
+        let instances = match &node.extensions.ext_mesh_gpu_instancing {
+            Some(instancing) => read_instances(source, *node_id, instancing)?,
+            None => vec![],
+        };
+
         Node {
             id: *node_id,
             per_node_info: PerNodeBuffer { transform },
             name: node.name.clone(),
             meshes: meshes_out,
+            instances,
             ..Default::default()
         }
     } else {
@@ -800,8 +1451,9 @@ fn insert_node(
     for children in &node.children {
         insert_node(
             gltf_info,
+            gltf_ext,
             children,
-            buffer_map,
+            source,
             scene_view_out,
             gltf_buffer_out,
             &node_out,
@@ -813,6 +1465,67 @@ fn insert_node(
     Ok(())
 }
 
+/// Read the `EXT_mesh_gpu_instancing` TRS accessors into one local transform per instance.
+fn read_instances(
+    source: &GltfSource,
+    node_id: usize,
+    instancing: &goth_gltf::extensions::ExtMeshGpuInstancing,
+) -> Result<Vec<Mat4>, Error> {
+    let gltf_info = source.gltf();
+    let mut reader = PrimitiveBufferReader::new(source);
+    let float = |accessor_id: Option<usize>| -> Result<Option<usize>, Error> {
+        let Some(accessor_id) = accessor_id else {
+            return Ok(None);
+        };
+        let accessor = gltf_info
+            .accessors
+            .get(accessor_id)
+            .context(FailedGetBufferSnafu)?;
+        if !matches!(accessor.component_type, ComponentType::Float) {
+            return InstanceAttributeTypeSnafu { node_id }.fail();
+        }
+        Ok(Some(accessor_id))
+    };
+    let attributes = &instancing.attributes;
+    let translations: Option<Vec<[f32; 3]>> = float(attributes.translation)?
+        .map(|i| reader.read(i))
+        .transpose()?;
+    let rotations: Option<Vec<[f32; 4]>> = float(attributes.rotation)?
+        .map(|i| reader.read(i))
+        .transpose()?;
+    let scales: Option<Vec<[f32; 3]>> = float(attributes.scale)?
+        .map(|i| reader.read(i))
+        .transpose()?;
+
+    let count = [
+        translations.as_ref().map(Vec::len),
+        rotations.as_ref().map(Vec::len),
+        scales.as_ref().map(Vec::len),
+    ]
+    .into_iter()
+    .flatten()
+    .max()
+    .unwrap_or_default();
+    Ok((0..count)
+        .map(|i| {
+            Mat4::from_scale_rotation_translation(
+                scales
+                    .as_ref()
+                    .and_then(|s| s.get(i))
+                    .map_or(Vec3::ONE, |s| Vec3::from(*s)),
+                rotations
+                    .as_ref()
+                    .and_then(|r| r.get(i))
+                    .map_or(Quat::IDENTITY, |r| Quat::from_array(*r)),
+                translations
+                    .as_ref()
+                    .and_then(|t| t.get(i))
+                    .map_or(Vec3::ZERO, |t| Vec3::from(*t)),
+            )
+        })
+        .collect())
+}
+
 pub fn check_and_cast<T: Copy + bytemuck::Pod, const N: usize>(
     scene_buffer: &[u8],
     range: &Range<usize>,
@@ -822,3 +1535,178 @@ pub fn check_and_cast<T: Copy + bytemuck::Pod, const N: usize>(
         .map(|slice| <[T; N]>::try_from(slice).unwrap())
         .collect()
 }
+
+#[cfg(test)]
+mod tests {
+    use nanoserde::DeJson;
+
+    use super::*;
+
+    fn assert_near(a: Vec2, b: Vec2) {
+        assert!(a.abs_diff_eq(b, 1e-5), "{a} != {b}");
+    }
+
+    #[test]
+    fn texture_transform_scales_then_rotates_then_offsets() {
+        let transform = TextureTransform {
+            offset: [0.5, 0.25],
+            rotation: std::f32::consts::FRAC_PI_2,
+            scale: [2.0, 3.0],
+        };
+        let matrix = transform.matrix();
+        // the spec's rotation matrix turns +u towards -v
+        assert_near(matrix.transform_point2(Vec2::X), Vec2::new(0.5, -1.75));
+        assert_near(matrix.transform_point2(Vec2::Y), Vec2::new(3.5, 0.25));
+        assert_near(matrix.transform_point2(Vec2::ZERO), Vec2::new(0.5, 0.25));
+    }
+
+    #[test]
+    fn default_texture_transform_is_identity() {
+        assert_eq!(TextureTransform::default().matrix(), Mat3::IDENTITY);
+    }
+
+    #[test]
+    fn texture_transform_may_switch_uv_set() {
+        let info = ext::TextureInfoExt::deserialize_json(
+            r#"{"index": 2, "texCoord": 0, "extensions": {"KHR_texture_transform": {"texCoord": 1, "rotation": 0.5}}}"#,
+        )
+        .unwrap();
+        let info = SuperTextureInfo::from(&info);
+        assert_eq!((info.index, info.tex_coord), (2, 1));
+        assert_eq!(
+            info.transform,
+            Some(TextureTransform {
+                rotation: 0.5,
+                ..Default::default()
+            })
+        );
+
+        let info = ext::TextureInfoExt::deserialize_json(r#"{"index": 2, "texCoord": 1}"#).unwrap();
+        let info = SuperTextureInfo::from(&info);
+        assert_eq!((info.tex_coord, info.transform), (1, None));
+    }
+
+    #[test]
+    fn instances_are_placed_by_their_node() {
+        let node = Node {
+            per_node_info: PerNodeBuffer {
+                transform: Mat4::from_translation(Vec3::X),
+            },
+            instances: vec![Mat4::from_rotation_z(1.0), Mat4::from_translation(Vec3::Y)],
+            ..Default::default()
+        };
+        let expected = [
+            Mat4::from_rotation_translation(Quat::from_rotation_z(1.0), Vec3::X),
+            Mat4::from_translation(Vec3::X + Vec3::Y),
+        ];
+        let transforms = node.instance_transforms();
+        assert_eq!(transforms.len(), expected.len());
+        for (transform, expected) in transforms.iter().zip(expected) {
+            assert!(
+                transform.abs_diff_eq(expected, 1e-6),
+                "{transform} != {expected}"
+            );
+        }
+    }
+
+    #[test]
+    fn variant_materials_fall_back_to_the_default_material() {
+        let extensions = ext::PrimitiveExtensions::deserialize_json(
+            r#"{"KHR_materials_variants": {"mappings": [
+                {"material": 4, "variants": [0, 2]},
+                {"material": 5, "variants": [1]}
+            ]}}"#,
+        )
+        .unwrap();
+        let mesh = Mesh {
+            mat: Some(3),
+            variant_materials: extensions.variant_materials(),
+            ..Default::default()
+        };
+        let materials: Vec<_> = [None, Some(0), Some(1), Some(2), Some(3)]
+            .into_iter()
+            .map(|i| mesh.material(i))
+            .collect();
+        assert_eq!(materials, [Some(3), Some(4), Some(5), Some(4), Some(3)]);
+
+        let mesh = Mesh::default();
+        assert_eq!(mesh.material(Some(0)), None);
+    }
+
+    fn png(width: u32, height: u32, texel: [u8; 4]) -> Vec<u8> {
+        let mut bytes = vec![];
+        image::RgbaImage::from_pixel(width, height, image::Rgba(texel))
+            .write_to(
+                &mut std::io::Cursor::new(&mut bytes),
+                image::ImageOutputFormat::Png,
+            )
+            .unwrap();
+        bytes
+    }
+
+    fn image_data(range: Range<usize>, mime: &str, decoded: Option<DecodedImage>) -> ImageData {
+        ImageData {
+            name: None,
+            range,
+            mime: mime.to_string(),
+            target_format: TextureFormat::Rgba8UnormSrgb,
+            decoded,
+        }
+    }
+
+    #[test]
+    fn decode_images_skips_ktx2_and_decoded_images() {
+        let shared_data = png(2, 1, [1, 2, 3, 4]);
+        let png_range = 0..shared_data.len();
+        // neither of these ranges holds an image, decoding them would fail
+        let cached = DecodedImage {
+            width: 7,
+            height: 7,
+            format: TextureFormat::Rgba8Unorm,
+            mip_level_count: 1,
+            data: vec![],
+        };
+        let mut images = [
+            image_data(png_range.clone(), "image/png", None),
+            image_data(0..4, "image/png", Some(cached)),
+            image_data(0..4, ktx2::MIME, None),
+            image_data(png_range, "image/png", None),
+        ];
+        images[3].target_format = TextureFormat::Rgba8Unorm;
+
+        let mut progress = vec![];
+        decode_images(&mut images, &shared_data, &mut |i| progress.push(i)).unwrap();
+
+        for (image, format) in [
+            (&images[0], TextureFormat::Rgba8UnormSrgb),
+            (&images[3], TextureFormat::Rgba8Unorm),
+        ] {
+            let decoded = image.decoded.as_ref().unwrap();
+            assert_eq!((decoded.width, decoded.height), (2, 1));
+            assert_eq!(decoded.format, format);
+            assert_eq!(decoded.data, [1, 2, 3, 4, 1, 2, 3, 4]);
+        }
+        assert_eq!(images[1].decoded.as_ref().unwrap().width, 7);
+        assert!(images[2].decoded.is_none());
+
+        let decoded: Vec<_> = progress
+            .iter()
+            .map(|i| match i {
+                LoadProgress::ImageDecoded { decoded, total: 4 } => *decoded,
+                other => panic!("unexpected {other:?}"),
+            })
+            .collect();
+        assert_eq!(decoded, [1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn decode_images_names_the_broken_image() {
+        let shared_data = png(1, 1, [0; 4]);
+        let mut images = [
+            image_data(0..shared_data.len(), "image/png", None),
+            image_data(0..4, "image/png", None),
+        ];
+        let err = decode_images(&mut images, &shared_data, &mut |_| {}).unwrap_err();
+        assert!(matches!(err, Error::ImageDecodeFailed { image_id: 1, .. }));
+    }
+}
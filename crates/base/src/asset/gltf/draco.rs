@@ -0,0 +1,175 @@
+use std::ops::Range;
+
+use draco_decoder::{decode_mesh, AttributeDataType, MeshDecodeConfig};
+use goth_gltf::{default_extensions, ComponentType, Gltf};
+use snafu::{ensure, OptionExt};
+
+use super::ext::KhrDracoMeshCompression;
+use super::{
+    check_and_cast, DracoDecodeFailedSnafu, DracoOutputSizeSnafu, Error, GLTFBuffer, GltfSource,
+    Index, IndexTypeSnafu, Mesh, NoIndexFoundSnafu, NoPositionFoundSnafu,
+};
+
+#[derive(Debug, Clone, Copy)]
+enum Stream {
+    Position,
+    Normal,
+    Tangent,
+    Uv0,
+    Uv1,
+}
+
+/// Decode a `KHR_draco_mesh_compression` primitive, the uncompressed accessors only provide
+/// the element count and layout of the decoded streams.
+pub(super) fn read_draco_primitive(
+    gltf_info: &Gltf<default_extensions::Extensions>,
+    mesh_id: usize,
+    primitive: &goth_gltf::Primitive,
+    draco: &KhrDracoMeshCompression,
+    source: &GltfSource,
+    gltf_buffer_out: &mut GLTFBuffer,
+) -> Result<Mesh, Error> {
+    let compressed = source.buffer_view(draco.buffer_view)?;
+
+    let position_accessor = &gltf_info.accessors[primitive
+        .attributes
+        .position
+        .context(NoPositionFoundSnafu { mesh_id })?];
+    let index_accessor =
+        &gltf_info.accessors[primitive.indices.context(NoIndexFoundSnafu { mesh_id })?];
+    let vertex_count = position_accessor.count;
+    let index_count = index_accessor.count;
+
+    // Draco emits attributes ordered by their unique id
+    let attributes = &draco.attributes;
+    let mut streams: Vec<(u32, Stream, &goth_gltf::Accessor)> = [
+        (
+            attributes.position,
+            primitive.attributes.position,
+            Stream::Position,
+        ),
+        (
+            attributes.normal,
+            primitive.attributes.normal,
+            Stream::Normal,
+        ),
+        (
+            attributes.tangent,
+            primitive.attributes.tangent,
+            Stream::Tangent,
+        ),
+        (
+            attributes.texcoord_0,
+            primitive.attributes.texcoord_0,
+            Stream::Uv0,
+        ),
+        (
+            attributes.texcoord_1,
+            primitive.attributes.texcoord_1,
+            Stream::Uv1,
+        ),
+    ]
+    .into_iter()
+    .filter_map(|(draco_id, accessor, stream)| {
+        Some((draco_id?, stream, &gltf_info.accessors[accessor?]))
+    })
+    .collect();
+    streams.sort_by_key(|i| i.0);
+
+    // The decoder writes indices in the type of the index accessor, then every stream
+    let index_type_size = match index_accessor.component_type {
+        ComponentType::UnsignedShort => 2,
+        ComponentType::UnsignedInt => 4,
+        _ => return IndexTypeSnafu { mesh_id }.fail(),
+    };
+    let mut expected = index_count * index_type_size;
+    let mut config = MeshDecodeConfig::new(vertex_count as u32, index_count as u32);
+    for (_, _, accessor) in &streams {
+        if accessor.component_type != ComponentType::Float {
+            return DracoDecodeFailedSnafu { mesh_id }.fail();
+        }
+        config.add_attribute(
+            accessor.accessor_type.num_components() as u32,
+            AttributeDataType::Float32,
+        );
+        expected += vertex_count
+            * accessor.component_type.byte_size()
+            * accessor.accessor_type.num_components();
+    }
+    let decoded = pollster::block_on(decode_mesh(compressed, &config))
+        .context(DracoDecodeFailedSnafu { mesh_id })?;
+    ensure!(
+        decoded.len() == expected,
+        DracoOutputSizeSnafu {
+            mesh_id,
+            expected,
+            decoded: decoded.len(),
+        }
+    );
+
+    let index_bytes = &decoded[0..index_count * index_type_size];
+    let new_indices: Vec<u32> = if index_type_size == 2 {
+        let index: Vec<[u16; 1]> = check_and_cast(index_bytes, &(0..index_bytes.len()));
+        index.iter().map(|i| i[0] as u32).collect()
+    } else {
+        let index: Vec<[u32; 1]> = check_and_cast(index_bytes, &(0..index_bytes.len()));
+        index.iter().map(|i| i[0]).collect()
+    };
+    let buffer_start = gltf_buffer_out.index.len();
+    gltf_buffer_out
+        .index
+        .extend(bytemuck::cast_slice(&new_indices));
+    let index = Index {
+        indices: buffer_start..gltf_buffer_out.index.len(),
+        count: new_indices.len(),
+        type_size: 4,
+    };
+
+    let mut mesh_out = Mesh {
+        id: mesh_id,
+        index,
+        vertex_count,
+        mode: primitive.mode,
+        mat: primitive.material,
+        ..Default::default()
+    };
+    let mut offset = index_bytes.len();
+    for (_, stream, accessor) in streams {
+        let type_size =
+            accessor.component_type.byte_size() * accessor.accessor_type.num_components();
+        let data = decoded
+            .get(offset..offset + vertex_count * type_size)
+            .context(DracoDecodeFailedSnafu { mesh_id })?;
+        offset += data.len();
+
+        let buffer_out = match stream {
+            Stream::Position => &mut gltf_buffer_out.positions,
+            Stream::Normal => &mut gltf_buffer_out.normal,
+            Stream::Tangent => &mut gltf_buffer_out.tangent,
+            Stream::Uv0 => gltf_buffer_out.uv_set(0),
+            Stream::Uv1 => gltf_buffer_out.uv_set(1),
+        };
+        let start = buffer_out.len();
+        buffer_out.extend(data);
+        let range: Range<usize> = start..buffer_out.len();
+        match stream {
+            Stream::Position => {
+                mesh_out.positions = range;
+                mesh_out.vertex_type_size = type_size;
+            }
+            Stream::Normal => mesh_out.normals = Some(range),
+            Stream::Tangent => mesh_out.tangents = Some(range),
+            Stream::Uv0 => mesh_out.uv0 = Some(range),
+            Stream::Uv1 => mesh_out.uv1 = Some(range),
+        }
+    }
+
+    if mesh_out.uv0.is_none() {
+        mesh_out.uv0 = Some(gltf_buffer_out.zeroed_uv(0, vertex_count));
+    }
+    if mesh_out.uv1.is_none() {
+        mesh_out.uv1 = Some(gltf_buffer_out.zeroed_uv(1, vertex_count));
+    }
+
+    Ok(mesh_out)
+}
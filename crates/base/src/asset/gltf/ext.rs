@@ -0,0 +1,281 @@
+// the DeJson derive expands to matches clippy would rather see as `?`
+#![allow(clippy::question_mark)]
+
+use std::collections::BTreeMap;
+
+use nanoserde::DeJson;
+use snafu::ResultExt;
+
+use super::{Error, JsonDeSerFailedSnafu, UTF8ErrSnafu};
+
+const GLB_MAGIC: &[u8] = b"glTF";
+const GLB_JSON_CHUNK: &[u8] = b"JSON";
+
+/// Extension data goth-gltf doesn't deserialize, read with a second pass over the json chunk.
+#[derive(Debug, Default, DeJson)]
+pub struct GltfExt {
+    #[nserde(default)]
+    pub meshes: Vec<MeshExt>,
+    #[nserde(default)]
+    pub materials: Vec<MaterialExt>,
+    #[nserde(default)]
+    pub cameras: Vec<CameraExt>,
+    #[nserde(default)]
+    pub extensions: RootExtensions,
+}
+
+#[derive(Debug, DeJson)]
+pub struct CameraExt {
+    pub perspective: Option<CameraPerspective>,
+    pub orthographic: Option<CameraOrthographic>,
+}
+
+#[derive(Debug, DeJson)]
+pub struct CameraPerspective {
+    #[nserde(rename = "aspectRatio")]
+    pub aspect_ratio: Option<f32>,
+    pub yfov: f32,
+    pub zfar: Option<f32>,
+    pub znear: f32,
+}
+
+#[derive(Debug, DeJson)]
+pub struct CameraOrthographic {
+    pub xmag: f32,
+    pub ymag: f32,
+    pub zfar: f32,
+    pub znear: f32,
+}
+
+#[derive(Debug, Default, DeJson)]
+pub struct RootExtensions {
+    #[nserde(rename = "KHR_materials_variants")]
+    pub khr_materials_variants: Option<KhrMaterialsVariants>,
+}
+
+#[derive(Debug, DeJson)]
+pub struct KhrMaterialsVariants {
+    pub variants: Vec<MaterialVariant>,
+}
+
+#[derive(Debug, DeJson)]
+pub struct MaterialVariant {
+    pub name: String,
+}
+
+#[derive(Debug, Default, DeJson)]
+pub struct MeshExt {
+    #[nserde(default)]
+    pub primitives: Vec<PrimitiveExt>,
+}
+
+#[derive(Debug, Default, DeJson)]
+pub struct PrimitiveExt {
+    #[nserde(default)]
+    pub extensions: PrimitiveExtensions,
+}
+
+#[derive(Debug, Default, DeJson)]
+pub struct PrimitiveExtensions {
+    #[nserde(rename = "KHR_draco_mesh_compression")]
+    pub khr_draco_mesh_compression: Option<KhrDracoMeshCompression>,
+    #[nserde(rename = "KHR_materials_variants")]
+    pub khr_materials_variants: Option<KhrMaterialsVariantsMappings>,
+}
+
+impl PrimitiveExtensions {
+    /// Material of the primitive for each variant index it maps.
+    pub fn variant_materials(&self) -> BTreeMap<usize, usize> {
+        self.khr_materials_variants
+            .iter()
+            .flat_map(|i| &i.mappings)
+            .flat_map(|mapping| {
+                mapping
+                    .variants
+                    .iter()
+                    .map(|variant| (*variant, mapping.material))
+            })
+            .collect()
+    }
+}
+
+#[cfg_attr(not(feature = "draco"), allow(dead_code))]
+#[derive(Debug, DeJson)]
+pub struct KhrDracoMeshCompression {
+    #[nserde(rename = "bufferView")]
+    pub buffer_view: usize,
+    #[nserde(default)]
+    pub attributes: DracoAttributes,
+}
+
+#[derive(Debug, DeJson)]
+pub struct KhrMaterialsVariantsMappings {
+    pub mappings: Vec<VariantMapping>,
+}
+
+#[derive(Debug, DeJson)]
+pub struct VariantMapping {
+    pub material: usize,
+    pub variants: Vec<usize>,
+}
+
+/// Draco attribute ids of the compressed streams, keyed like the primitive attributes.
+#[cfg_attr(not(feature = "draco"), allow(dead_code))]
+#[derive(Debug, Default, DeJson)]
+pub struct DracoAttributes {
+    #[nserde(rename = "POSITION")]
+    pub position: Option<u32>,
+    #[nserde(rename = "NORMAL")]
+    pub normal: Option<u32>,
+    #[nserde(rename = "TANGENT")]
+    pub tangent: Option<u32>,
+    #[nserde(rename = "TEXCOORD_0")]
+    pub texcoord_0: Option<u32>,
+    #[nserde(rename = "TEXCOORD_1")]
+    pub texcoord_1: Option<u32>,
+}
+
+#[derive(Debug, Default, DeJson)]
+pub struct MaterialExt {
+    #[nserde(default)]
+    pub extensions: MaterialExtensions,
+}
+
+#[derive(Debug, Default, DeJson)]
+pub struct MaterialExtensions {
+    #[nserde(rename = "KHR_materials_clearcoat")]
+    pub khr_materials_clearcoat: Option<KhrMaterialsClearcoat>,
+    #[nserde(rename = "KHR_materials_sheen")]
+    pub khr_materials_sheen: Option<KhrMaterialsSheen>,
+    #[nserde(rename = "KHR_materials_transmission")]
+    pub khr_materials_transmission: Option<KhrMaterialsTransmission>,
+    #[nserde(rename = "KHR_materials_specular")]
+    pub khr_materials_specular: Option<KhrMaterialsSpecular>,
+    #[nserde(rename = "KHR_materials_volume")]
+    pub khr_materials_volume: Option<KhrMaterialsVolume>,
+}
+
+/// Texture reference inside a material extension, goth-gltf only parses the core ones.
+#[derive(Debug, DeJson)]
+pub struct TextureInfoExt {
+    pub index: usize,
+    #[nserde(rename = "texCoord")]
+    pub tex_coord: Option<usize>,
+    pub scale: Option<f32>,
+    #[nserde(default)]
+    pub extensions: TextureInfoExtensions,
+}
+
+#[derive(Debug, Default, DeJson)]
+pub struct TextureInfoExtensions {
+    #[nserde(rename = "KHR_texture_transform")]
+    pub khr_texture_transform: Option<KhrTextureTransform>,
+}
+
+#[derive(Debug, DeJson)]
+pub struct KhrTextureTransform {
+    pub offset: Option<[f32; 2]>,
+    pub rotation: Option<f32>,
+    pub scale: Option<[f32; 2]>,
+    #[nserde(rename = "texCoord")]
+    pub tex_coord: Option<usize>,
+}
+
+#[derive(Debug, DeJson)]
+pub struct KhrMaterialsClearcoat {
+    #[nserde(rename = "clearcoatFactor")]
+    pub clearcoat_factor: Option<f32>,
+    #[nserde(rename = "clearcoatTexture")]
+    pub clearcoat_texture: Option<TextureInfoExt>,
+    #[nserde(rename = "clearcoatRoughnessFactor")]
+    pub clearcoat_roughness_factor: Option<f32>,
+    #[nserde(rename = "clearcoatRoughnessTexture")]
+    pub clearcoat_roughness_texture: Option<TextureInfoExt>,
+    #[nserde(rename = "clearcoatNormalTexture")]
+    pub clearcoat_normal_texture: Option<TextureInfoExt>,
+}
+
+#[derive(Debug, DeJson)]
+pub struct KhrMaterialsSheen {
+    #[nserde(rename = "sheenColorFactor")]
+    pub sheen_color_factor: Option<[f32; 3]>,
+    #[nserde(rename = "sheenColorTexture")]
+    pub sheen_color_texture: Option<TextureInfoExt>,
+    #[nserde(rename = "sheenRoughnessFactor")]
+    pub sheen_roughness_factor: Option<f32>,
+    #[nserde(rename = "sheenRoughnessTexture")]
+    pub sheen_roughness_texture: Option<TextureInfoExt>,
+}
+
+#[derive(Debug, DeJson)]
+pub struct KhrMaterialsTransmission {
+    #[nserde(rename = "transmissionFactor")]
+    pub transmission_factor: Option<f32>,
+    #[nserde(rename = "transmissionTexture")]
+    pub transmission_texture: Option<TextureInfoExt>,
+}
+
+#[derive(Debug, DeJson)]
+pub struct KhrMaterialsSpecular {
+    #[nserde(rename = "specularFactor")]
+    pub specular_factor: Option<f32>,
+    #[nserde(rename = "specularTexture")]
+    pub specular_texture: Option<TextureInfoExt>,
+    #[nserde(rename = "specularColorFactor")]
+    pub specular_color_factor: Option<[f32; 3]>,
+    #[nserde(rename = "specularColorTexture")]
+    pub specular_color_texture: Option<TextureInfoExt>,
+}
+
+#[derive(Debug, DeJson)]
+pub struct KhrMaterialsVolume {
+    #[nserde(rename = "thicknessFactor")]
+    pub thickness_factor: Option<f32>,
+    #[nserde(rename = "thicknessTexture")]
+    pub thickness_texture: Option<TextureInfoExt>,
+    #[nserde(rename = "attenuationDistance")]
+    pub attenuation_distance: Option<f32>,
+    #[nserde(rename = "attenuationColor")]
+    pub attenuation_color: Option<[f32; 3]>,
+}
+
+impl GltfExt {
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        let json = String::from_utf8(json_chunk(bytes).to_vec()).context(UTF8ErrSnafu)?;
+        GltfExt::deserialize_json(&json).context(JsonDeSerFailedSnafu)
+    }
+
+    pub fn primitive(&self, mesh_id: usize, primitive_id: usize) -> Option<&PrimitiveExtensions> {
+        self.meshes
+            .get(mesh_id)?
+            .primitives
+            .get(primitive_id)
+            .map(|i| &i.extensions)
+    }
+
+    pub fn material(&self, material_id: usize) -> Option<&MaterialExtensions> {
+        self.materials.get(material_id).map(|i| &i.extensions)
+    }
+
+    pub fn camera(&self, camera_id: usize) -> Option<&CameraExt> {
+        self.cameras.get(camera_id)
+    }
+
+    /// Names of the `KHR_materials_variants` variants, indexed like the primitive mappings.
+    pub fn variant_names(&self) -> Vec<String> {
+        self.extensions
+            .khr_materials_variants
+            .iter()
+            .flat_map(|i| &i.variants)
+            .map(|i| i.name.clone())
+            .collect()
+    }
+}
+
+fn json_chunk(bytes: &[u8]) -> &[u8] {
+    if !bytes.starts_with(GLB_MAGIC) || bytes.len() < 20 || &bytes[16..20] != GLB_JSON_CHUNK {
+        return bytes;
+    }
+    let length = u32::from_le_bytes([bytes[12], bytes[13], bytes[14], bytes[15]]) as usize;
+    &bytes[20..(20 + length).min(bytes.len())]
+}
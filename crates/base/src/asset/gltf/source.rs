@@ -0,0 +1,168 @@
+//! The bytes behind a glTF, read into memory or memory mapped.
+
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::ops::{Deref, Range};
+use std::path::Path;
+
+use goth_gltf::{default_extensions, Gltf};
+use memmap2::Mmap;
+use snafu::{OptionExt, ResultExt};
+
+use super::{
+    buffer_view_bytes, read_uri_data, uri_path, Error, FailedGetBufferSnafu, FileReadFailedSnafu,
+    JsonDeSerFailedSnafu,
+};
+
+enum Bytes {
+    Read(Vec<u8>),
+    Mapped(Mmap),
+}
+
+impl Bytes {
+    fn open(path: &Path, mmap: bool) -> Result<Self, Error> {
+        let context = |_: &mut std::io::Error| FileReadFailedSnafu {
+            path: path.to_string_lossy(),
+        };
+        if !mmap {
+            return std::fs::read(path).map(Bytes::Read).with_context(context);
+        }
+        let file = File::open(path).with_context(context)?;
+        // SAFETY: the file must not be truncated while mapped, assets are not written while loading
+        let map = unsafe { Mmap::map(&file) }.with_context(context)?;
+        Ok(Bytes::Mapped(map))
+    }
+}
+
+impl Deref for Bytes {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            Bytes::Read(bytes) => bytes,
+            Bytes::Mapped(map) => map,
+        }
+    }
+}
+
+/// A parsed glTF and its buffers. With `mmap` the GLB and the `.bin` files are memory
+/// mapped, accessors are then read straight out of the page cache.
+pub struct GltfSource {
+    gltf_info: Gltf<default_extensions::Extensions>,
+    file: Bytes,
+    /// Binary chunk of a GLB inside `file`
+    glb_bin: Option<Range<usize>>,
+    buffers: BTreeMap<usize, Bytes>,
+}
+
+impl GltfSource {
+    /// `bytes_read` is called with the size of every file once it is read or mapped.
+    pub fn open<P: AsRef<Path>>(
+        path: P,
+        mmap: bool,
+        bytes_read: &mut dyn FnMut(u64),
+    ) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let file = Bytes::open(path, mmap)?;
+        bytes_read(file.len() as u64);
+        let (gltf_info, glb_bin) = Gltf::<default_extensions::Extensions>::from_bytes(&file)
+            .context(JsonDeSerFailedSnafu)?;
+        // the chunk borrows `file`, remember where it is instead
+        let glb_bin = glb_bin.map(|bin| {
+            let start = bin.as_ptr() as usize - file.as_ptr() as usize;
+            start..start + bin.len()
+        });
+
+        let mut buffers = BTreeMap::new();
+        for (index, buffer) in gltf_info.buffers.iter().enumerate() {
+            // meshopt fallback buffers carry no data
+            if buffer
+                .extensions
+                .ext_meshopt_compression
+                .as_ref()
+                .map(|ext| ext.fallback)
+                .unwrap_or(false)
+            {
+                continue;
+            }
+            let Some(uri) = &buffer.uri else {
+                continue;
+            };
+            let bytes = if uri.starts_with("data:") {
+                Bytes::Read(read_uri_data(uri, path)?)
+            } else {
+                Bytes::open(&uri_path(uri, path)?, mmap)?
+            };
+            bytes_read(bytes.len() as u64);
+            buffers.insert(index, bytes);
+        }
+
+        Ok(Self {
+            gltf_info,
+            file,
+            glb_bin,
+            buffers,
+        })
+    }
+
+    pub fn gltf(&self) -> &Gltf<default_extensions::Extensions> {
+        &self.gltf_info
+    }
+
+    /// The glTF JSON, or the whole GLB.
+    pub fn file_bytes(&self) -> &[u8] {
+        &self.file
+    }
+
+    /// Buffer by index, a buffer without uri in a GLB is its binary chunk.
+    pub fn buffer(&self, buffer_id: usize) -> Option<&[u8]> {
+        match self.buffers.get(&buffer_id) {
+            Some(bytes) => Some(bytes),
+            None if buffer_id == 0 => self.glb_bin.clone().map(|bin| &self.file[bin]),
+            None => None,
+        }
+    }
+
+    /// Bytes of a whole buffer view, always borrowed.
+    pub fn buffer_view(&self, view_id: usize) -> Result<&[u8], Error> {
+        let buffer_view = self
+            .gltf_info
+            .buffer_views
+            .get(view_id)
+            .context(FailedGetBufferSnafu)?;
+        let buffer = self
+            .buffer(buffer_view.buffer)
+            .context(FailedGetBufferSnafu)?;
+        let start = buffer_view.byte_offset;
+        buffer
+            .get(start..start + buffer_view.byte_length)
+            .context(FailedGetBufferSnafu)
+    }
+
+    /// Bytes of an accessor, borrowed when tightly packed and only copied when the
+    /// buffer view stride has to be removed. Sparse accessors are not applied.
+    pub fn accessor(&self, accessor_id: usize) -> Result<Cow<'_, [u8]>, Error> {
+        let accessor = self
+            .gltf_info
+            .accessors
+            .get(accessor_id)
+            .context(FailedGetBufferSnafu)?;
+        let buffer_view = accessor
+            .buffer_view
+            .and_then(|i| self.gltf_info.buffer_views.get(i))
+            .context(FailedGetBufferSnafu)?;
+        let buffer = self
+            .buffer(buffer_view.buffer)
+            .context(FailedGetBufferSnafu)?;
+        let type_size =
+            accessor.component_type.byte_size() * accessor.accessor_type.num_components();
+        buffer_view_bytes(
+            accessor.byte_offset,
+            buffer_view,
+            buffer,
+            accessor.byte_length(buffer_view),
+            type_size,
+        )
+    }
+}
@@ -0,0 +1,797 @@
+use std::io::Read;
+
+use ktx2::{ColorModel, Format, SupercompressionScheme, TransferFunction};
+use snafu::{OptionExt, ResultExt, Snafu};
+use wgpu::util::DeviceExt;
+use wgpu::{AstcBlock, AstcChannel, TextureFormat};
+
+pub const MIME: &str = "image/ktx2";
+
+/// Block compression features worth requesting when the adapter has them.
+pub const COMPRESSION_FEATURES: wgpu::Features = wgpu::Features::TEXTURE_COMPRESSION_BC
+    .union(wgpu::Features::TEXTURE_COMPRESSION_ETC2)
+    .union(wgpu::Features::TEXTURE_COMPRESSION_ASTC);
+
+#[derive(Debug, Snafu)]
+#[snafu(visibility(pub))]
+pub enum Error {
+    ParseFailed {
+        source: ktx2::ParseError,
+    },
+    ZstdDecodeFailed {
+        source: ruzstd::frame_decoder::FrameDecoderError,
+    },
+    SupercompressionReadFailed {
+        source: std::io::Error,
+    },
+    /// A vendor scheme, only BasisLZ, Zstandard and ZLIB are known.
+    UnsupportedSupercompression {
+        scheme: SupercompressionScheme,
+    },
+    UnsupportedFormat {
+        format: Option<Format>,
+    },
+    /// The texture is block compressed in a format the device can't sample and the CPU
+    /// decoder doesn't know.
+    MissingFeature {
+        feature: wgpu::Features,
+    },
+    BlockDecodeFailed {
+        level: usize,
+        message: &'static str,
+    },
+    UnsupportedTextureShape,
+    #[cfg(feature = "basisu")]
+    TranscodeFailed {
+        level: usize,
+    },
+    /// The BasisLZ global data is too short for its codebooks or image descriptions.
+    #[cfg(feature = "basisu")]
+    InvalidGlobalData,
+    #[cfg(not(feature = "basisu"))]
+    #[snafu(display("ETC1S and UASTC textures need the basisu feature"))]
+    BasisUniversalDisabled,
+}
+
+/// A KTX2 container decoded into uploadable mip levels, biggest level first.
+#[derive(Debug)]
+pub struct Ktx2Image {
+    pub width: u32,
+    pub height: u32,
+    pub format: TextureFormat,
+    pub levels: Vec<Vec<u8>>,
+}
+
+impl Ktx2Image {
+    /// Parse a KTX2 container, inflate supercompressed levels and transcode ETC1S and UASTC to
+    /// the best format `features` can sample. Block compressed formats the device lacks are
+    /// decoded to RGBA8. `srgb` is used when the data format descriptor doesn't tell.
+    pub fn from_bytes(bytes: &[u8], features: wgpu::Features, srgb: bool) -> Result<Self, Error> {
+        let reader = ktx2::Reader::new(bytes).context(ParseFailedSnafu)?;
+        let header = reader.header();
+        if header.pixel_depth > 1 || header.layer_count > 1 || header.face_count > 1 {
+            return UnsupportedTextureShapeSnafu.fail();
+        }
+
+        let dfd = reader
+            .data_format_descriptors()
+            .next()
+            .and_then(|i| ktx2::BasicDataFormatDescriptor::parse(i.data).ok());
+        let srgb = dfd
+            .as_ref()
+            .and_then(|i| i.transfer_function)
+            .map(|i| i == TransferFunction::SRGB)
+            .unwrap_or(srgb);
+        let color_model = dfd.as_ref().and_then(|i| i.color_model);
+
+        let mut levels = Vec::with_capacity(header.level_count.max(1) as usize);
+        for level in reader.levels() {
+            let level = match header.supercompression_scheme {
+                // BasisLZ levels are ETC1S slices, inflated by the transcoder
+                None | Some(SupercompressionScheme::BasisLZ) => level.to_vec(),
+                Some(SupercompressionScheme::Zstandard) => {
+                    let mut out = vec![];
+                    ruzstd::StreamingDecoder::new(level)
+                        .context(ZstdDecodeFailedSnafu)?
+                        .read_to_end(&mut out)
+                        .context(SupercompressionReadFailedSnafu)?;
+                    out
+                }
+                Some(SupercompressionScheme::ZLIB) => {
+                    let mut out = vec![];
+                    flate2::read::ZlibDecoder::new(level)
+                        .read_to_end(&mut out)
+                        .context(SupercompressionReadFailedSnafu)?;
+                    out
+                }
+                Some(scheme) => return UnsupportedSupercompressionSnafu { scheme }.fail(),
+            };
+            levels.push(level);
+        }
+
+        let (width, height) = (header.pixel_width, header.pixel_height.max(1));
+        let (format, levels) = match (header.format, header.supercompression_scheme, color_model) {
+            (None, Some(SupercompressionScheme::BasisLZ), _) => transcode_etc1s(
+                &levels,
+                reader.supercompression_global_data(),
+                width,
+                height,
+                features,
+                srgb,
+            )?,
+            (None, _, Some(ColorModel::UASTC)) => {
+                transcode_uastc(&levels, width, height, features, srgb)?
+            }
+            (Some(format), _, _) => {
+                let format = map_format(format, srgb).context(UnsupportedFormatSnafu {
+                    format: Some(format),
+                })?;
+                if features.contains(format.required_features()) {
+                    (format, levels)
+                } else {
+                    decode_to_rgba8(format, &levels, width, height)?
+                }
+            }
+            (None, _, _) => return UnsupportedFormatSnafu { format: None }.fail(),
+        };
+
+        Ok(Self {
+            width,
+            height,
+            format,
+            levels,
+        })
+    }
+
+    pub fn create_texture(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        label: Option<&str>,
+    ) -> wgpu::Texture {
+        device.create_texture_with_data(
+            queue,
+            &wgpu::TextureDescriptor {
+                label,
+                size: wgpu::Extent3d {
+                    width: self.width,
+                    height: self.height,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: self.levels.len() as u32,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: self.format,
+                usage: wgpu::TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            },
+            &self.levels.concat(),
+        )
+    }
+}
+
+/// Pick the transcode target, ASTC and BC7 keep nearly all of the Basis Universal quality.
+#[cfg(feature = "basisu")]
+fn transcode_target(features: wgpu::Features, srgb: bool) -> TextureFormat {
+    let pick = |linear, srgb_format| if srgb { srgb_format } else { linear };
+    if features.contains(wgpu::Features::TEXTURE_COMPRESSION_ASTC) {
+        let channel = if srgb {
+            AstcChannel::UnormSrgb
+        } else {
+            AstcChannel::Unorm
+        };
+        TextureFormat::Astc {
+            block: AstcBlock::B4x4,
+            channel,
+        }
+    } else if features.contains(wgpu::Features::TEXTURE_COMPRESSION_BC) {
+        pick(TextureFormat::Bc7RgbaUnorm, TextureFormat::Bc7RgbaUnormSrgb)
+    } else if features.contains(wgpu::Features::TEXTURE_COMPRESSION_ETC2) {
+        pick(
+            TextureFormat::Etc2Rgba8Unorm,
+            TextureFormat::Etc2Rgba8UnormSrgb,
+        )
+    } else {
+        pick(TextureFormat::Rgba8Unorm, TextureFormat::Rgba8UnormSrgb)
+    }
+}
+
+#[cfg(feature = "basisu")]
+fn transcode_uastc(
+    levels: &[Vec<u8>],
+    width: u32,
+    height: u32,
+    features: wgpu::Features,
+    srgb: bool,
+) -> Result<(TextureFormat, Vec<Vec<u8>>), Error> {
+    use basis_universal::{
+        DecodeFlags, LowLevelUastcTranscoder, SliceParametersUastc, TranscoderBlockFormat,
+    };
+
+    let format = transcode_target(features, srgb);
+    let block_format = match format {
+        TextureFormat::Astc { .. } => TranscoderBlockFormat::ASTC_4x4,
+        TextureFormat::Bc7RgbaUnorm | TextureFormat::Bc7RgbaUnormSrgb => TranscoderBlockFormat::BC7,
+        TextureFormat::Etc2Rgba8Unorm | TextureFormat::Etc2Rgba8UnormSrgb => {
+            TranscoderBlockFormat::ETC2_RGBA
+        }
+        _ => TranscoderBlockFormat::RGBA32,
+    };
+
+    basis_universal::transcoder_init();
+    let transcoder = LowLevelUastcTranscoder::new();
+    let mut levels_out = Vec::with_capacity(levels.len());
+    for (level, data) in levels.iter().enumerate() {
+        let level_width = (width >> level).max(1);
+        let level_height = (height >> level).max(1);
+        let transcoded = transcoder
+            .transcode_slice(
+                data,
+                SliceParametersUastc {
+                    num_blocks_x: level_width.div_ceil(4),
+                    num_blocks_y: level_height.div_ceil(4),
+                    has_alpha: true,
+                    original_width: level_width,
+                    original_height: level_height,
+                },
+                DecodeFlags::HIGH_QUALITY,
+                block_format,
+            )
+            .ok()
+            .context(TranscodeFailedSnafu { level })?;
+        levels_out.push(transcoded);
+    }
+    Ok((format, levels_out))
+}
+
+#[cfg(not(feature = "basisu"))]
+fn transcode_uastc(
+    _levels: &[Vec<u8>],
+    _width: u32,
+    _height: u32,
+    _features: wgpu::Features,
+    _srgb: bool,
+) -> Result<(TextureFormat, Vec<Vec<u8>>), Error> {
+    BasisUniversalDisabledSnafu.fail()
+}
+
+/// basis-universal only transcodes ETC1S out of `.basis` files, so the BasisLZ slices and the
+/// codebooks of the global data are repacked into one.
+#[cfg(feature = "basisu")]
+fn transcode_etc1s(
+    levels: &[Vec<u8>],
+    global_data: &[u8],
+    width: u32,
+    height: u32,
+    features: wgpu::Features,
+    srgb: bool,
+) -> Result<(TextureFormat, Vec<Vec<u8>>), Error> {
+    use basis_universal::{TranscodeParameters, Transcoder, TranscoderTextureFormat};
+
+    let format = transcode_target(features, srgb);
+    let texture_format = match format {
+        TextureFormat::Astc { .. } => TranscoderTextureFormat::ASTC_4x4_RGBA,
+        TextureFormat::Bc7RgbaUnorm | TextureFormat::Bc7RgbaUnormSrgb => {
+            TranscoderTextureFormat::BC7_RGBA
+        }
+        TextureFormat::Etc2Rgba8Unorm | TextureFormat::Etc2Rgba8UnormSrgb => {
+            TranscoderTextureFormat::ETC2_RGBA
+        }
+        _ => TranscoderTextureFormat::RGBA32,
+    };
+    let basis =
+        basis_file(levels, global_data, width, height, srgb).context(InvalidGlobalDataSnafu)?;
+
+    basis_universal::transcoder_init();
+    let mut transcoder = Transcoder::new();
+    transcoder
+        .prepare_transcoding(&basis)
+        .ok()
+        .context(TranscodeFailedSnafu { level: 0usize })?;
+    let levels = (0..levels.len())
+        .map(|level| {
+            let parameters = TranscodeParameters {
+                level_index: level as u32,
+                ..Default::default()
+            };
+            transcoder
+                .transcode_image_level(&basis, texture_format, parameters)
+                .ok()
+                .context(TranscodeFailedSnafu { level })
+        })
+        .collect::<Result<_, _>>()?;
+    Ok((format, levels))
+}
+
+#[cfg(not(feature = "basisu"))]
+fn transcode_etc1s(
+    _levels: &[Vec<u8>],
+    _global_data: &[u8],
+    _width: u32,
+    _height: u32,
+    _features: wgpu::Features,
+    _srgb: bool,
+) -> Result<(TextureFormat, Vec<Vec<u8>>), Error> {
+    BasisUniversalDisabledSnafu.fail()
+}
+
+/// Build a `.basis` file, laid out as in basisu_file_headers.h, around the ETC1S slices of
+/// `levels`. The global data starts with the codebook sizes and one image description per
+/// level, slice offsets in those are relative to the level.
+#[cfg(feature = "basisu")]
+fn basis_file(
+    levels: &[Vec<u8>],
+    global_data: &[u8],
+    width: u32,
+    height: u32,
+    srgb: bool,
+) -> Option<Vec<u8>> {
+    const HEADER_SIZE: usize = 77;
+    const SLICE_DESC_SIZE: usize = 23;
+    const IMAGE_DESCS_START: usize = 20;
+    const IMAGE_DESC_SIZE: usize = 20;
+    let read = |offset: usize, size: usize| {
+        let mut bytes = [0; 4];
+        bytes[..size].copy_from_slice(global_data.get(offset..offset + size)?);
+        Some(u32::from_le_bytes(bytes) as usize)
+    };
+    let put = |out: &mut Vec<u8>, value: usize, size: usize| {
+        out.extend_from_slice(&(value as u64).to_le_bytes()[..size])
+    };
+
+    let (endpoint_count, selector_count) = (read(0, 2)?, read(2, 2)?);
+    let (endpoints_size, selectors_size) = (read(4, 4)?, read(8, 4)?);
+    let (tables_size, extended_size) = (read(12, 4)?, read(16, 4)?);
+    let codebooks_start = IMAGE_DESCS_START + levels.len() * IMAGE_DESC_SIZE;
+    let codebooks_size = endpoints_size + selectors_size + tables_size + extended_size;
+    let codebooks = global_data.get(codebooks_start..codebooks_start + codebooks_size)?;
+
+    // (level, slice, is alpha), a level with alpha stores its alpha slice right after the color
+    let has_alpha = read(IMAGE_DESCS_START + 16, 4)? > 0;
+    let mut slices = vec![];
+    for (level, data) in levels.iter().enumerate() {
+        let desc = IMAGE_DESCS_START + level * IMAGE_DESC_SIZE;
+        let (rgb_offset, rgb_size) = (read(desc + 4, 4)?, read(desc + 8, 4)?);
+        slices.push((level, data.get(rgb_offset..rgb_offset + rgb_size)?, false));
+        if has_alpha {
+            let (alpha_offset, alpha_size) = (read(desc + 12, 4)?, read(desc + 16, 4)?);
+            slices.push((
+                level,
+                data.get(alpha_offset..alpha_offset + alpha_size)?,
+                true,
+            ));
+        }
+    }
+
+    let endpoints_offset = HEADER_SIZE + slices.len() * SLICE_DESC_SIZE;
+    let selectors_offset = endpoints_offset + endpoints_size;
+    let tables_offset = selectors_offset + selectors_size;
+    let extended_offset = tables_offset + tables_size;
+    let mut slice_offset = extended_offset + extended_size;
+    let mut data = Vec::with_capacity(slice_offset - HEADER_SIZE);
+    for &(level, slice, alpha) in &slices {
+        let level_width = (width as usize >> level).max(1);
+        let level_height = (height as usize >> level).max(1);
+        put(&mut data, 0, 3);
+        put(&mut data, level, 1);
+        put(&mut data, alpha as usize, 1);
+        put(&mut data, level_width, 2);
+        put(&mut data, level_height, 2);
+        put(&mut data, level_width.div_ceil(4), 2);
+        put(&mut data, level_height.div_ceil(4), 2);
+        put(&mut data, slice_offset, 4);
+        put(&mut data, slice.len(), 4);
+        put(&mut data, crc16(slice) as usize, 2);
+        slice_offset += slice.len();
+    }
+    data.extend_from_slice(codebooks);
+    for (_, slice, _) in &slices {
+        data.extend_from_slice(slice);
+    }
+
+    // ETC1S, then has alpha slices and sRGB
+    let flags = 1 | (has_alpha as usize) << 2 | (srgb as usize) << 4;
+    let mut file = Vec::with_capacity(HEADER_SIZE + data.len());
+    put(&mut file, (b'B' as usize) << 8 | b's' as usize, 2);
+    put(&mut file, 0x13, 2);
+    put(&mut file, HEADER_SIZE, 2);
+    put(&mut file, 0, 2);
+    put(&mut file, data.len(), 4);
+    put(&mut file, crc16(&data) as usize, 2);
+    put(&mut file, slices.len(), 3);
+    put(&mut file, 1, 3);
+    put(&mut file, 0, 1);
+    put(&mut file, flags, 2);
+    put(&mut file, 0, 1);
+    put(&mut file, 0, 3);
+    // reserved and user data
+    file.extend([0; 12]);
+    put(&mut file, endpoint_count, 2);
+    put(&mut file, endpoints_offset, 4);
+    put(&mut file, endpoints_size, 3);
+    put(&mut file, selector_count, 2);
+    put(&mut file, selectors_offset, 4);
+    put(&mut file, selectors_size, 3);
+    put(&mut file, tables_offset, 4);
+    put(&mut file, tables_size, 4);
+    put(&mut file, HEADER_SIZE, 4);
+    put(&mut file, extended_offset, 4);
+    put(&mut file, extended_size, 4);
+    let header_crc = crc16(&file[8..]);
+    file[6..8].copy_from_slice(&header_crc.to_le_bytes());
+    file.extend_from_slice(&data);
+    Some(file)
+}
+
+/// The CRC-16 basisu checks headers and slices with.
+#[cfg(feature = "basisu")]
+fn crc16(data: &[u8]) -> u16 {
+    let mut crc = !0u16;
+    for &byte in data {
+        let q = u16::from(byte) ^ (crc >> 8);
+        let k = (q >> 4) ^ q;
+        crc = (crc << 8) ^ k ^ (k << 5) ^ (k << 12);
+    }
+    !crc
+}
+
+/// Decode block compressed levels on the CPU when the device can't sample them.
+fn decode_to_rgba8(
+    format: TextureFormat,
+    levels: &[Vec<u8>],
+    width: u32,
+    height: u32,
+) -> Result<(TextureFormat, Vec<Vec<u8>>), Error> {
+    type Decode = fn(&[u8], usize, usize, &mut [u32]) -> Result<(), &'static str>;
+    let decode: Decode = match format {
+        TextureFormat::Bc1RgbaUnorm | TextureFormat::Bc1RgbaUnormSrgb => {
+            texture2ddecoder::decode_bc1
+        }
+        TextureFormat::Bc3RgbaUnorm | TextureFormat::Bc3RgbaUnormSrgb => {
+            texture2ddecoder::decode_bc3
+        }
+        TextureFormat::Bc4RUnorm => texture2ddecoder::decode_bc4,
+        TextureFormat::Bc5RgUnorm => texture2ddecoder::decode_bc5,
+        TextureFormat::Bc7RgbaUnorm | TextureFormat::Bc7RgbaUnormSrgb => {
+            texture2ddecoder::decode_bc7
+        }
+        TextureFormat::Etc2Rgb8Unorm | TextureFormat::Etc2Rgb8UnormSrgb => {
+            texture2ddecoder::decode_etc2_rgb
+        }
+        TextureFormat::Etc2Rgba8Unorm | TextureFormat::Etc2Rgba8UnormSrgb => {
+            texture2ddecoder::decode_etc2_rgba8
+        }
+        TextureFormat::Astc {
+            block: AstcBlock::B4x4,
+            ..
+        } => |data, width, height, image| {
+            texture2ddecoder::decode_astc(data, width, height, 4, 4, image)
+        },
+        _ => {
+            let feature = format.required_features();
+            return MissingFeatureSnafu { feature }.fail();
+        }
+    };
+
+    let mut levels_out = Vec::with_capacity(levels.len());
+    for (level, data) in levels.iter().enumerate() {
+        let level_width = (width >> level).max(1) as usize;
+        let level_height = (height >> level).max(1) as usize;
+        let mut texels = vec![0u32; level_width * level_height];
+        decode(data, level_width, level_height, &mut texels)
+            .map_err(|message| BlockDecodeFailedSnafu { level, message }.build())?;
+        // texture2ddecoder packs texels as BGRA
+        levels_out.push(
+            texels
+                .iter()
+                .flat_map(|texel| {
+                    let [b, g, r, a] = texel.to_le_bytes();
+                    [r, g, b, a]
+                })
+                .collect(),
+        );
+    }
+    let format = if format.is_srgb() {
+        TextureFormat::Rgba8UnormSrgb
+    } else {
+        TextureFormat::Rgba8Unorm
+    };
+    Ok((format, levels_out))
+}
+
+fn map_format(format: Format, srgb: bool) -> Option<TextureFormat> {
+    let pick = |linear, srgb_format| if srgb { srgb_format } else { linear };
+    let astc_channel = if srgb {
+        AstcChannel::UnormSrgb
+    } else {
+        AstcChannel::Unorm
+    };
+    Some(match format {
+        Format::R8_UNORM => TextureFormat::R8Unorm,
+        Format::R8G8_UNORM => TextureFormat::Rg8Unorm,
+        Format::R8G8B8A8_UNORM | Format::R8G8B8A8_SRGB => {
+            pick(TextureFormat::Rgba8Unorm, TextureFormat::Rgba8UnormSrgb)
+        }
+        Format::B8G8R8A8_UNORM | Format::B8G8R8A8_SRGB => {
+            pick(TextureFormat::Bgra8Unorm, TextureFormat::Bgra8UnormSrgb)
+        }
+        Format::R16G16B16A16_SFLOAT => TextureFormat::Rgba16Float,
+        Format::R32G32B32A32_SFLOAT => TextureFormat::Rgba32Float,
+        Format::BC1_RGBA_UNORM_BLOCK | Format::BC1_RGBA_SRGB_BLOCK => {
+            pick(TextureFormat::Bc1RgbaUnorm, TextureFormat::Bc1RgbaUnormSrgb)
+        }
+        Format::BC3_UNORM_BLOCK | Format::BC3_SRGB_BLOCK => {
+            pick(TextureFormat::Bc3RgbaUnorm, TextureFormat::Bc3RgbaUnormSrgb)
+        }
+        Format::BC4_UNORM_BLOCK => TextureFormat::Bc4RUnorm,
+        Format::BC5_UNORM_BLOCK => TextureFormat::Bc5RgUnorm,
+        Format::BC7_UNORM_BLOCK | Format::BC7_SRGB_BLOCK => {
+            pick(TextureFormat::Bc7RgbaUnorm, TextureFormat::Bc7RgbaUnormSrgb)
+        }
+        Format::ETC2_R8G8B8_UNORM_BLOCK | Format::ETC2_R8G8B8_SRGB_BLOCK => pick(
+            TextureFormat::Etc2Rgb8Unorm,
+            TextureFormat::Etc2Rgb8UnormSrgb,
+        ),
+        Format::ETC2_R8G8B8A8_UNORM_BLOCK | Format::ETC2_R8G8B8A8_SRGB_BLOCK => pick(
+            TextureFormat::Etc2Rgba8Unorm,
+            TextureFormat::Etc2Rgba8UnormSrgb,
+        ),
+        Format::ASTC_4x4_UNORM_BLOCK | Format::ASTC_4x4_SRGB_BLOCK => TextureFormat::Astc {
+            block: AstcBlock::B4x4,
+            channel: astc_channel,
+        },
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::*;
+
+    /// A 2x2 RGBA8 image and its 1x1 mip.
+    fn rgba_levels() -> Vec<Vec<u8>> {
+        vec![
+            vec![
+                255, 0, 0, 255, 0, 255, 0, 255, 0, 0, 255, 255, 255, 255, 255, 128,
+            ],
+            vec![10, 20, 30, 40],
+        ]
+    }
+
+    /// A 2D KTX2 with a basic data format descriptor and `levels` stored as given.
+    fn ktx2_file(
+        format: Option<Format>,
+        scheme: Option<SupercompressionScheme>,
+        color_model: ColorModel,
+        transfer: TransferFunction,
+        (width, height): (u32, u32),
+        levels: &[Vec<u8>],
+    ) -> Vec<u8> {
+        let mut dfd = vec![];
+        dfd.extend(28u32.to_le_bytes());
+        // vendor and type 0 is the basic descriptor, version 2, 24 bytes without samples
+        dfd.extend(0u32.to_le_bytes());
+        dfd.extend((2u32 | 24 << 16).to_le_bytes());
+        dfd.extend((color_model.0.get() | 1 << 8 | transfer.0.get() << 16).to_le_bytes());
+        dfd.extend([0; 12]);
+
+        let dfd_offset = 80 + levels.len() * 24;
+        let mut file = vec![
+            0xAB, 0x4B, 0x54, 0x58, 0x20, 0x32, 0x30, 0xBB, 0x0D, 0x0A, 0x1A, 0x0A,
+        ];
+        let header = [
+            format.map_or(0, |i| i.0.get()),
+            1,
+            width,
+            height,
+            0,
+            0,
+            1,
+            levels.len() as u32,
+            scheme.map_or(0, |i| i.0.get()),
+            dfd_offset as u32,
+            dfd.len() as u32,
+            0,
+            0,
+        ];
+        for value in header {
+            file.extend(value.to_le_bytes());
+        }
+        file.extend([0; 16]);
+        let mut level_offset = dfd_offset + dfd.len();
+        for level in levels {
+            for value in [level_offset, level.len(), level.len()] {
+                file.extend((value as u64).to_le_bytes());
+            }
+            level_offset += level.len();
+        }
+        file.extend(dfd);
+        file.extend(levels.concat());
+        file
+    }
+
+    fn rgba8_file(scheme: Option<SupercompressionScheme>, levels: &[Vec<u8>]) -> Vec<u8> {
+        ktx2_file(
+            Some(Format::R8G8B8A8_UNORM),
+            scheme,
+            ColorModel::RGBSDA,
+            TransferFunction::SRGB,
+            (2, 2),
+            levels,
+        )
+    }
+
+    /// A zstd frame made of one raw block, which ruzstd can read but not write.
+    fn zstd_frame(data: &[u8]) -> Vec<u8> {
+        let mut frame = 0xFD2FB528u32.to_le_bytes().to_vec();
+        // single segment with a one byte content size
+        frame.extend([0x20, data.len() as u8]);
+        let block_header = 1 | (data.len() as u32) << 3;
+        frame.extend(&block_header.to_le_bytes()[..3]);
+        frame.extend(data);
+        frame
+    }
+
+    #[test]
+    fn reads_uncompressed_levels() {
+        let file = rgba8_file(None, &rgba_levels());
+        let image = Ktx2Image::from_bytes(&file, wgpu::Features::empty(), false).unwrap();
+        assert_eq!((image.width, image.height), (2, 2));
+        // the transfer function wins over the `srgb` argument
+        assert_eq!(image.format, TextureFormat::Rgba8UnormSrgb);
+        assert_eq!(image.levels, rgba_levels());
+    }
+
+    #[test]
+    fn inflates_zlib_levels() {
+        let levels: Vec<_> = rgba_levels()
+            .iter()
+            .map(|level| {
+                let mut encoder =
+                    flate2::write::ZlibEncoder::new(vec![], flate2::Compression::default());
+                encoder.write_all(level).unwrap();
+                encoder.finish().unwrap()
+            })
+            .collect();
+        let file = rgba8_file(Some(SupercompressionScheme::ZLIB), &levels);
+        let image = Ktx2Image::from_bytes(&file, wgpu::Features::empty(), false).unwrap();
+        assert_eq!(image.levels, rgba_levels());
+    }
+
+    #[test]
+    fn inflates_zstd_levels() {
+        let levels: Vec<_> = rgba_levels().iter().map(|i| zstd_frame(i)).collect();
+        let file = rgba8_file(Some(SupercompressionScheme::Zstandard), &levels);
+        let image = Ktx2Image::from_bytes(&file, wgpu::Features::empty(), false).unwrap();
+        assert_eq!(image.levels, rgba_levels());
+    }
+
+    #[test]
+    fn rejects_unknown_supercompression() {
+        let file = rgba8_file(SupercompressionScheme::new(4), &rgba_levels()[..1]);
+        let err = Ktx2Image::from_bytes(&file, wgpu::Features::empty(), false).unwrap_err();
+        assert!(matches!(err, Error::UnsupportedSupercompression { .. }));
+    }
+
+    #[test]
+    fn rejects_bad_magic() {
+        let mut file = rgba8_file(None, &rgba_levels()[..1]);
+        file[1] = b'X';
+        let err = Ktx2Image::from_bytes(&file, wgpu::Features::empty(), false).unwrap_err();
+        assert!(matches!(err, Error::ParseFailed { .. }));
+    }
+
+    /// A red BC1 block whose last three rows are blue.
+    const BC1_BLOCK: [u8; 8] = [0x00, 0xF8, 0x1F, 0x00, 0x00, 0x55, 0x55, 0x55];
+
+    fn bc1_file() -> Vec<u8> {
+        ktx2_file(
+            Some(Format::BC1_RGBA_UNORM_BLOCK),
+            None,
+            ColorModel::BC1A,
+            TransferFunction::Linear,
+            (4, 4),
+            &[BC1_BLOCK.to_vec()],
+        )
+    }
+
+    #[test]
+    fn keeps_supported_block_formats() {
+        let features = wgpu::Features::TEXTURE_COMPRESSION_BC;
+        let image = Ktx2Image::from_bytes(&bc1_file(), features, false).unwrap();
+        assert_eq!(image.format, TextureFormat::Bc1RgbaUnorm);
+        assert_eq!(image.levels, [BC1_BLOCK.to_vec()]);
+    }
+
+    #[test]
+    fn decodes_unsupported_block_formats() {
+        let image = Ktx2Image::from_bytes(&bc1_file(), wgpu::Features::empty(), false).unwrap();
+        assert_eq!(image.format, TextureFormat::Rgba8Unorm);
+        let texels: Vec<_> = image.levels[0].chunks_exact(4).collect();
+        assert_eq!(texels.len(), 16);
+        assert!(texels[..4].iter().all(|i| *i == [255, 0, 0, 255]));
+        assert!(texels[4..].iter().all(|i| *i == [0, 0, 255, 255]));
+    }
+
+    #[cfg(not(feature = "basisu"))]
+    #[test]
+    fn basis_textures_need_the_feature() {
+        let uastc = ktx2_file(
+            None,
+            None,
+            ColorModel::UASTC,
+            TransferFunction::SRGB,
+            (4, 4),
+            &[vec![0; 16]],
+        );
+        let err = Ktx2Image::from_bytes(&uastc, wgpu::Features::empty(), false).unwrap_err();
+        assert!(matches!(err, Error::BasisUniversalDisabled));
+
+        let etc1s = ktx2_file(
+            None,
+            Some(SupercompressionScheme::BasisLZ),
+            ColorModel::ETC1S,
+            TransferFunction::SRGB,
+            (4, 4),
+            &[vec![0; 16]],
+        );
+        let err = Ktx2Image::from_bytes(&etc1s, wgpu::Features::empty(), false).unwrap_err();
+        assert!(matches!(err, Error::BasisUniversalDisabled));
+    }
+
+    #[cfg(feature = "basisu")]
+    #[test]
+    fn crc16_matches_basisu() {
+        // CRC-16/GENIBUS check value
+        assert_eq!(crc16(b"123456789"), 0xD64E);
+    }
+
+    #[cfg(feature = "basisu")]
+    #[test]
+    fn repacks_basis_lz_into_basis_file() {
+        let mut global_data = vec![];
+        for (value, size) in [(3, 2), (5, 2), (2, 4), (1, 4), (3, 4), (0, 4)] {
+            global_data.extend(&(value as u32).to_le_bytes()[..size]);
+        }
+        // one level, color slice at 1..4 and no alpha
+        for value in [0u32, 1, 3, 0, 0] {
+            global_data.extend(value.to_le_bytes());
+        }
+        global_data.extend([10, 11, 20, 30, 31, 32]);
+        let level = vec![0, 7, 8, 9];
+
+        let file = basis_file(&[level], &global_data, 8, 4, true).unwrap();
+        let read = |offset: usize, size: usize| {
+            let mut bytes = [0; 4];
+            bytes[..size].copy_from_slice(&file[offset..offset + size]);
+            u32::from_le_bytes(bytes) as usize
+        };
+        assert_eq!(&file[..2], b"sB");
+        assert_eq!(read(6, 2), crc16(&file[8..77]) as usize);
+        assert_eq!(read(8, 4), file.len() - 77);
+        assert_eq!(read(12, 2), crc16(&file[77..]) as usize);
+        assert_eq!(read(14, 3), 1);
+        // ETC1S and sRGB
+        assert_eq!(read(21, 2), 1 | 16);
+        assert_eq!(read(39, 2), 3);
+        assert_eq!(&file[read(41, 4)..][..2], [10, 11]);
+        assert_eq!(read(48, 2), 5);
+        assert_eq!(&file[read(50, 4)..][..1], [20]);
+        assert_eq!(&file[read(57, 4)..][..3], [30, 31, 32]);
+
+        let slice_desc = read(65, 4);
+        assert_eq!(read(slice_desc + 5, 2), 8);
+        assert_eq!(read(slice_desc + 9, 2), 2);
+        let (offset, size) = (read(slice_desc + 13, 4), read(slice_desc + 17, 4));
+        assert_eq!(&file[offset..offset + size], [7, 8, 9]);
+    }
+
+    #[cfg(feature = "basisu")]
+    #[test]
+    fn rejects_short_basis_lz_global_data() {
+        let global_data = [0; 30];
+        assert!(basis_file(&[vec![0; 4]], &global_data, 4, 4, false).is_none());
+    }
+}
@@ -0,0 +1,61 @@
+//! Background glTF loading, the render thread only polls for events.
+
+use std::path::Path;
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+
+use super::gltf::{
+    load_gltf_with_progress, Error, GLTFBuffer, LoadOption, LoadProgress, SceneView,
+};
+
+#[derive(Debug)]
+pub enum LoadEvent {
+    Progress(LoadProgress),
+    /// Images are decoded already, hand them to [`crate::scene::GpuSceneUpload`]
+    Loaded(Box<(SceneView, GLTFBuffer)>),
+    Failed(Error),
+}
+
+/// A glTF parsed, read and decoded on a worker thread.
+///
+/// ```ignore
+/// let loader = GltfLoader::spawn(path, LoadOption::default());
+/// // every frame
+/// for event in loader.poll() {
+///     match event {
+///         LoadEvent::Progress(progress) => log::info!("{progress:?}"),
+///         LoadEvent::Loaded(loaded) => { /* start a GpuSceneUpload */ }
+///         LoadEvent::Failed(err) => log::error!("{err}"),
+///     }
+/// }
+/// ```
+pub struct GltfLoader {
+    events: Receiver<LoadEvent>,
+}
+
+impl GltfLoader {
+    pub fn spawn<P: AsRef<Path>>(path: P, option: LoadOption) -> Self {
+        let path = path.as_ref().to_path_buf();
+        let (sender, events) = mpsc::channel();
+        thread::Builder::new()
+            .name("gltf loader".into())
+            .spawn(move || {
+                // the receiver may be gone already, nobody is left to tell
+                let mut progress = |progress| {
+                    let _ = sender.send(LoadEvent::Progress(progress));
+                };
+                let loaded = load_gltf_with_progress(&path, option, &mut progress);
+                let _ = sender.send(match loaded {
+                    Ok(loaded) => LoadEvent::Loaded(Box::new(loaded)),
+                    Err(err) => LoadEvent::Failed(err),
+                });
+            })
+            .expect("failed to spawn the gltf loader thread");
+        Self { events }
+    }
+
+    /// Events since the last poll, never blocks.
+    pub fn poll(&self) -> impl Iterator<Item = LoadEvent> + '_ {
+        self.events.try_iter()
+    }
+}
@@ -1 +1,5 @@
+pub mod cache;
 pub mod gltf;
+pub mod ktx2;
+pub mod loader;
+pub mod server;
@@ -0,0 +1,389 @@
+//! Shared scenes, textures and shaders behind reference counted handles.
+//!
+//! Loading the same path twice hands out the same GPU resources, they are freed
+//! as soon as the last [`Handle`] drops.
+
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Weak};
+
+use image::GenericImageView;
+use snafu::ResultExt;
+
+use super::cache::option_hash;
+use super::gltf::{load_gltf, GLTFBuffer, LoadOption, SceneView};
+use super::ktx2::Ktx2Image;
+use crate::scene::GpuScene;
+use crate::shader::{Defines, Reflection, ShaderComposer};
+use crate::texture::{texture_bytes, MipmapGenerator, Texture};
+use crate::{
+    AssetReadErrSnafu, Error, GLTFErrSnafu, ImageLoadErrSnafu, Ktx2ErrSnafu, ShaderErrSnafu,
+};
+
+/// Memory an asset holds, reported by [`AssetServer::memory_usage`].
+pub trait Asset {
+    fn cpu_bytes(&self) -> u64;
+    fn gpu_bytes(&self) -> u64;
+}
+
+/// A shared asset, cloning only bumps the reference count.
+pub struct Handle<T> {
+    inner: Arc<Loaded<T>>,
+}
+
+struct Loaded<T> {
+    path: PathBuf,
+    asset: T,
+}
+
+impl<T> Handle<T> {
+    pub fn path(&self) -> &Path {
+        &self.inner.path
+    }
+
+    /// Handles of the same asset.
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.inner, &other.inner)
+    }
+}
+
+impl<T> Clone for Handle<T> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<T> Deref for Handle<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.inner.asset
+    }
+}
+
+/// A glTF uploaded with [`GpuScene::new`]. Decoded pixels are dropped after the upload,
+/// state like the active material variant is shared, load a scene yourself to change it.
+pub struct SceneAsset {
+    pub view: SceneView,
+    pub buffer: GLTFBuffer,
+    pub gpu: GpuScene,
+    /// Options of the first load, reloads use them again
+    option: LoadOption,
+}
+
+impl Asset for SceneAsset {
+    fn cpu_bytes(&self) -> u64 {
+        let buffer = &self.buffer;
+        let texcoord: usize = buffer.texcoord.iter().map(Vec::len).sum();
+        (buffer.positions.len()
+            + buffer.tangent.len()
+            + buffer.normal.len()
+            + texcoord
+            + buffer.index.len()
+            + buffer.shared_data.len()
+            + buffer.bi_tangent.len()) as u64
+    }
+
+    fn gpu_bytes(&self) -> u64 {
+        self.gpu.gpu_bytes()
+    }
+}
+
+impl Asset for Texture {
+    fn cpu_bytes(&self) -> u64 {
+        0
+    }
+
+    fn gpu_bytes(&self) -> u64 {
+        texture_bytes(&self.texture)
+    }
+}
+
+/// A WGSL permutation built by the [`ShaderComposer`], shared with its cache.
+pub struct ShaderAsset {
+    pub module: Arc<wgpu::ShaderModule>,
+    pub reflection: Arc<Reflection>,
+    /// Source after the directives ran
+    pub source: String,
+}
+
+impl Asset for ShaderAsset {
+    fn cpu_bytes(&self) -> u64 {
+        self.source.len() as u64
+    }
+
+    fn gpu_bytes(&self) -> u64 {
+        0
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AssetKind {
+    Scene,
+    Texture,
+    Shader,
+}
+
+#[derive(Debug, Clone)]
+pub struct AssetMemory {
+    pub kind: AssetKind,
+    pub path: PathBuf,
+    /// Live handles
+    pub handles: usize,
+    pub cpu_bytes: u64,
+    pub gpu_bytes: u64,
+}
+
+type Assets<K, T> = HashMap<K, Weak<Loaded<T>>>;
+
+/// Path and [`option_hash`], loads differing only in `mmap` or `ignore_cache` share a scene.
+type SceneKey = (PathBuf, u64);
+
+#[derive(Default)]
+pub struct AssetServer {
+    scenes: Assets<SceneKey, SceneAsset>,
+    /// Keyed by path and sRGB
+    textures: Assets<(PathBuf, bool), Texture>,
+    /// Keyed by path and defines
+    shaders: Assets<(PathBuf, Defines), ShaderAsset>,
+    mipmap_generator: Option<MipmapGenerator>,
+}
+
+impl AssetServer {
+    pub fn load_scene<P: AsRef<Path>>(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        path: P,
+        option: LoadOption,
+    ) -> Result<Handle<SceneAsset>, Error> {
+        let path = canonical(path.as_ref());
+        let key = scene_key(&path, &option);
+        get_or_load(&mut self.scenes, key, path, |path| {
+            load_scene(device, queue, path, option)
+        })
+    }
+
+    /// Load the scene behind `handle` from disk again, later loads of its path share the
+    /// new scene. Existing handles keep the old one until they are replaced.
+    pub fn reload_scene(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        handle: &Handle<SceneAsset>,
+    ) -> Result<Handle<SceneAsset>, Error> {
+        let path = handle.path().to_path_buf();
+        let option = handle.option.clone();
+        let key = scene_key(&path, &option);
+        let asset = load_scene(device, queue, &path, option)?;
+        let inner = Arc::new(Loaded { path, asset });
+        self.scenes.insert(key, Arc::downgrade(&inner));
+        Ok(Handle { inner })
+    }
+
+    /// An image or KTX2 file with a full mip chain, `srgb` for color data.
+    pub fn load_texture<P: AsRef<Path>>(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        path: P,
+        srgb: bool,
+    ) -> Result<Handle<Texture>, Error> {
+        let path = canonical(path.as_ref());
+        let mipmap_generator = self
+            .mipmap_generator
+            .get_or_insert_with(|| MipmapGenerator::new(device));
+        get_or_load(&mut self.textures, (path.clone(), srgb), path, |path| {
+            let bytes = read(path)?;
+            let label = path.to_str();
+            let texture = if path.extension().is_some_and(|i| i == "ktx2") {
+                Ktx2Image::from_bytes(&bytes, device.features(), srgb)
+                    .context(Ktx2ErrSnafu)?
+                    .create_texture(device, queue, label)
+            } else {
+                let image = image::load_from_memory(&bytes).context(ImageLoadErrSnafu)?;
+                let (width, height) = image.dimensions();
+                let format = match srgb {
+                    true => wgpu::TextureFormat::Rgba8UnormSrgb,
+                    false => wgpu::TextureFormat::Rgba8Unorm,
+                };
+                mipmap_generator.create_texture_with_data(
+                    device,
+                    queue,
+                    &wgpu::TextureDescriptor {
+                        label,
+                        size: wgpu::Extent3d {
+                            width,
+                            height,
+                            depth_or_array_layers: 1,
+                        },
+                        mip_level_count: 1,
+                        sample_count: 1,
+                        dimension: wgpu::TextureDimension::D2,
+                        format,
+                        usage: wgpu::TextureUsages::TEXTURE_BINDING,
+                        view_formats: &[],
+                    },
+                    &image.to_rgba8(),
+                )
+            };
+            let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+            Ok(Texture {
+                texture,
+                view,
+                sampler: None,
+            })
+        })
+    }
+
+    /// A WGSL file with `defines` switched on, read at runtime unlike `include_wgsl!`.
+    /// `#include`s resolve through `composer`, invalid WGSL is returned as a naga
+    /// diagnostic pointing at the original files instead of reaching the device.
+    pub fn load_shader<P: AsRef<Path>>(
+        &mut self,
+        device: &wgpu::Device,
+        composer: &mut ShaderComposer,
+        path: P,
+        defines: &[&str],
+    ) -> Result<Handle<ShaderAsset>, Error> {
+        let path = canonical(path.as_ref());
+        let define_set: Defines = defines.iter().map(|i| i.to_string()).collect();
+        let key = (path.clone(), define_set.clone());
+        get_or_load(&mut self.shaders, key, path, |path| {
+            let entry = path.to_string_lossy();
+            let source = composer
+                .preprocess(&entry, &define_set)
+                .context(ShaderErrSnafu)?
+                .source;
+            let module = composer
+                .module(device, &entry, defines)
+                .context(ShaderErrSnafu)?;
+            let reflection = composer
+                .reflection(device, &entry, defines)
+                .context(ShaderErrSnafu)?;
+            Ok(ShaderAsset {
+                module,
+                reflection,
+                source,
+            })
+        })
+    }
+
+    /// Every asset that still has handles, dropped ones are forgotten here.
+    pub fn memory_usage(&mut self) -> Vec<AssetMemory> {
+        let mut usage = vec![];
+        collect_usage(&mut self.scenes, AssetKind::Scene, &mut usage);
+        collect_usage(&mut self.textures, AssetKind::Texture, &mut usage);
+        collect_usage(&mut self.shaders, AssetKind::Shader, &mut usage);
+        usage
+    }
+}
+
+fn load_scene(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    path: &Path,
+    option: LoadOption,
+) -> Result<SceneAsset, Error> {
+    let (mut view, mut buffer) = load_gltf(path, option.clone()).context(GLTFErrSnafu)?;
+    let gpu = GpuScene::new(device, queue, &view, &buffer)?;
+    for image in &mut view.images {
+        image.decoded = None;
+    }
+    buffer.shared_data = vec![];
+    Ok(SceneAsset {
+        view,
+        buffer,
+        gpu,
+        option,
+    })
+}
+
+fn scene_key(path: &Path, option: &LoadOption) -> SceneKey {
+    (path.to_path_buf(), option_hash(option))
+}
+
+fn get_or_load<K: Hash + Eq, T>(
+    assets: &mut Assets<K, T>,
+    key: K,
+    path: PathBuf,
+    load: impl FnOnce(&Path) -> Result<T, Error>,
+) -> Result<Handle<T>, Error> {
+    if let Some(inner) = assets.get(&key).and_then(Weak::upgrade) {
+        return Ok(Handle { inner });
+    }
+    let asset = load(&path)?;
+    let inner = Arc::new(Loaded { path, asset });
+    assets.retain(|_, i| i.strong_count() > 0);
+    assets.insert(key, Arc::downgrade(&inner));
+    Ok(Handle { inner })
+}
+
+fn collect_usage<K, T: Asset>(
+    assets: &mut Assets<K, T>,
+    kind: AssetKind,
+    usage: &mut Vec<AssetMemory>,
+) {
+    assets.retain(|_, i| i.strong_count() > 0);
+    usage.extend(
+        assets
+            .values()
+            .filter_map(Weak::upgrade)
+            .map(|i| AssetMemory {
+                kind,
+                path: i.path.clone(),
+                // without the one upgraded just now
+                handles: Arc::strong_count(&i) - 1,
+                cpu_bytes: i.asset.cpu_bytes(),
+                gpu_bytes: i.asset.gpu_bytes(),
+            }),
+    );
+}
+
+/// The same file reached through different relative paths dedups to one asset.
+fn canonical(path: &Path) -> PathBuf {
+    std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+fn read(path: &Path) -> Result<Vec<u8>, Error> {
+    std::fs::read(path).context(AssetReadErrSnafu {
+        path: path.to_string_lossy(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asset::gltf::SceneSelect;
+
+    #[test]
+    fn scene_keys_ignore_how_a_scene_is_read() {
+        let path = Path::new("scene.gltf");
+        let key = scene_key(path, &LoadOption::default());
+        let read_differently = LoadOption {
+            mmap: true,
+            ignore_cache: true,
+            ..Default::default()
+        };
+        assert_eq!(scene_key(path, &read_differently), key);
+
+        let tbn = LoadOption {
+            gen_tbn: true,
+            ..Default::default()
+        };
+        let second_scene = LoadOption {
+            scene: SceneSelect::Index(1),
+            ..Default::default()
+        };
+        assert_ne!(scene_key(path, &tbn), key);
+        assert_ne!(scene_key(path, &second_scene), key);
+        assert_ne!(
+            scene_key(Path::new("other.gltf"), &LoadOption::default()),
+            key
+        );
+    }
+}
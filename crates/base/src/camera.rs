@@ -6,6 +6,8 @@ use winit::{
     event::{ElementState, MouseScrollDelta, VirtualKeyCode},
 };
 
+use crate::asset::gltf::{CameraProjection, SceneCamera};
+
 const SAFE_FRAC_PI_2: f32 = FRAC_PI_2 - 0.0001;
 
 #[derive(Debug)]
@@ -14,6 +16,8 @@ pub struct Projection {
     fovy: f32,
     znear: f32,
     zfar: f32,
+    /// Half height of an orthographic view, perspective when `None`
+    ortho_ymag: Option<f32>,
 }
 
 impl Projection {
@@ -24,6 +28,33 @@ impl Projection {
             fovy,
             znear,
             zfar,
+            ortho_ymag: None,
+        }
+    }
+
+    /// Projection of a glTF camera, the aspect ratio keeps following the surface size
+    /// so the authored one and `xmag` are ignored.
+    pub fn from_gltf(projection: &CameraProjection, width: u32, height: u32) -> Self {
+        let aspect = width as f32 / height as f32;
+        match *projection {
+            CameraProjection::Perspective {
+                yfov, znear, zfar, ..
+            } => Self {
+                aspect,
+                fovy: yfov,
+                znear,
+                zfar: zfar.unwrap_or(f32::INFINITY),
+                ortho_ymag: None,
+            },
+            CameraProjection::Orthographic {
+                ymag, znear, zfar, ..
+            } => Self {
+                aspect,
+                fovy: 0.0,
+                znear,
+                zfar,
+                ortho_ymag: Some(ymag),
+            },
         }
     }
 
@@ -50,6 +81,20 @@ impl Camera {
         }
     }
 
+    /// Move to a camera imported from glTF, its roll is dropped since this camera has none.
+    pub fn jump_to(&mut self, camera: &SceneCamera, width: u32, height: u32) {
+        let (_, _, position) = camera.transform.to_scale_rotation_translation();
+        let forward = camera.transform.transform_vector3(Vec3::NEG_Z).normalize();
+        self.position = position;
+        self.yaw = forward.z.atan2(forward.x);
+        self.pitch = forward
+            .y
+            .clamp(-1.0, 1.0)
+            .asin()
+            .clamp(-SAFE_FRAC_PI_2, SAFE_FRAC_PI_2);
+        self.proj = Projection::from_gltf(&camera.projection, width, height);
+    }
+
     pub fn calc_matrix(&self) -> Mat4 {
         let (sin_pitch, cos_pitch) = self.pitch.sin_cos();
         let (sin_yaw, cos_yaw) = self.yaw.sin_cos();
@@ -66,6 +111,24 @@ impl Camera {
     }
 
     pub fn calc_matrix_proj(&self) -> Mat4 {
+        if let Some(ymag) = self.proj.ortho_ymag {
+            let xmag = ymag * self.proj.aspect;
+            return Mat4::orthographic_rh(
+                -xmag,
+                xmag,
+                -ymag,
+                ymag,
+                self.proj.znear,
+                self.proj.zfar,
+            );
+        }
+        if self.proj.zfar.is_infinite() {
+            return Mat4::perspective_infinite_rh(
+                self.proj.fovy,
+                self.proj.aspect,
+                self.proj.znear,
+            );
+        }
         Mat4::perspective_rh(
             self.proj.fovy,
             self.proj.aspect,
@@ -0,0 +1,126 @@
+//! Reloading shaders and assets when their files change on disk.
+//!
+//! Only debug builds watch anything, in release [`FileWatcher::changed`] never reports
+//! and the embedded shaders stay in use.
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver};
+use std::time::{Duration, Instant};
+
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use snafu::Snafu;
+
+/// Editors save in several writes, wait for the file to settle before reporting it.
+const SETTLE: Duration = Duration::from_millis(100);
+
+#[derive(Debug, Snafu)]
+#[snafu(visibility(pub(crate)))]
+pub enum Error {
+    #[snafu(display("wgpu rejected the rebuild: {message}"))]
+    RebuildFailed { message: String },
+}
+
+/// Watched files that changed, polled once a frame.
+pub struct FileWatcher {
+    watcher: Option<RecommendedWatcher>,
+    events: Receiver<PathBuf>,
+    /// Canonical path to the path it was watched with
+    files: HashMap<PathBuf, PathBuf>,
+    dirs: HashSet<PathBuf>,
+    pending: HashMap<PathBuf, Instant>,
+}
+
+impl Default for FileWatcher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FileWatcher {
+    pub fn new() -> Self {
+        let (sender, events) = mpsc::channel();
+        let watcher = cfg!(debug_assertions)
+            .then(|| {
+                notify::recommended_watcher(move |event: notify::Result<notify::Event>| match event
+                {
+                    Ok(event)
+                        if matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) =>
+                    {
+                        for path in event.paths {
+                            let _ = sender.send(path);
+                        }
+                    }
+                    Ok(_) => {}
+                    Err(err) => log::warn!("file watch failed: {err}"),
+                })
+            })
+            .and_then(|watcher| {
+                watcher
+                    .map_err(|err| log::warn!("hot reload disabled: {err}"))
+                    .ok()
+            });
+        Self {
+            watcher,
+            events,
+            files: HashMap::new(),
+            dirs: HashSet::new(),
+            pending: HashMap::new(),
+        }
+    }
+
+    /// Report `path` from [`Self::changed`] when it is written.
+    pub fn watch<P: AsRef<Path>>(&mut self, path: P) {
+        let Some(watcher) = &mut self.watcher else {
+            return;
+        };
+        let path = path.as_ref();
+        let canonical = std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+        // Watch the directory, saving by renaming over the file would end a watch on the file
+        if let Some(dir) = canonical.parent() {
+            if !self.dirs.contains(dir) {
+                match watcher.watch(dir, RecursiveMode::NonRecursive) {
+                    Ok(()) => {
+                        self.dirs.insert(dir.to_path_buf());
+                    }
+                    Err(err) => log::warn!("failed to watch {}: {err}", dir.display()),
+                }
+            }
+        }
+        self.files.insert(canonical, path.to_path_buf());
+    }
+
+    /// Files written since the last call that have been quiet for a moment, as passed to
+    /// [`Self::watch`].
+    pub fn changed(&mut self) -> Vec<PathBuf> {
+        let now = Instant::now();
+        for path in self.events.try_iter() {
+            if let Some(watched) = self.files.get(&path) {
+                self.pending.insert(watched.clone(), now);
+            }
+        }
+        let mut changed = vec![];
+        self.pending.retain(|path, written| {
+            let settled = now.duration_since(*written) >= SETTLE;
+            if settled {
+                changed.push(path.clone());
+            }
+            !settled
+        });
+        changed
+    }
+}
+
+/// Run `create` in a validation error scope, so a pipeline that doesn't match its layout
+/// is returned as an error and the caller can keep the last good one.
+pub fn rebuild<T>(device: &wgpu::Device, create: impl FnOnce() -> T) -> Result<T, Error> {
+    device.push_error_scope(wgpu::ErrorFilter::Validation);
+    let created = create();
+    match pollster::block_on(device.pop_error_scope()) {
+        Some(err) => RebuildFailedSnafu {
+            message: err.to_string(),
+        }
+        .fail(),
+        None => Ok(created),
+    }
+}
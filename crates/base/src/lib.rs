@@ -1,13 +1,19 @@
 pub mod asset;
 pub mod camera;
+pub mod hot_reload;
+pub mod oit;
+pub mod scene;
+pub mod shader;
 pub mod texture;
 
 use std::time::Duration;
 
 use as_any::{AsAny, Downcast};
+use asset::server::AssetServer;
 use camera::{Camera, CameraController, CameraUniform};
 use env_logger::Env;
 use image::ImageError;
+use shader::ShaderComposer;
 use snafu::{Backtrace, Snafu};
 use texture::Texture;
 use typed_builder::TypedBuilder;
@@ -24,6 +30,8 @@ pub use bytemuck;
 pub use env_logger;
 pub use glam;
 pub use image;
+pub use log;
+pub use naga;
 pub use snafu;
 pub use wgpu;
 pub use winit;
@@ -47,7 +55,12 @@ pub enum Error {
     GLTFErr { source: asset::gltf::Error },
     NoneErr { backtrace: Backtrace },
     ImageLoadErr { source: ImageError },
+    Ktx2Err { source: asset::ktx2::Error },
     SurfaceErr { source: wgpu::SurfaceError },
+    AssetReadErr { path: String, source: std::io::Error },
+    ShaderErr { source: shader::Error },
+    /// A baked texture format the device lacks the feature for
+    UnsupportedTextureFormat { format: wgpu::TextureFormat },
 }
 
 pub struct BaseState {
@@ -68,6 +81,9 @@ pub struct BaseState {
 
     pub mouse_pressed: bool,
 
+    pub assets: AssetServer,
+    pub shaders: ShaderComposer,
+
     pub extra_state: Option<Box<dyn StateDynObj>>,
     pub render_fn: Option<RenderFn>,
     pub tick_fn: Option<TickFn>,
@@ -104,6 +120,9 @@ impl BaseState {
         }))
         .unwrap();
 
+        // Block compressed formats are opt-in, grab whatever the adapter offers for ktx2 textures
+        let features = features | (adapter.features() & asset::ktx2::COMPRESSION_FEATURES);
+
         let (device, queue) = pollster::block_on(adapter.request_device(
             &wgpu::DeviceDescriptor {
                 features,
@@ -194,6 +213,8 @@ impl BaseState {
             camera_controller,
             camera_uniform,
             mouse_pressed: false,
+            assets: AssetServer::default(),
+            shaders: ShaderComposer::default(),
             camera_bind_group,
             camera_buffer,
             camera_bind_group_layout,
@@ -0,0 +1,47 @@
+// Downsample one mip level into the next one.
+// Reading through textureLoad decodes sRGB and the render target encodes it again,
+// so the filtering happens in linear space.
+
+@group(0) @binding(0)
+var src: texture_2d<f32>;
+
+@vertex
+fn vs_main(@builtin(vertex_index) id: u32) -> @builtin(position) vec4<f32> {
+    // Fullscreen triangle
+    let uv = vec2<f32>(f32((id << 1u) & 2u), f32(id & 2u));
+    return vec4<f32>(uv * 2.0 - 1.0, 0.0, 1.0);
+}
+
+// Box filter weights along one axis, an odd source size spreads each texel over 3 source texels
+fn axis_weights(dst: i32, src_size: i32) -> vec3<f32> {
+    if src_size == 1 {
+        return vec3<f32>(1.0, 0.0, 0.0);
+    }
+    if src_size % 2 == 0 {
+        return vec3<f32>(0.5, 0.5, 0.0);
+    }
+    let dst_size = f32(src_size / 2);
+    let d = f32(dst);
+    return vec3<f32>(dst_size - d, dst_size, d + 1.0) / (2.0 * dst_size + 1.0);
+}
+
+@fragment
+fn fs_main(@builtin(position) frag_pos: vec4<f32>) -> @location(0) vec4<f32> {
+    let src_size = vec2<i32>(textureDimensions(src));
+    let dst = vec2<i32>(frag_pos.xy);
+    let base = select(dst * 2, vec2<i32>(0), src_size == vec2<i32>(1));
+    let wx = axis_weights(dst.x, src_size.x);
+    let wy = axis_weights(dst.y, src_size.y);
+
+    var color = vec4<f32>(0.0);
+    for (var y = 0; y < 3; y++) {
+        for (var x = 0; x < 3; x++) {
+            let w = wx[x] * wy[y];
+            if w > 0.0 {
+                let coord = min(base + vec2<i32>(x, y), src_size - 1);
+                color += textureLoad(src, coord, 0) * w;
+            }
+        }
+    }
+    return color;
+}
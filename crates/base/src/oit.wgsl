@@ -0,0 +1,33 @@
+// Weighted blended OIT composite, resolves accumulation and revealage over the opaque color.
+
+@group(0) @binding(0)
+var accum_tex: texture_2d<f32>;
+@group(0) @binding(1)
+var revealage_tex: texture_2d<f32>;
+
+@vertex
+fn vs_main(@builtin(vertex_index) id: u32) -> @builtin(position) vec4<f32> {
+    // Fullscreen triangle
+    let uv = vec2<f32>(f32((id << 1u) & 2u), f32(id & 2u));
+    return vec4<f32>(uv * 2.0 - 1.0, 0.0, 1.0);
+}
+
+@fragment
+fn fs_main(@builtin(position) frag_pos: vec4<f32>) -> @location(0) vec4<f32> {
+    let coord = vec2<i32>(frag_pos.xy);
+    let revealage = textureLoad(revealage_tex, coord, 0).r;
+    // Nothing transparent covers this pixel
+    if revealage >= 0.9999 {
+        discard;
+    }
+
+    var accum = textureLoad(accum_tex, coord, 0);
+    // Too many bright layers can overflow the f16 target
+    if any(abs(accum) > vec4<f32>(65504.0)) {
+        accum = vec4<f32>(accum.aaa, accum.a);
+    }
+    let average = accum.rgb / max(accum.a, 0.00001);
+
+    // Blended with SrcAlpha, OneMinusSrcAlpha
+    return vec4<f32>(average, 1.0 - revealage);
+}
@@ -0,0 +1,1296 @@
+use std::collections::BTreeMap;
+use std::mem::size_of;
+use std::num::NonZeroU32;
+use std::ops::Range;
+
+use glam::{Mat4, Vec3};
+use snafu::{ensure, OptionExt, ResultExt};
+use wgpu::util::{BufferInitDescriptor, DeviceExt, DrawIndexedIndirect};
+
+use crate::asset::gltf::{
+    AlphaMode, GLTFBuffer, ImageData, Material, MaterialKey, Mesh, MeshKey, SInto, SceneView,
+    TextureData,
+};
+use crate::asset::ktx2::{self, Ktx2Image};
+use crate::texture::{texture_bytes, MipmapGenerator};
+use crate::{Error, Ktx2ErrSnafu, NoneErrSnafu, UnsupportedTextureFormatSnafu};
+
+/// Per instance data, fed as an instance rate vertex buffer at locations 8 and 14..=17.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct PerObjData {
+    pub transform: [[f32; 4]; 4],
+    pub material: u32,
+    _padding: [u32; 3],
+}
+
+/// `PerObjData::material` comes right after the transform.
+const PER_OBJ_MATERIAL_OFFSET: u64 = size_of::<[[f32; 4]; 4]>() as u64;
+
+/// Material record in the scene's storage buffer, missing textures point at the fallbacks.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct GpuMaterial {
+    pub base_color_factor: [f32; 4],
+    pub base_color: u32,
+    pub base_color_sampler: u32,
+    pub normal: u32,
+    pub normal_sampler: u32,
+    pub metallic_roughness: u32,
+    pub metallic_roughness_sampler: u32,
+    pub metallic_factor: f32,
+    pub roughness_factor: f32,
+    /// rgb factor premultiplied by the emissive strength
+    pub emissive_factor: [f32; 4],
+    pub emissive: u32,
+    pub emissive_sampler: u32,
+    pub occlusion: u32,
+    pub occlusion_sampler: u32,
+    pub normal_scale: f32,
+    pub occlusion_strength: f32,
+    /// MASK cutoff, 0 for the other alpha modes so nothing gets discarded
+    pub alpha_cutoff: f32,
+    /// One of [`shading_model`]
+    pub shading_model: u32,
+    /// `KHR_texture_transform` of every texture as a WGSL `mat3x3`, columns padded to vec4
+    pub base_color_transform: [[f32; 4]; 3],
+    pub normal_transform: [[f32; 4]; 3],
+    pub metallic_roughness_transform: [[f32; 4]; 3],
+    pub emissive_transform: [[f32; 4]; 3],
+    pub occlusion_transform: [[f32; 4]; 3],
+    pub clearcoat_transform: [[f32; 4]; 3],
+    pub clearcoat_roughness_transform: [[f32; 4]; 3],
+    pub sheen_color_transform: [[f32; 4]; 3],
+    pub sheen_roughness_transform: [[f32; 4]; 3],
+    pub transmission_transform: [[f32; 4]; 3],
+    pub specular_transform: [[f32; 4]; 3],
+    pub specular_color_transform: [[f32; 4]; 3],
+    /// Bits of [`material_features`], lighting skips the lobes of unset bits
+    pub features: u32,
+    pub ior: f32,
+    pub clearcoat_factor: f32,
+    pub clearcoat_roughness_factor: f32,
+    pub clearcoat: u32,
+    pub clearcoat_sampler: u32,
+    pub clearcoat_roughness: u32,
+    pub clearcoat_roughness_sampler: u32,
+    pub sheen_color: u32,
+    pub sheen_color_sampler: u32,
+    pub sheen_roughness: u32,
+    pub sheen_roughness_sampler: u32,
+    pub transmission: u32,
+    pub transmission_sampler: u32,
+    pub specular: u32,
+    pub specular_sampler: u32,
+    pub specular_color: u32,
+    pub specular_color_sampler: u32,
+    pub transmission_factor: f32,
+    /// The thickness texture isn't sampled, only the factor is used
+    pub thickness_factor: f32,
+    pub sheen_color_factor: [f32; 3],
+    pub sheen_roughness_factor: f32,
+    pub specular_color_factor: [f32; 3],
+    pub specular_factor: f32,
+    pub attenuation_color: [f32; 3],
+    pub attenuation_distance: f32,
+    /// `texCoord` of every texture, 1 samples `TEXCOORD_1` and anything else `TEXCOORD_0`
+    pub base_color_tex_coord: u32,
+    pub normal_tex_coord: u32,
+    pub metallic_roughness_tex_coord: u32,
+    pub emissive_tex_coord: u32,
+    pub occlusion_tex_coord: u32,
+    pub clearcoat_tex_coord: u32,
+    pub clearcoat_roughness_tex_coord: u32,
+    pub sheen_color_tex_coord: u32,
+    pub sheen_roughness_tex_coord: u32,
+    pub transmission_tex_coord: u32,
+    pub specular_tex_coord: u32,
+    pub specular_color_tex_coord: u32,
+}
+
+/// Values of [`GpuMaterial::shading_model`], deferred samples carry it through the gbuffer.
+pub mod shading_model {
+    pub const LIT: u32 = 0;
+    /// `KHR_materials_unlit`, the lighting outputs the base color as is
+    pub const UNLIT: u32 = 1;
+}
+
+/// Bits of [`GpuMaterial::features`], one per material extension with a BRDF lobe.
+pub mod material_features {
+    pub const CLEARCOAT: u32 = 1;
+    pub const SHEEN: u32 = 1 << 1;
+    pub const TRANSMISSION: u32 = 1 << 2;
+    pub const SPECULAR: u32 = 1 << 3;
+    pub const VOLUME: u32 = 1 << 4;
+}
+
+/// Texture ids of the 1x1 textures appended after the scene images.
+#[derive(Debug, Clone, Copy)]
+pub struct FallbackTextures {
+    pub white: u32,
+    pub flat_normal: u32,
+    pub linear_white: u32,
+}
+
+impl GpuMaterial {
+    pub fn new(material: &Material, fallback: &FallbackTextures) -> Self {
+        let texture_of = |data: Option<&TextureData>, fallback: u32| {
+            data.map_or((fallback, 0), |i| {
+                (
+                    i.image_id.map_or(fallback, |id| id as u32),
+                    i.sampler as u32,
+                )
+            })
+        };
+        let texture = |key: MaterialKey, fallback: u32| texture_of(material.get(&key), fallback);
+        let (base_color, base_color_sampler) = texture(MaterialKey::BaseColor, fallback.white);
+        let (normal, normal_sampler) = texture(MaterialKey::Normal, fallback.flat_normal);
+        let (metallic_roughness, metallic_roughness_sampler) =
+            texture(MaterialKey::MetallicRoughness, fallback.linear_white);
+        let (emissive, emissive_sampler) = texture(MaterialKey::Emissive, fallback.white);
+        let (occlusion, occlusion_sampler) = texture(MaterialKey::Occlusion, fallback.linear_white);
+        let transform_of = |data: Option<&TextureData>| {
+            let m = data.map(|i| i.transform.matrix()).unwrap_or_default();
+            [
+                m.x_axis.extend(0.0).to_array(),
+                m.y_axis.extend(0.0).to_array(),
+                m.z_axis.extend(0.0).to_array(),
+            ]
+        };
+        let tex_coord_of = |data: Option<&TextureData>| data.map_or(0, |i| i.tex_coord as u32);
+        let uv_transform = |key: MaterialKey| transform_of(material.get(&key));
+        let tex_coord = |key: MaterialKey| tex_coord_of(material.get(&key));
+        // factor of the metallic roughness texture is stored as [_, roughness, metallic, _]
+        let mr_factor = material
+            .get(&MaterialKey::MetallicRoughness)
+            .map_or([1.0; 4], |i| i.factor);
+        let [r, g, b, strength] = material
+            .get(&MaterialKey::Emissive)
+            .map_or([0.0, 0.0, 0.0, 1.0], |i| i.factor);
+
+        let clearcoat = material.clearcoat.as_ref();
+        let sheen = material.sheen.as_ref();
+        let transmission = material.transmission.as_ref();
+        let specular = material.specular.as_ref();
+        let volume = material.volume.as_ref();
+        let mut features = 0;
+        for (enabled, bit) in [
+            (clearcoat.is_some(), material_features::CLEARCOAT),
+            (sheen.is_some(), material_features::SHEEN),
+            (transmission.is_some(), material_features::TRANSMISSION),
+            (specular.is_some(), material_features::SPECULAR),
+            (volume.is_some(), material_features::VOLUME),
+        ] {
+            if enabled {
+                features |= bit;
+            }
+        }
+        let clearcoat_data = clearcoat.and_then(|i| i.texture.as_ref());
+        let clearcoat_roughness_data = clearcoat.and_then(|i| i.roughness_texture.as_ref());
+        let sheen_color_data = sheen.and_then(|i| i.color_texture.as_ref());
+        let sheen_roughness_data = sheen.and_then(|i| i.roughness_texture.as_ref());
+        let transmission_data = transmission.and_then(|i| i.texture.as_ref());
+        let specular_data = specular.and_then(|i| i.texture.as_ref());
+        let specular_color_data = specular.and_then(|i| i.color_texture.as_ref());
+        let (clearcoat_texture, clearcoat_sampler) =
+            texture_of(clearcoat_data, fallback.linear_white);
+        let (clearcoat_roughness, clearcoat_roughness_sampler) =
+            texture_of(clearcoat_roughness_data, fallback.linear_white);
+        let (sheen_color, sheen_color_sampler) = texture_of(sheen_color_data, fallback.white);
+        let (sheen_roughness, sheen_roughness_sampler) =
+            texture_of(sheen_roughness_data, fallback.linear_white);
+        let (transmission_texture, transmission_sampler) =
+            texture_of(transmission_data, fallback.linear_white);
+        let (specular_texture, specular_sampler) = texture_of(specular_data, fallback.linear_white);
+        let (specular_color, specular_color_sampler) =
+            texture_of(specular_color_data, fallback.white);
+
+        Self {
+            base_color_factor: material
+                .get(&MaterialKey::BaseColor)
+                .map_or([1.0; 4], |i| i.factor),
+            base_color,
+            base_color_sampler,
+            normal,
+            normal_sampler,
+            metallic_roughness,
+            metallic_roughness_sampler,
+            metallic_factor: mr_factor[2],
+            roughness_factor: mr_factor[1],
+            emissive_factor: [r * strength, g * strength, b * strength, 0.0],
+            emissive,
+            emissive_sampler,
+            occlusion,
+            occlusion_sampler,
+            normal_scale: material
+                .get(&MaterialKey::Normal)
+                .and_then(|i| i.scale)
+                .unwrap_or(1.0),
+            occlusion_strength: material
+                .get(&MaterialKey::Occlusion)
+                .and_then(|i| i.strength)
+                .unwrap_or(1.0),
+            alpha_cutoff: match material.alpha_mode {
+                AlphaMode::Mask => material.alpha_cutoff,
+                AlphaMode::Opaque | AlphaMode::Blend => 0.0,
+            },
+            shading_model: if material.unlit {
+                shading_model::UNLIT
+            } else {
+                shading_model::LIT
+            },
+            base_color_transform: uv_transform(MaterialKey::BaseColor),
+            normal_transform: uv_transform(MaterialKey::Normal),
+            metallic_roughness_transform: uv_transform(MaterialKey::MetallicRoughness),
+            emissive_transform: uv_transform(MaterialKey::Emissive),
+            occlusion_transform: uv_transform(MaterialKey::Occlusion),
+            clearcoat_transform: transform_of(clearcoat_data),
+            clearcoat_roughness_transform: transform_of(clearcoat_roughness_data),
+            sheen_color_transform: transform_of(sheen_color_data),
+            sheen_roughness_transform: transform_of(sheen_roughness_data),
+            transmission_transform: transform_of(transmission_data),
+            specular_transform: transform_of(specular_data),
+            specular_color_transform: transform_of(specular_color_data),
+            features,
+            ior: material.ior,
+            clearcoat_factor: clearcoat.map_or(0.0, |i| i.factor),
+            clearcoat_roughness_factor: clearcoat.map_or(0.0, |i| i.roughness_factor),
+            clearcoat: clearcoat_texture,
+            clearcoat_sampler,
+            clearcoat_roughness,
+            clearcoat_roughness_sampler,
+            sheen_color,
+            sheen_color_sampler,
+            sheen_roughness,
+            sheen_roughness_sampler,
+            transmission: transmission_texture,
+            transmission_sampler,
+            specular: specular_texture,
+            specular_sampler,
+            specular_color,
+            specular_color_sampler,
+            transmission_factor: transmission.map_or(0.0, |i| i.factor),
+            thickness_factor: volume.map_or(0.0, |i| i.thickness_factor),
+            sheen_color_factor: sheen.map_or([0.0; 3], |i| i.color_factor),
+            sheen_roughness_factor: sheen.map_or(0.0, |i| i.roughness_factor),
+            specular_color_factor: specular.map_or([1.0; 3], |i| i.color_factor),
+            specular_factor: specular.map_or(1.0, |i| i.factor),
+            attenuation_color: volume.map_or([1.0; 3], |i| i.attenuation_color),
+            attenuation_distance: volume.map_or(f32::INFINITY, |i| i.attenuation_distance),
+            base_color_tex_coord: tex_coord(MaterialKey::BaseColor),
+            normal_tex_coord: tex_coord(MaterialKey::Normal),
+            metallic_roughness_tex_coord: tex_coord(MaterialKey::MetallicRoughness),
+            emissive_tex_coord: tex_coord(MaterialKey::Emissive),
+            occlusion_tex_coord: tex_coord(MaterialKey::Occlusion),
+            clearcoat_tex_coord: tex_coord_of(clearcoat_data),
+            clearcoat_roughness_tex_coord: tex_coord_of(clearcoat_roughness_data),
+            sheen_color_tex_coord: tex_coord_of(sheen_color_data),
+            sheen_roughness_tex_coord: tex_coord_of(sheen_roughness_data),
+            transmission_tex_coord: tex_coord_of(transmission_data),
+            specular_tex_coord: tex_coord_of(specular_data),
+            specular_color_tex_coord: tex_coord_of(specular_color_data),
+        }
+    }
+
+    /// The glTF default material, used by primitives without one.
+    pub fn gltf_default(fallback: &FallbackTextures) -> Self {
+        Self::new(&Material::default(), fallback)
+    }
+}
+
+const POSITION_ATTRS: [wgpu::VertexAttribute; 1] = wgpu::vertex_attr_array![0 => Float32x3];
+const NORMAL_ATTRS: [wgpu::VertexAttribute; 1] = wgpu::vertex_attr_array![1 => Float32x3];
+const UV0_ATTRS: [wgpu::VertexAttribute; 1] = wgpu::vertex_attr_array![2 => Float32x2];
+const UV1_ATTRS: [wgpu::VertexAttribute; 1] = wgpu::vertex_attr_array![5 => Float32x2];
+const TANGENT_ATTRS: [wgpu::VertexAttribute; 1] = wgpu::vertex_attr_array![3 => Float32x4];
+const BI_TANGENT_ATTRS: [wgpu::VertexAttribute; 1] = wgpu::vertex_attr_array![4 => Float32x3];
+const OBJECT_ATTRS: [wgpu::VertexAttribute; 5] = wgpu::vertex_attr_array![
+    14 => Float32x4,
+    15 => Float32x4,
+    16 => Float32x4,
+    17 => Float32x4,
+
+    8 => Uint32,
+];
+
+/// A BLEND draw, sorted back to front every frame.
+#[derive(Debug, Clone, Copy)]
+pub struct BlendDraw {
+    /// Index of the command in the indirect buffer
+    pub draw: u32,
+    /// World space center of the mesh bounds
+    pub center: Vec3,
+    pub double_sided: bool,
+}
+
+/// A draw whose material depends on the active `KHR_materials_variants` variant.
+#[derive(Debug)]
+struct VariantDraw {
+    /// Range of the draw's instances in `obj_buf`
+    instances: Range<u32>,
+    default_material: u32,
+    variant_materials: BTreeMap<usize, u32>,
+}
+
+impl VariantDraw {
+    fn material(&self, variant: Option<usize>) -> u32 {
+        variant
+            .and_then(|i| self.variant_materials.get(&i).copied())
+            .unwrap_or(self.default_material)
+    }
+}
+
+/// Indirect commands are grouped in this order so each group is one contiguous range.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum DrawGroup {
+    Culled,
+    DoubleSided,
+    Blend,
+}
+
+impl DrawGroup {
+    /// BLEND draws sort as their own group whatever their culling, primitives without a
+    /// material get the single sided glTF default.
+    fn of(material: Option<&Material>) -> Self {
+        match material {
+            Some(i) if i.alpha_mode == AlphaMode::Blend => Self::Blend,
+            Some(i) if i.double_sided => Self::DoubleSided,
+            _ => Self::Culled,
+        }
+    }
+}
+
+/// A loaded glTF scene living on the gpu, drawn with one `multi_draw_indexed_indirect`.
+pub struct GpuScene {
+    pub positions: wgpu::Buffer,
+    pub normals: wgpu::Buffer,
+    pub uv0: wgpu::Buffer,
+    pub uv1: wgpu::Buffer,
+    pub tangents: wgpu::Buffer,
+    pub bi_tangents: wgpu::Buffer,
+    pub index: wgpu::Buffer,
+    pub obj_buf: wgpu::Buffer,
+    pub indirect_buf: wgpu::Buffer,
+    pub draw_count: u32,
+    /// OPAQUE and MASK draws with back face culling
+    pub culled_draws: Range<u32>,
+    /// OPAQUE and MASK draws of double sided materials
+    pub double_sided_draws: Range<u32>,
+    pub blend_draws: Vec<BlendDraw>,
+    variant_draws: Vec<VariantDraw>,
+    active_variant: Option<usize>,
+    pub materials: wgpu::Buffer,
+
+    pub textures: Vec<wgpu::Texture>,
+    pub samplers: Vec<wgpu::Sampler>,
+    pub fallback: FallbackTextures,
+    /// Texture array at binding 0, sampler array at binding 1, materials at binding 2.
+    pub tex_bind_group_layout: wgpu::BindGroupLayout,
+    pub tex_bind_group: wgpu::BindGroup,
+}
+
+impl GpuScene {
+    pub const POSITION_LAYOUT: wgpu::VertexBufferLayout<'static> = wgpu::VertexBufferLayout {
+        array_stride: wgpu::VertexFormat::Float32x3.size(),
+        step_mode: wgpu::VertexStepMode::Vertex,
+        attributes: &POSITION_ATTRS,
+    };
+    pub const NORMAL_LAYOUT: wgpu::VertexBufferLayout<'static> = wgpu::VertexBufferLayout {
+        array_stride: wgpu::VertexFormat::Float32x3.size(),
+        step_mode: wgpu::VertexStepMode::Vertex,
+        attributes: &NORMAL_ATTRS,
+    };
+    pub const UV0_LAYOUT: wgpu::VertexBufferLayout<'static> = wgpu::VertexBufferLayout {
+        array_stride: wgpu::VertexFormat::Float32x2.size(),
+        step_mode: wgpu::VertexStepMode::Vertex,
+        attributes: &UV0_ATTRS,
+    };
+    pub const UV1_LAYOUT: wgpu::VertexBufferLayout<'static> = wgpu::VertexBufferLayout {
+        array_stride: wgpu::VertexFormat::Float32x2.size(),
+        step_mode: wgpu::VertexStepMode::Vertex,
+        attributes: &UV1_ATTRS,
+    };
+    pub const TANGENT_LAYOUT: wgpu::VertexBufferLayout<'static> = wgpu::VertexBufferLayout {
+        array_stride: wgpu::VertexFormat::Float32x4.size(),
+        step_mode: wgpu::VertexStepMode::Vertex,
+        attributes: &TANGENT_ATTRS,
+    };
+    pub const BI_TANGENT_LAYOUT: wgpu::VertexBufferLayout<'static> = wgpu::VertexBufferLayout {
+        array_stride: wgpu::VertexFormat::Float32x3.size(),
+        step_mode: wgpu::VertexStepMode::Vertex,
+        attributes: &BI_TANGENT_ATTRS,
+    };
+    pub const OBJECT_LAYOUT: wgpu::VertexBufferLayout<'static> = wgpu::VertexBufferLayout {
+        array_stride: size_of::<PerObjData>() as u64,
+        step_mode: wgpu::VertexStepMode::Instance,
+        attributes: &OBJECT_ATTRS,
+    };
+
+    /// Vertex buffer layouts in the slot order `draw_indirect` binds them.
+    pub fn vertex_layouts() -> [wgpu::VertexBufferLayout<'static>; 7] {
+        [
+            Self::POSITION_LAYOUT,
+            Self::NORMAL_LAYOUT,
+            Self::UV0_LAYOUT,
+            Self::UV1_LAYOUT,
+            Self::TANGENT_LAYOUT,
+            Self::BI_TANGENT_LAYOUT,
+            Self::OBJECT_LAYOUT,
+        ]
+    }
+
+    pub fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        scene_view: &SceneView,
+        scene_buffer: &GLTFBuffer,
+    ) -> Result<Self, Error> {
+        let textures = load_textures(device, queue, scene_view, scene_buffer)?;
+        Self::with_textures(device, queue, scene_view, scene_buffer, textures)
+    }
+
+    /// `textures` holds the uploaded `scene_view.images` in order.
+    fn with_textures(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        scene_view: &SceneView,
+        scene_buffer: &GLTFBuffer,
+        mut textures: Vec<wgpu::Texture>,
+    ) -> Result<Self, Error> {
+        let positions = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("vertex"),
+            contents: &scene_buffer.positions,
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+        let index = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("index"),
+            contents: &scene_buffer.index,
+            usage: wgpu::BufferUsages::INDEX,
+        });
+        let normals = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("normal"),
+            contents: &scene_buffer.normal,
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+        let uv0 = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("uv0"),
+            contents: scene_buffer.texcoord.first().context(NoneErrSnafu)?,
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+        let uv1 = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("uv1"),
+            contents: scene_buffer.texcoord.get(1).context(NoneErrSnafu)?,
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+        let tangents = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("tangent"),
+            contents: &scene_buffer.tangent,
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+        let bi_tangents = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("bi_tangent"),
+            contents: &scene_buffer.bi_tangent,
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+
+        let fallback = create_fallback_textures(device, queue, &mut textures);
+
+        let mut materials: Vec<GpuMaterial> = scene_view
+            .materials
+            .iter()
+            .map(|i| GpuMaterial::new(i, &fallback))
+            .collect();
+        let default_material = materials.len() as u32;
+        materials.push(GpuMaterial::gltf_default(&fallback));
+        let materials = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("materials"),
+            contents: bytemuck::cast_slice(&materials),
+            usage: wgpu::BufferUsages::STORAGE,
+        });
+
+        //Collect Indirect commands&generate per object buffer,
+        //one command per unique mesh with an instance for every node using it
+        let mut instances: BTreeMap<MeshKey, Vec<Mat4>> = BTreeMap::new();
+        for node in scene_view.nodes.values() {
+            for key in &node.meshes {
+                instances
+                    .entry(*key)
+                    .or_default()
+                    .extend(node.instance_transforms());
+            }
+        }
+        let mut draws = vec![];
+        for (key, transforms) in &instances {
+            let Some(mesh) = scene_view.meshes.get(key) else {
+                continue;
+            };
+            let material = mesh.mat.and_then(|i| scene_view.materials.get(i));
+            let double_sided = material.is_some_and(|i| i.double_sided);
+            let group = DrawGroup::of(material);
+            // Instances of a BLEND mesh are sorted as one, around their average center
+            let center = mesh_center(mesh, scene_buffer);
+            let center = transforms
+                .iter()
+                .map(|i| i.transform_point3(center))
+                .sum::<Vec3>()
+                / transforms.len() as f32;
+            draws.push((group, double_sided, center, mesh, transforms));
+        }
+        draws.sort_by_key(|i| i.0);
+        let group_len = |group| draws.iter().filter(|i| i.0 == group).count() as u32;
+        let culled_draws = 0..group_len(DrawGroup::Culled);
+        let double_sided_draws =
+            culled_draws.end..culled_draws.end + group_len(DrawGroup::DoubleSided);
+
+        let mut indirect = Vec::with_capacity(draws.len());
+        let mut per_obj_data = vec![];
+        let mut blend_draws = vec![];
+        let mut variant_draws = vec![];
+        for (group, double_sided, center, mesh, transforms) in draws {
+            let draw = indirect.len() as u32;
+            let first_instance = per_obj_data.len() as u32;
+            let instance_count = transforms.len() as u32;
+            let material = mesh.mat.map_or(default_material, |i| i as u32);
+            if !mesh.variant_materials.is_empty() {
+                variant_draws.push(VariantDraw {
+                    instances: first_instance..first_instance + instance_count,
+                    default_material: material,
+                    variant_materials: mesh
+                        .variant_materials
+                        .iter()
+                        .map(|(variant, material)| (*variant, *material as u32))
+                        .collect(),
+                });
+            }
+            if group == DrawGroup::Blend {
+                blend_draws.push(BlendDraw {
+                    draw,
+                    center,
+                    double_sided,
+                });
+            }
+            indirect.push(DrawIndexedIndirect {
+                vertex_count: mesh.index.count as u32,
+                instance_count,
+                base_index: (mesh.index.indices.start / mesh.index.type_size) as u32,
+                vertex_offset: (mesh.positions.start / mesh.vertex_type_size) as i32,
+                base_instance: first_instance,
+            });
+            per_obj_data.extend(transforms.iter().map(|transform| PerObjData {
+                transform: transform.to_cols_array_2d(),
+                material,
+                _padding: [0; 3],
+            }));
+        }
+
+        let obj_buf = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Per obj buffer"),
+            contents: bytemuck::cast_slice(&per_obj_data),
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let indirect_bytes: Vec<u8> = indirect
+            .iter()
+            .flat_map(|i| i.as_bytes())
+            .copied()
+            .collect();
+        let indirect_buf = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("indirect"),
+            contents: &indirect_bytes,
+            usage: wgpu::BufferUsages::INDIRECT,
+        });
+
+        let samplers = create_samplers(device, scene_view);
+        let (tex_bind_group_layout, tex_bind_group) =
+            create_tex_bind_group(device, &textures, &samplers, &materials);
+
+        Ok(Self {
+            positions,
+            normals,
+            uv0,
+            uv1,
+            tangents,
+            bi_tangents,
+            index,
+            obj_buf,
+            indirect_buf,
+            draw_count: indirect.len() as u32,
+            culled_draws,
+            double_sided_draws,
+            blend_draws,
+            variant_draws,
+            active_variant: None,
+            materials,
+            textures,
+            fallback,
+            samplers,
+            tex_bind_group_layout,
+            tex_bind_group,
+        })
+    }
+
+    /// Bytes of every buffer and texture of the scene.
+    pub fn gpu_bytes(&self) -> u64 {
+        let buffers = [
+            &self.positions,
+            &self.normals,
+            &self.uv0,
+            &self.uv1,
+            &self.tangents,
+            &self.bi_tangents,
+            &self.index,
+            &self.obj_buf,
+            &self.indirect_buf,
+            &self.materials,
+        ];
+        buffers.iter().map(|i| i.size()).sum::<u64>()
+            + self.textures.iter().map(texture_bytes).sum::<u64>()
+    }
+
+    /// The `KHR_materials_variants` variant in use, `None` for the default materials.
+    pub fn active_variant(&self) -> Option<usize> {
+        self.active_variant
+    }
+
+    /// Switch to the variant at `variant` in [`SceneView::variants`], `None` goes back to the
+    /// default materials.
+    ///
+    /// Only the material index of the draws whose material changes is rewritten, so a draw
+    /// keeps the alpha mode and culling group of its default material.
+    pub fn set_variant(&mut self, queue: &wgpu::Queue, variant: Option<usize>) {
+        for i in &self.variant_draws {
+            let material = i.material(variant);
+            if material == i.material(self.active_variant) {
+                continue;
+            }
+            for instance in i.instances.clone() {
+                let offset =
+                    instance as u64 * size_of::<PerObjData>() as u64 + PER_OBJ_MATERIAL_OFFSET;
+                queue.write_buffer(&self.obj_buf, offset, bytemuck::bytes_of(&material));
+            }
+        }
+        self.active_variant = variant;
+    }
+
+    fn set_buffers<'a>(&'a self, pass: &mut wgpu::RenderPass<'a>) {
+        pass.set_vertex_buffer(0, self.positions.slice(..));
+        pass.set_vertex_buffer(1, self.normals.slice(..));
+        pass.set_vertex_buffer(2, self.uv0.slice(..));
+        pass.set_vertex_buffer(3, self.uv1.slice(..));
+        pass.set_vertex_buffer(4, self.tangents.slice(..));
+        pass.set_vertex_buffer(5, self.bi_tangents.slice(..));
+        pass.set_vertex_buffer(6, self.obj_buf.slice(..));
+        pass.set_index_buffer(self.index.slice(..), wgpu::IndexFormat::Uint32);
+    }
+
+    /// Bind the scene's vertex and index buffers and draw every mesh,
+    /// the caller sets the pipeline and `tex_bind_group`.
+    pub fn draw_indirect<'a>(&'a self, pass: &mut wgpu::RenderPass<'a>) {
+        self.set_buffers(pass);
+        pass.multi_draw_indexed_indirect(&self.indirect_buf, 0, self.draw_count);
+    }
+
+    /// Draw the OPAQUE and MASK meshes, `double_sided` is the same pipeline without culling.
+    pub fn draw_opaque<'a>(
+        &'a self,
+        pass: &mut wgpu::RenderPass<'a>,
+        culled: &'a wgpu::RenderPipeline,
+        double_sided: &'a wgpu::RenderPipeline,
+    ) {
+        self.set_buffers(pass);
+        for (pipeline, draws) in [
+            (culled, &self.culled_draws),
+            (double_sided, &self.double_sided_draws),
+        ] {
+            if draws.is_empty() {
+                continue;
+            }
+            pass.set_pipeline(pipeline);
+            pass.multi_draw_indexed_indirect(
+                &self.indirect_buf,
+                draws.start as u64 * INDIRECT_STRIDE,
+                draws.len() as u32,
+            );
+        }
+    }
+
+    /// Draw the BLEND meshes back to front as seen from `view_pos`.
+    pub fn draw_blend<'a>(
+        &'a self,
+        pass: &mut wgpu::RenderPass<'a>,
+        view_pos: Vec3,
+        culled: &'a wgpu::RenderPipeline,
+        double_sided: &'a wgpu::RenderPipeline,
+    ) {
+        if self.blend_draws.is_empty() {
+            return;
+        }
+        let mut sorted: Vec<&BlendDraw> = self.blend_draws.iter().collect();
+        sorted.sort_by(|a, b| {
+            let a = a.center.distance_squared(view_pos);
+            let b = b.center.distance_squared(view_pos);
+            b.total_cmp(&a)
+        });
+
+        self.set_buffers(pass);
+        let mut bound = None;
+        for draw in sorted {
+            if bound != Some(draw.double_sided) {
+                pass.set_pipeline(if draw.double_sided {
+                    double_sided
+                } else {
+                    culled
+                });
+                bound = Some(draw.double_sided);
+            }
+            pass.draw_indexed_indirect(&self.indirect_buf, draw.draw as u64 * INDIRECT_STRIDE);
+        }
+    }
+}
+
+const INDIRECT_STRIDE: u64 = size_of::<DrawIndexedIndirect>() as u64;
+
+/// Center of the mesh bounds in model space.
+fn mesh_center(mesh: &Mesh, scene_buffer: &GLTFBuffer) -> Vec3 {
+    let (min, max) = scene_buffer.positions[mesh.positions.clone()]
+        .chunks_exact(size_of::<[f32; 3]>())
+        .map(|i| Vec3::from(bytemuck::pod_read_unaligned::<[f32; 3]>(i)))
+        .fold((Vec3::MAX, Vec3::MIN), |(min, max), i| {
+            (min.min(i), max.max(i))
+        });
+    if min.cmple(max).all() {
+        (min + max) * 0.5
+    } else {
+        Vec3::ZERO
+    }
+}
+
+/// Spreads the texture upload of a loaded scene over several frames, call
+/// [`GpuSceneUpload::step`] once per frame until it hands back the [`GpuScene`].
+///
+/// Images come decoded from the loader, each one only costs a copy and its mip chain here.
+pub struct GpuSceneUpload {
+    mipmap_generator: MipmapGenerator,
+    textures: Vec<wgpu::Texture>,
+}
+
+impl GpuSceneUpload {
+    pub fn new(device: &wgpu::Device) -> Self {
+        Self {
+            mipmap_generator: MipmapGenerator::new(device),
+            textures: vec![],
+        }
+    }
+
+    /// Images uploaded so far.
+    pub fn uploaded(&self) -> usize {
+        self.textures.len()
+    }
+
+    /// Upload up to `max_images` more images, the scene is built and returned
+    /// once all of them are on the GPU. Pass the same `scene_view` and `scene_buffer` every frame.
+    pub fn step(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        scene_view: &SceneView,
+        scene_buffer: &GLTFBuffer,
+        max_images: usize,
+    ) -> Result<Option<GpuScene>, Error> {
+        let pending = scene_view.images.iter().skip(self.textures.len());
+        for img_info in pending.take(max_images) {
+            let texture = upload_image(
+                device,
+                queue,
+                &mut self.mipmap_generator,
+                img_info,
+                scene_buffer,
+            )?;
+            self.textures.push(texture);
+        }
+        if self.textures.len() < scene_view.images.len() {
+            return Ok(None);
+        }
+        let textures = std::mem::take(&mut self.textures);
+        GpuScene::with_textures(device, queue, scene_view, scene_buffer, textures).map(Some)
+    }
+}
+
+fn load_textures(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    scene_view: &SceneView,
+    scene_buffer: &GLTFBuffer,
+) -> Result<Vec<wgpu::Texture>, Error> {
+    let mut mipmap_generator = MipmapGenerator::new(device);
+    scene_view
+        .images
+        .iter()
+        .map(|img_info| upload_image(device, queue, &mut mipmap_generator, img_info, scene_buffer))
+        .collect()
+}
+
+fn upload_image(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    mipmap_generator: &mut MipmapGenerator,
+    img_info: &ImageData,
+    scene_buffer: &GLTFBuffer,
+) -> Result<wgpu::Texture, Error> {
+    let source_data = &scene_buffer.shared_data[img_info.range.clone()];
+    if img_info.mime == ktx2::MIME {
+        let ktx = Ktx2Image::from_bytes(
+            source_data,
+            device.features(),
+            img_info.target_format.is_srgb(),
+        )
+        .context(Ktx2ErrSnafu)?;
+        return Ok(ktx.create_texture(device, queue, img_info.name.as_deref()));
+    }
+    let decoded = img_info.decoded.as_ref().context(NoneErrSnafu)?;
+    let desc = wgpu::TextureDescriptor {
+        label: img_info.name.as_deref(),
+        size: wgpu::Extent3d {
+            width: decoded.width,
+            height: decoded.height,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: decoded.mip_level_count,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: decoded.format,
+        usage: wgpu::TextureUsages::TEXTURE_BINDING,
+        view_formats: &[],
+    };
+    // baked caches come with their mip chain and may be block compressed
+    if decoded.mip_level_count > 1 || decoded.format.block_dimensions() != (1, 1) {
+        let required = decoded.format.required_features();
+        ensure!(
+            device.features().contains(required),
+            UnsupportedTextureFormatSnafu {
+                format: decoded.format
+            }
+        );
+        return Ok(device.create_texture_with_data(queue, &desc, &decoded.data));
+    }
+    Ok(mipmap_generator.create_texture_with_data(device, queue, &desc, &decoded.data))
+}
+
+fn create_fallback_textures(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    textures: &mut Vec<wgpu::Texture>,
+) -> FallbackTextures {
+    let mut push = |label, format, pixel: [u8; 4]| {
+        textures.push(device.create_texture_with_data(
+            queue,
+            &wgpu::TextureDescriptor {
+                label: Some(label),
+                size: wgpu::Extent3d {
+                    width: 1,
+                    height: 1,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format,
+                usage: wgpu::TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            },
+            &pixel,
+        ));
+        textures.len() as u32 - 1
+    };
+
+    FallbackTextures {
+        white: push(
+            "Fallback White",
+            wgpu::TextureFormat::Rgba8UnormSrgb,
+            [255; 4],
+        ),
+        flat_normal: push(
+            "Fallback Normal",
+            wgpu::TextureFormat::Rgba8Unorm,
+            [128, 128, 255, 255],
+        ),
+        linear_white: push(
+            "Fallback Linear White",
+            wgpu::TextureFormat::Rgba8Unorm,
+            [255; 4],
+        ),
+    }
+}
+
+fn create_samplers(device: &wgpu::Device, scene_view: &SceneView) -> Vec<wgpu::Sampler> {
+    let mut samplers: Vec<wgpu::Sampler> = scene_view
+        .samplers
+        .iter()
+        .map(|sampler| {
+            let desc: wgpu::SamplerDescriptor<'_> = sampler.t_into();
+            device.create_sampler(&desc)
+        })
+        .collect();
+    if samplers.is_empty() {
+        samplers.push(device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Default Sampler"),
+            ..Default::default()
+        }));
+    }
+    samplers
+}
+
+fn create_tex_bind_group(
+    device: &wgpu::Device,
+    textures: &[wgpu::Texture],
+    samplers: &[wgpu::Sampler],
+    materials: &wgpu::Buffer,
+) -> (wgpu::BindGroupLayout, wgpu::BindGroup) {
+    let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        label: Some("Scene Texture Bind Group Layout"),
+        entries: &[
+            wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    multisampled: false,
+                },
+                count: NonZeroU32::new(textures.len() as u32),
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 1,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                count: NonZeroU32::new(samplers.len() as u32),
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 2,
+                visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Storage { read_only: true },
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            },
+        ],
+    });
+
+    let views: Vec<wgpu::TextureView> = textures
+        .iter()
+        .map(|i| i.create_view(&wgpu::TextureViewDescriptor::default()))
+        .collect();
+    let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("Scene Texture Bind Group"),
+        layout: &layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureViewArray(
+                    views.iter().collect::<Vec<_>>().as_slice(),
+                ),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::SamplerArray(
+                    samplers.iter().collect::<Vec<_>>().as_slice(),
+                ),
+            },
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: materials.as_entire_binding(),
+            },
+        ],
+    });
+    (layout, bind_group)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::mem::offset_of;
+
+    use super::*;
+    use crate::asset::gltf::{Clearcoat, TextureTransform};
+
+    #[test]
+    fn per_obj_data_matches_object_layout() {
+        assert_eq!(
+            PER_OBJ_MATERIAL_OFFSET as usize,
+            offset_of!(PerObjData, material)
+        );
+        let attributes: Vec<_> = OBJECT_ATTRS
+            .iter()
+            .map(|i| (i.shader_location, i.offset))
+            .collect();
+        assert_eq!(
+            attributes,
+            [
+                (14, 0),
+                (15, 16),
+                (16, 32),
+                (17, 48),
+                (8, PER_OBJ_MATERIAL_OFFSET)
+            ]
+        );
+        assert_eq!(
+            GpuScene::OBJECT_LAYOUT.array_stride as usize,
+            size_of::<PerObjData>()
+        );
+    }
+
+    /// `(name, offset)` of the listed `GpuMaterial` fields.
+    macro_rules! offsets {
+        ($($field:ident),* $(,)?) => {
+            vec![$((stringify!($field), offset_of!(GpuMaterial, $field) as u32)),*]
+        };
+    }
+
+    #[test]
+    fn gpu_material_matches_material_wgsl() {
+        let module = naga::front::wgsl::parse_str(include_str!("shader/material.wgsl")).unwrap();
+        let (members, span) = module
+            .types
+            .iter()
+            .find_map(|(_, ty)| match &ty.inner {
+                naga::TypeInner::Struct { members, span }
+                    if ty.name.as_deref() == Some("Material") =>
+                {
+                    Some((members, *span))
+                }
+                _ => None,
+            })
+            .unwrap();
+        assert_eq!(size_of::<GpuMaterial>(), 848);
+        assert_eq!(span as usize, size_of::<GpuMaterial>());
+        let wgsl: Vec<_> = members
+            .iter()
+            .map(|i| (i.name.as_deref().unwrap(), i.offset))
+            .collect();
+        let rust = offsets!(
+            base_color_factor,
+            base_color,
+            base_color_sampler,
+            normal,
+            normal_sampler,
+            metallic_roughness,
+            metallic_roughness_sampler,
+            metallic_factor,
+            roughness_factor,
+            emissive_factor,
+            emissive,
+            emissive_sampler,
+            occlusion,
+            occlusion_sampler,
+            normal_scale,
+            occlusion_strength,
+            alpha_cutoff,
+            shading_model,
+            base_color_transform,
+            normal_transform,
+            metallic_roughness_transform,
+            emissive_transform,
+            occlusion_transform,
+            clearcoat_transform,
+            clearcoat_roughness_transform,
+            sheen_color_transform,
+            sheen_roughness_transform,
+            transmission_transform,
+            specular_transform,
+            specular_color_transform,
+            features,
+            ior,
+            clearcoat_factor,
+            clearcoat_roughness_factor,
+            clearcoat,
+            clearcoat_sampler,
+            clearcoat_roughness,
+            clearcoat_roughness_sampler,
+            sheen_color,
+            sheen_color_sampler,
+            sheen_roughness,
+            sheen_roughness_sampler,
+            transmission,
+            transmission_sampler,
+            specular,
+            specular_sampler,
+            specular_color,
+            specular_color_sampler,
+            transmission_factor,
+            thickness_factor,
+            sheen_color_factor,
+            sheen_roughness_factor,
+            specular_color_factor,
+            specular_factor,
+            attenuation_color,
+            attenuation_distance,
+            base_color_tex_coord,
+            normal_tex_coord,
+            metallic_roughness_tex_coord,
+            emissive_tex_coord,
+            occlusion_tex_coord,
+            clearcoat_tex_coord,
+            clearcoat_roughness_tex_coord,
+            sheen_color_tex_coord,
+            sheen_roughness_tex_coord,
+            transmission_tex_coord,
+            specular_tex_coord,
+            specular_color_tex_coord,
+        );
+        assert_eq!(wgsl, rust);
+    }
+
+    #[test]
+    fn vertex_layouts_feed_mesh_wgsl() {
+        let reflection = crate::shader::ShaderComposer::default()
+            .preprocess("gf/mesh.wgsl", &Default::default())
+            .and_then(|i| i.validate())
+            .unwrap();
+        reflection
+            .check_vertex_buffers("vs_main", &GpuScene::vertex_layouts())
+            .unwrap();
+    }
+
+    #[test]
+    fn draw_groups_sort_culled_double_sided_blend() {
+        let material = |alpha_mode, double_sided| Material {
+            alpha_mode,
+            double_sided,
+            ..Default::default()
+        };
+        let mut groups: Vec<_> = [
+            material(AlphaMode::Blend, true),
+            material(AlphaMode::Mask, true),
+            material(AlphaMode::Blend, false),
+            material(AlphaMode::Opaque, false),
+            material(AlphaMode::Mask, false),
+        ]
+        .iter()
+        .map(|i| DrawGroup::of(Some(i)))
+        .chain([DrawGroup::of(None)])
+        .collect();
+        assert_eq!(
+            groups,
+            [
+                DrawGroup::Blend,
+                DrawGroup::DoubleSided,
+                DrawGroup::Blend,
+                DrawGroup::Culled,
+                DrawGroup::Culled,
+                DrawGroup::Culled,
+            ]
+        );
+        groups.sort();
+        assert_eq!(
+            groups,
+            [
+                DrawGroup::Culled,
+                DrawGroup::Culled,
+                DrawGroup::Culled,
+                DrawGroup::DoubleSided,
+                DrawGroup::Blend,
+                DrawGroup::Blend,
+            ]
+        );
+    }
+
+    #[test]
+    fn variant_draws_use_the_default_material_for_unmapped_variants() {
+        let draw = VariantDraw {
+            instances: 0..1,
+            default_material: 7,
+            variant_materials: BTreeMap::from([(0, 2), (2, 9)]),
+        };
+        let materials: Vec<_> = [None, Some(0), Some(1), Some(2)]
+            .into_iter()
+            .map(|i| draw.material(i))
+            .collect();
+        assert_eq!(materials, [7, 2, 7, 9]);
+    }
+
+    fn fallback() -> FallbackTextures {
+        FallbackTextures {
+            white: 10,
+            flat_normal: 11,
+            linear_white: 12,
+        }
+    }
+
+    #[test]
+    fn gpu_material_applies_factors_and_fallbacks() {
+        let mut material = Material {
+            alpha_mode: AlphaMode::Mask,
+            alpha_cutoff: 0.25,
+            ..Default::default()
+        };
+        material.textures.insert(
+            MaterialKey::MetallicRoughness,
+            TextureData {
+                factor: [0.0, 0.3, 0.7, 0.0],
+                ..Default::default()
+            },
+        );
+        material.textures.insert(
+            MaterialKey::Emissive,
+            TextureData {
+                image_id: Some(3),
+                sampler: 1,
+                factor: [1.0, 0.5, 0.0, 4.0],
+                tex_coord: 1,
+                ..Default::default()
+            },
+        );
+
+        let gpu = GpuMaterial::new(&material, &fallback());
+        assert_eq!((gpu.metallic_factor, gpu.roughness_factor), (0.7, 0.3));
+        assert_eq!(gpu.emissive_factor, [4.0, 2.0, 0.0, 0.0]);
+        assert_eq!((gpu.emissive, gpu.emissive_sampler), (3, 1));
+        assert_eq!((gpu.emissive_tex_coord, gpu.base_color_tex_coord), (1, 0));
+        assert_eq!(gpu.metallic_roughness, 12);
+        assert_eq!((gpu.base_color, gpu.normal, gpu.occlusion), (10, 11, 12));
+        assert_eq!(gpu.alpha_cutoff, 0.25);
+
+        material.alpha_mode = AlphaMode::Blend;
+        assert_eq!(GpuMaterial::new(&material, &fallback()).alpha_cutoff, 0.0);
+    }
+
+    #[test]
+    fn gpu_material_keeps_extension_texture_transforms() {
+        let material = Material {
+            clearcoat: Some(Clearcoat {
+                factor: 1.0,
+                texture: None,
+                roughness_factor: 0.5,
+                roughness_texture: Some(TextureData {
+                    tex_coord: 1,
+                    transform: TextureTransform {
+                        offset: [0.25, 0.5],
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                }),
+                normal_texture: None,
+            }),
+            ..Default::default()
+        };
+
+        let gpu = GpuMaterial::new(&material, &fallback());
+        assert_eq!(gpu.features, material_features::CLEARCOAT);
+        assert_eq!(gpu.clearcoat_roughness_transform[2], [0.25, 0.5, 1.0, 0.0]);
+        assert_eq!(gpu.clearcoat_roughness_tex_coord, 1);
+        assert_eq!(gpu.clearcoat_transform[2], [0.0, 0.0, 1.0, 0.0]);
+        assert_eq!(gpu.clearcoat_tex_coord, 0);
+    }
+
+    #[test]
+    fn default_gpu_material_is_the_gltf_default() {
+        let gpu = GpuMaterial::gltf_default(&fallback());
+        assert_eq!(gpu.base_color_factor, [1.0; 4]);
+        assert_eq!((gpu.metallic_factor, gpu.roughness_factor), (1.0, 1.0));
+        assert_eq!(gpu.emissive_factor, [0.0; 4]);
+        assert_eq!(gpu.ior, 1.5);
+        assert_eq!(gpu.features, 0);
+        assert_eq!(gpu.base_color_transform[2], [0.0, 0.0, 1.0, 0.0]);
+    }
+}
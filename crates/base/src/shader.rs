@@ -0,0 +1,693 @@
+//! WGSL with `#include` and `#ifdef` permutations, compiled once per define set.
+//!
+//! Directives sit on their own line:
+//! - `#include "gf/camera.wgsl"` pastes a library file, or a file relative to the including one.
+//!   Every file is included once, later includes of it are skipped.
+//! - `#define NAME` adds to the defines for the rest of the source.
+//! - `#ifdef NAME`, `#ifndef NAME`, `#else` and `#endif` keep or drop the lines between them.
+//!
+//! naga errors point back at the file and line they came from. GLSL and HLSL files are
+//! compiled through [`foreign`] and cached alongside.
+
+use std::borrow::Cow;
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use snafu::{ensure, OptionExt, ResultExt, Snafu};
+
+use crate::hot_reload;
+
+pub mod foreign;
+pub mod reflect;
+
+pub use foreign::ForeignShader;
+pub use reflect::{merge_bind_group_layout_entries, InterfaceVariable, Reflection};
+
+/// Library files built into gf_base, by include name.
+const LIBRARIES: &[(&str, &str)] = &[
+    ("gf/camera.wgsl", include_str!("shader/camera.wgsl")),
+    ("gf/light.wgsl", include_str!("shader/light.wgsl")),
+    ("gf/material.wgsl", include_str!("shader/material.wgsl")),
+    ("gf/scene.wgsl", include_str!("shader/scene.wgsl")),
+    ("gf/mesh.wgsl", include_str!("shader/mesh.wgsl")),
+    ("gf/brdf.wgsl", include_str!("shader/brdf.wgsl")),
+    ("gf/lobes.wgsl", include_str!("shader/lobes.wgsl")),
+    (
+        "gf/light_debug.wgsl",
+        include_str!("shader/light_debug.wgsl"),
+    ),
+];
+
+#[derive(Debug, Snafu)]
+#[snafu(visibility(pub(crate)))]
+pub enum Error {
+    #[snafu(display("failed to read {path}"))]
+    ReadFailed {
+        path: String,
+        source: std::io::Error,
+    },
+    #[snafu(display("{file}:{line}: no library or file {name}"))]
+    IncludeNotFound {
+        file: String,
+        line: usize,
+        name: String,
+    },
+    #[snafu(display("{file}:{line}: {message}"))]
+    BadDirective {
+        file: String,
+        line: usize,
+        message: String,
+    },
+    #[snafu(display("{file}: #ifdef without #endif"))]
+    UnterminatedIf {
+        file: String,
+    },
+    #[snafu(display("{diagnostic}"))]
+    InvalidShader {
+        diagnostic: String,
+    },
+    ModuleCreateFailed {
+        source: hot_reload::Error,
+    },
+    #[snafu(display("{path}: not GLSL or HLSL, or its front end feature is off"))]
+    UnknownLanguage {
+        path: String,
+    },
+    #[snafu(display("no entry point {name}"))]
+    EntryPointNotFound {
+        name: String,
+    },
+    #[snafu(display("@group({group}) @binding({binding}): {message}"))]
+    BindingConflict {
+        group: u32,
+        binding: u32,
+        message: String,
+    },
+    #[snafu(display("@group({group}) @binding({binding}) doesn't match the layout: {message}"))]
+    BindingMismatch {
+        group: u32,
+        binding: u32,
+        message: String,
+    },
+    #[snafu(display("vertex inputs of {entry_point}: {message}"))]
+    VertexMismatch {
+        entry_point: String,
+        message: String,
+    },
+    #[snafu(display("color targets of {entry_point}: {message}"))]
+    ColorTargetMismatch {
+        entry_point: String,
+        message: String,
+    },
+    #[snafu(display("{vertex} -> {fragment}: {message}"))]
+    StageMismatch {
+        vertex: String,
+        fragment: String,
+        message: String,
+    },
+}
+
+/// Names switched on for one permutation.
+pub type Defines = BTreeSet<String>;
+
+/// Source after the directives ran, remembering where each line came from.
+pub struct Preprocessed {
+    pub source: String,
+    /// Library names and paths of every file pasted in, the entry first
+    pub files: Vec<String>,
+    /// Names tested by the `#ifdef`s and `#ifndef`s met, the defines that change `source`
+    pub conditions: Defines,
+    /// File index and 1 based line of each line of `source`
+    lines: Vec<(usize, usize)>,
+}
+
+impl Preprocessed {
+    /// A file taken as is, for the front ends without the directives.
+    fn verbatim(file: &str, source: String) -> Self {
+        let lines = (1..=source.lines().count()).map(|i| (0, i)).collect();
+        Self {
+            source,
+            files: vec![file.to_string()],
+            conditions: Defines::new(),
+            lines,
+        }
+    }
+
+    /// File and 1 based line a 1 based line of `source` came from.
+    pub fn origin(&self, line: usize) -> Option<(&str, usize)> {
+        let (file, line) = *self.lines.get(line.checked_sub(1)?)?;
+        Some((&self.files[file], line))
+    }
+
+    /// Parse and validate with naga, errors are reported against the original files.
+    pub fn validate(&self) -> Result<Reflection, Error> {
+        let module = naga::front::wgsl::parse_str(&self.source).map_err(|err| {
+            let labels = err.labels().map(|(span, label)| (span, label.to_string()));
+            InvalidShaderSnafu {
+                diagnostic: self.diagnostic(err.message(), labels.collect()),
+            }
+            .build()
+        })?;
+        self.reflect(module)
+    }
+
+    /// Validate a module a front end parsed from `source`.
+    fn reflect(&self, module: naga::Module) -> Result<Reflection, Error> {
+        let info = naga::valid::Validator::new(
+            naga::valid::ValidationFlags::all(),
+            naga::valid::Capabilities::all(),
+        )
+        .validate(&module)
+        .map_err(|err| {
+            let mut message = err.as_inner().to_string();
+            let mut source = std::error::Error::source(err.as_inner());
+            while let Some(err) = source {
+                message = format!("{message}: {err}");
+                source = err.source();
+            }
+            InvalidShaderSnafu {
+                diagnostic: self.diagnostic(&message, err.spans().cloned().collect()),
+            }
+            .build()
+        })?;
+        Ok(Reflection { module, info })
+    }
+
+    fn diagnostic(&self, message: &str, labels: Vec<(naga::Span, String)>) -> String {
+        let mut diagnostic = format!("error: {message}");
+        for (span, label) in labels {
+            if !span.is_defined() {
+                continue;
+            }
+            let location = span.location(&self.source);
+            let line = location.line_number as usize;
+            let text = self.source.lines().nth(line - 1).unwrap_or_default();
+            match self.origin(line) {
+                Some((file, original)) => diagnostic.push_str(&format!(
+                    "\n  --> {file}:{original}:{}: {label}\n   | {text}",
+                    location.line_position
+                )),
+                None => diagnostic.push_str(&format!("\n  --> {label}")),
+            }
+        }
+        diagnostic
+    }
+}
+
+/// What a cached module was built from.
+#[derive(Clone, PartialEq, Eq, Hash)]
+enum Source {
+    Wgsl(String),
+    Foreign(ForeignShader),
+}
+
+struct Cached {
+    module: Arc<wgpu::ShaderModule>,
+    reflection: Arc<Reflection>,
+    files: Vec<String>,
+}
+
+/// Library files and every compiled permutation, shared by the passes through [`crate::BaseState`].
+pub struct ShaderComposer {
+    libraries: HashMap<String, String>,
+    modules: HashMap<(Source, Defines), Cached>,
+}
+
+impl Default for ShaderComposer {
+    fn default() -> Self {
+        Self {
+            libraries: LIBRARIES
+                .iter()
+                .map(|(name, source)| (name.to_string(), source.to_string()))
+                .collect(),
+            modules: HashMap::new(),
+        }
+    }
+}
+
+impl ShaderComposer {
+    /// Make `source` includable as `name`, replacing a library of the same name.
+    pub fn add_library(&mut self, name: &str, source: &str) {
+        self.libraries.insert(name.to_string(), source.to_string());
+        self.invalidate(name);
+    }
+
+    /// The module of `entry`, a library name or a WGSL path, with `defines` switched on.
+    /// Compiled on first use, then shared by everyone asking for the same permutation.
+    pub fn module(
+        &mut self,
+        device: &wgpu::Device,
+        entry: &str,
+        defines: &[&str],
+    ) -> Result<Arc<wgpu::ShaderModule>, Error> {
+        let source = Source::Wgsl(entry.to_string());
+        Ok(self.compile(device, source, defines)?.module.clone())
+    }
+
+    /// What the permutation returned by [`Self::module`] binds and reads, to derive its
+    /// layouts from or check the Rust side against.
+    pub fn reflection(
+        &mut self,
+        device: &wgpu::Device,
+        entry: &str,
+        defines: &[&str],
+    ) -> Result<Arc<Reflection>, Error> {
+        let source = Source::Wgsl(entry.to_string());
+        Ok(self.compile(device, source, defines)?.reflection.clone())
+    }
+
+    /// Like [`Self::module`] for a GLSL or HLSL file, `defines` are passed as `#define`s.
+    pub fn foreign_module(
+        &mut self,
+        device: &wgpu::Device,
+        shader: &ForeignShader,
+        defines: &[&str],
+    ) -> Result<Arc<wgpu::ShaderModule>, Error> {
+        let source = Source::Foreign(shader.clone());
+        Ok(self.compile(device, source, defines)?.module.clone())
+    }
+
+    /// Like [`Self::reflection`] for a GLSL or HLSL file.
+    pub fn foreign_reflection(
+        &mut self,
+        device: &wgpu::Device,
+        shader: &ForeignShader,
+        defines: &[&str],
+    ) -> Result<Arc<Reflection>, Error> {
+        let source = Source::Foreign(shader.clone());
+        Ok(self.compile(device, source, defines)?.reflection.clone())
+    }
+
+    /// Forget the permutations built from `file`, a library name or path, so the next
+    /// [`Self::module`] reads it again.
+    pub fn invalidate<P: AsRef<Path>>(&mut self, file: P) {
+        let file = file.as_ref();
+        self.modules
+            .retain(|_, cached| !cached.files.iter().any(|i| Path::new(i) == file));
+    }
+
+    /// Files on disk the cached permutations were built from, includes of includes too.
+    /// Libraries are left out, they only change through [`Self::add_library`].
+    pub fn files(&self) -> Vec<&str> {
+        let mut files: Vec<&str> = self
+            .modules
+            .values()
+            .flat_map(|cached| &cached.files)
+            .map(String::as_str)
+            .filter(|i| !self.libraries.contains_key(*i))
+            .collect();
+        files.sort_unstable();
+        files.dedup();
+        files
+    }
+
+    /// Run the directives of `entry` without compiling it.
+    pub fn preprocess(&self, entry: &str, defines: &Defines) -> Result<Preprocessed, Error> {
+        let mut state = Preprocess {
+            defines: defines.clone(),
+            out: Preprocessed {
+                source: String::new(),
+                files: vec![],
+                conditions: Defines::new(),
+                lines: vec![],
+            },
+        };
+        let source = self.read(entry)?;
+        self.include(&mut state, entry, &source)?;
+        Ok(state.out)
+    }
+
+    fn compile(
+        &mut self,
+        device: &wgpu::Device,
+        source: Source,
+        defines: &[&str],
+    ) -> Result<&Cached, Error> {
+        let defines: Defines = defines.iter().map(|i| i.to_string()).collect();
+        let key = (source, defines);
+        if !self.modules.contains_key(&key) {
+            let (name, preprocessed, reflection) = match &key.0 {
+                Source::Wgsl(entry) => {
+                    let preprocessed = self.preprocess(entry, &key.1)?;
+                    let reflection = preprocessed.validate()?;
+                    (entry.clone(), preprocessed, reflection)
+                }
+                Source::Foreign(shader) => {
+                    let preprocessed =
+                        Preprocessed::verbatim(&shader.path, self.read(&shader.path)?);
+                    let reflection = shader.compile(&preprocessed, &key.1)?;
+                    let name = format!("{} {}", shader.path, shader.entry_point);
+                    (name, preprocessed, reflection)
+                }
+            };
+            let label = match key.1.is_empty() {
+                true => name,
+                false => format!("{name} {:?}", key.1),
+            };
+            let source = match &key.0 {
+                Source::Wgsl(_) => wgpu::ShaderSource::Wgsl(preprocessed.source.as_str().into()),
+                // Already parsed, wgpu would need its own copy of the front end otherwise
+                Source::Foreign(_) => {
+                    wgpu::ShaderSource::Naga(Cow::Owned(reflection.module.clone()))
+                }
+            };
+            let module = hot_reload::rebuild(device, || {
+                device.create_shader_module(wgpu::ShaderModuleDescriptor {
+                    label: Some(&label),
+                    source,
+                })
+            })
+            .context(ModuleCreateFailedSnafu)?;
+            let cached = Cached {
+                module: Arc::new(module),
+                reflection: Arc::new(reflection),
+                files: preprocessed.files,
+            };
+            self.modules.insert(key.clone(), cached);
+        }
+        Ok(&self.modules[&key])
+    }
+
+    fn read(&self, name: &str) -> Result<String, Error> {
+        if let Some(source) = self.libraries.get(name) {
+            return Ok(source.clone());
+        }
+        std::fs::read_to_string(name).context(ReadFailedSnafu { path: name })
+    }
+
+    /// A library, else a file next to `from` when that is on disk.
+    fn resolve(&self, from: &str, name: &str) -> Option<String> {
+        if self.libraries.contains_key(name) {
+            return Some(name.to_string());
+        }
+        if self.libraries.contains_key(from) {
+            return None;
+        }
+        let path: PathBuf = Path::new(from).parent()?.join(name);
+        path.is_file().then(|| path.to_string_lossy().into_owned())
+    }
+
+    fn include(&self, state: &mut Preprocess, file: &str, source: &str) -> Result<(), Error> {
+        let file_index = state.out.files.len();
+        state.out.files.push(file.to_string());
+
+        // Whether each enclosing #ifdef keeps its lines, and if it saw an #else
+        let mut branches: Vec<(bool, bool)> = vec![];
+        for (index, text) in source.lines().enumerate() {
+            let line = index + 1;
+            let active = branches.iter().all(|(keep, _)| *keep);
+            let Some(directive) = text.trim_start().strip_prefix('#') else {
+                if active {
+                    state.out.source.push_str(text);
+                    state.out.source.push('\n');
+                    state.out.lines.push((file_index, line));
+                }
+                continue;
+            };
+            let bad_directive = |message: &str| BadDirectiveSnafu {
+                file,
+                line,
+                message: message.to_string(),
+            };
+            let mut words = directive.split_whitespace();
+            let keyword = words.next().unwrap_or_default();
+            let argument = words.next();
+            match keyword {
+                "ifdef" | "ifndef" => {
+                    let name = argument.context(bad_directive("missing the define name"))?;
+                    state.out.conditions.insert(name.to_string());
+                    let defined = state.defines.contains(name);
+                    branches.push((defined == (keyword == "ifdef"), false));
+                }
+                "else" => {
+                    let branch = branches
+                        .last_mut()
+                        .context(bad_directive("#else without #ifdef"))?;
+                    ensure!(!branch.1, bad_directive("second #else"));
+                    *branch = (!branch.0, true);
+                }
+                "endif" => {
+                    branches
+                        .pop()
+                        .context(bad_directive("#endif without #ifdef"))?;
+                }
+                _ if !active => {}
+                "define" => {
+                    let name = argument.context(bad_directive("missing the define name"))?;
+                    state.defines.insert(name.to_string());
+                }
+                "include" => {
+                    let name = argument
+                        .and_then(|i| i.strip_prefix('"')?.strip_suffix('"'))
+                        .context(bad_directive("expected #include \"name\""))?;
+                    let path = self.resolve(file, name).context(IncludeNotFoundSnafu {
+                        file,
+                        line,
+                        name,
+                    })?;
+                    if !state.out.files.contains(&path) {
+                        let source = self.read(&path)?;
+                        self.include(state, &path, &source)?;
+                    }
+                }
+                _ => bad_directive(&format!("unknown directive #{keyword}")).fail()?,
+            }
+        }
+        ensure!(branches.is_empty(), UnterminatedIfSnafu { file });
+        Ok(())
+    }
+}
+
+struct Preprocess {
+    defines: Defines,
+    out: Preprocessed,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn composer(libraries: &[(&str, &str)]) -> ShaderComposer {
+        let mut composer = ShaderComposer::default();
+        for (name, source) in libraries {
+            composer.add_library(name, source);
+        }
+        composer
+    }
+
+    fn defines(names: &[&str]) -> Defines {
+        names.iter().map(|i| i.to_string()).collect()
+    }
+
+    fn lines(preprocessed: &Preprocessed) -> Vec<&str> {
+        preprocessed.source.lines().collect()
+    }
+
+    #[test]
+    fn includes_are_pasted_in_place() {
+        let composer = composer(&[
+            ("t/lib.wgsl", "const A = 1;\nconst B = 2;"),
+            (
+                "t/main.wgsl",
+                "// main\n#include \"t/lib.wgsl\"\nconst C = A + B;",
+            ),
+        ]);
+        let out = composer.preprocess("t/main.wgsl", &Defines::new()).unwrap();
+        assert_eq!(
+            lines(&out),
+            [
+                "// main",
+                "const A = 1;",
+                "const B = 2;",
+                "const C = A + B;"
+            ]
+        );
+        assert_eq!(out.files, ["t/main.wgsl", "t/lib.wgsl"]);
+    }
+
+    #[test]
+    fn origin_maps_back_to_the_included_file() {
+        let composer = composer(&[
+            (
+                "t/lib.wgsl",
+                "const A = 1;\n#ifdef NEVER\nconst B = 2;\n#endif\nconst C = 3;",
+            ),
+            ("t/main.wgsl", "#include \"t/lib.wgsl\"\n\nconst D = 4;"),
+        ]);
+        let out = composer.preprocess("t/main.wgsl", &Defines::new()).unwrap();
+        assert_eq!(
+            lines(&out),
+            ["const A = 1;", "const C = 3;", "", "const D = 4;"]
+        );
+        assert_eq!(out.origin(1), Some(("t/lib.wgsl", 1)));
+        // the dropped #ifdef block still counts towards the line numbers
+        assert_eq!(out.origin(2), Some(("t/lib.wgsl", 5)));
+        assert_eq!(out.origin(4), Some(("t/main.wgsl", 3)));
+        assert_eq!(out.origin(0), None);
+        assert_eq!(out.origin(5), None);
+    }
+
+    #[test]
+    fn files_are_included_once() {
+        let composer = composer(&[
+            ("t/common.wgsl", "const COMMON = 1;"),
+            ("t/a.wgsl", "#include \"t/common.wgsl\"\nconst A = COMMON;"),
+            ("t/b.wgsl", "#include \"t/common.wgsl\"\nconst B = COMMON;"),
+            (
+                "t/main.wgsl",
+                "#include \"t/a.wgsl\"\n#include \"t/b.wgsl\"\n#include \"t/a.wgsl\"",
+            ),
+        ]);
+        let out = composer.preprocess("t/main.wgsl", &Defines::new()).unwrap();
+        assert_eq!(
+            lines(&out),
+            [
+                "const COMMON = 1;",
+                "const A = COMMON;",
+                "const B = COMMON;"
+            ]
+        );
+        assert_eq!(
+            out.files,
+            ["t/main.wgsl", "t/a.wgsl", "t/common.wgsl", "t/b.wgsl"]
+        );
+    }
+
+    #[test]
+    fn nested_conditions_pick_one_branch() {
+        let source = "\
+#ifdef OUTER
+#ifndef INNER
+outer_only
+#else
+outer_and_inner
+#endif
+#else
+#ifdef INNER
+inner_only
+#endif
+neither_outer
+#endif
+always";
+        let composer = composer(&[("t/main.wgsl", source)]);
+        let run = |names: &[&str]| {
+            let out = composer.preprocess("t/main.wgsl", &defines(names)).unwrap();
+            assert_eq!(out.conditions, defines(&["INNER", "OUTER"]));
+            out.source
+        };
+        assert_eq!(run(&[]), "neither_outer\nalways\n");
+        assert_eq!(run(&["INNER"]), "inner_only\nneither_outer\nalways\n");
+        assert_eq!(run(&["OUTER"]), "outer_only\nalways\n");
+        assert_eq!(run(&["OUTER", "INNER"]), "outer_and_inner\nalways\n");
+    }
+
+    #[test]
+    fn defines_apply_to_the_lines_after_them() {
+        let composer = composer(&[
+            ("t/lib.wgsl", "#ifdef FAST\nfast\n#endif"),
+            (
+                "t/main.wgsl",
+                "#ifdef FAST\nbefore\n#endif\n#define FAST\n#include \"t/lib.wgsl\"",
+            ),
+        ]);
+        let out = composer.preprocess("t/main.wgsl", &Defines::new()).unwrap();
+        assert_eq!(lines(&out), ["fast"]);
+    }
+
+    #[test]
+    fn dropped_includes_are_not_read() {
+        let composer = composer(&[(
+            "t/main.wgsl",
+            "#ifdef NEVER\n#include \"t/gone.wgsl\"\n#endif",
+        )]);
+        assert!(composer.preprocess("t/main.wgsl", &Defines::new()).is_ok());
+    }
+
+    #[test]
+    fn includes_resolve_next_to_the_including_file() {
+        let dir = std::env::temp_dir().join(format!("gf_shader_{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("sub")).unwrap();
+        std::fs::write(dir.join("sub/util.wgsl"), "const UTIL = 1;").unwrap();
+        let main = dir.join("main.wgsl");
+        std::fs::write(
+            &main,
+            "#include \"sub/util.wgsl\"\n#include \"gf/camera.wgsl\"",
+        )
+        .unwrap();
+
+        let main = main.to_string_lossy();
+        let out = ShaderComposer::default()
+            .preprocess(&main, &Defines::new())
+            .unwrap();
+        assert_eq!(out.files.len(), 3);
+        assert!(out.files[1].ends_with("util.wgsl"));
+        assert_eq!(out.files[2], "gf/camera.wgsl");
+        assert_eq!(out.origin(1), Some((out.files[1].as_str(), 1)));
+    }
+
+    #[test]
+    fn bad_directives_name_their_line() {
+        let error = |source: &str| {
+            composer(&[("t/main.wgsl", source)])
+                .preprocess("t/main.wgsl", &Defines::new())
+                .map(|_| ())
+                .unwrap_err()
+                .to_string()
+        };
+        assert_eq!(
+            error("\n#include \"t/missing.wgsl\""),
+            "t/main.wgsl:2: no library or file t/missing.wgsl"
+        );
+        assert_eq!(
+            error("#include t/lib.wgsl"),
+            "t/main.wgsl:1: expected #include \"name\""
+        );
+        assert_eq!(error("#else"), "t/main.wgsl:1: #else without #ifdef");
+        assert_eq!(
+            error("#ifdef A\n#else\n#else\n#endif"),
+            "t/main.wgsl:3: second #else"
+        );
+        assert_eq!(error("#endif"), "t/main.wgsl:1: #endif without #ifdef");
+        assert_eq!(error("#ifdef"), "t/main.wgsl:1: missing the define name");
+        assert_eq!(
+            error("#pragma once"),
+            "t/main.wgsl:1: unknown directive #pragma"
+        );
+        assert_eq!(
+            error("#ifdef A\nfn f() {}"),
+            "t/main.wgsl: #ifdef without #endif"
+        );
+    }
+
+    #[test]
+    fn parse_errors_point_at_the_original_file() {
+        let composer = composer(&[
+            ("t/lib.wgsl", "// lib\n\nfn broken( {}"),
+            ("t/main.wgsl", "#include \"t/lib.wgsl\"\nfn ok() {}"),
+        ]);
+        let out = composer.preprocess("t/main.wgsl", &Defines::new()).unwrap();
+        let Err(err) = out.validate() else {
+            panic!("broken( must not parse");
+        };
+        let message = err.to_string();
+        assert!(message.contains("t/lib.wgsl:3:"), "{message}");
+    }
+
+    #[test]
+    fn validation_errors_point_at_the_original_file() {
+        let composer = composer(&[
+            (
+                "t/lib.wgsl",
+                "// lib\nfn broken() -> f32 { return vec2(1.0); }",
+            ),
+            ("t/main.wgsl", "#include \"t/lib.wgsl\"\nfn ok() {}"),
+        ]);
+        let out = composer.preprocess("t/main.wgsl", &Defines::new()).unwrap();
+        let Err(err) = out.validate() else {
+            panic!("broken() must not validate");
+        };
+        let message = err.to_string();
+        assert!(message.contains("t/lib.wgsl:2:"), "{message}");
+    }
+}
@@ -0,0 +1,71 @@
+const PI = 3.1415926;
+
+// KHR_materials_sheen, Charlie distribution (Estevez and Kulla 2017)
+fn D_Charlie(sheen_roughness: f32, NoH: f32) -> f32 {
+    let alpha = max(sheen_roughness * sheen_roughness, 0.000001);
+    let inv_alpha = 1.0 / alpha;
+    let sin2h = 1.0 - NoH * NoH;
+    return (2.0 + inv_alpha) * pow(sin2h, inv_alpha * 0.5) / (2.0 * PI);
+}
+
+fn V_Neubelt(NoV: f32, NoL: f32) -> f32 {
+    return 1.0 / (4.0 * (NoL + NoV - NoL * NoV) + 0.0001);
+}
+
+// Learn OpenGL
+fn DistributionGGX(N: vec3<f32>, H: vec3<f32>, roughness: f32) -> f32 {
+    let a2 = roughness * roughness;
+    let NdotH = max(dot(N, H), 0.0);
+    let NdotH2 = NdotH * NdotH;
+
+    let nom = a2;
+    var denom = (NdotH2 * (a2 - 1.0) + 1.0);
+    denom = PI * denom * denom;
+
+    return nom / denom;
+}
+
+fn GeometrySchlickGGX(NdotV: f32, roughness: f32) -> f32 {
+    let r = (roughness + 1.0);
+    let k = (r * r) / 8.0;
+
+    let nom = NdotV;
+    let denom = NdotV * (1.0 - k) + k;
+
+    return nom / denom;
+}
+fn GeometrySmith(N: vec3<f32>, V: vec3<f32>, L: vec3<f32>, roughness: f32) -> f32 {
+    let NdotV = max(dot(N, V), 0.0);
+    let NdotL = max(dot(N, L), 0.0);
+    let ggx2 = GeometrySchlickGGX(NdotV, roughness);
+    let ggx1 = GeometrySchlickGGX(NdotL, roughness);
+
+    return ggx1 * ggx2;
+}
+
+fn fresnelSchlick(cosTheta: f32, F0: vec3<f32>) -> vec3<f32> {
+    return F0 + (1.0 - F0) * pow(clamp(1.0 - cosTheta, 0.0, 1.0), 5.0);
+}
+
+// filament 
+fn D_GGX(NoH: f32, a: f32) -> f32 {
+    let a2 = a * a;
+    let f = (NoH * a2 - NoH) * NoH + 1.0;
+    return a2 / (PI * f * f);
+}
+
+fn F_Schlick(u: f32, f0: vec3<f32>) -> vec3<f32> {
+    return f0 + (vec3(1.0) - f0) * pow(1.0 - u, 5.0);
+}
+
+
+fn V_SmithGGXCorrelatedFast(NoV: f32, NoL: f32, a: f32) -> f32 {
+    let a2 = a * a;
+    let GGXL = NoV * sqrt((-NoL * a2 + NoL) * NoL + a2);
+    let GGXV = NoL * sqrt((-NoV * a2 + NoV) * NoV + a2);
+    return 0.5 / (GGXV + GGXL);
+}
+
+fn Fd_Lambert() -> f32 {
+    return 1.0 / PI;
+}
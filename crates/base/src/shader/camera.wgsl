@@ -0,0 +1,7 @@
+// The camera uniform of BaseState, bound at group 0 by every pass
+struct Camera {
+    view_pos: vec4<f32>,
+    view_proj: mat4x4<f32>,
+}
+@group(0) @binding(0)
+var<uniform> camera: Camera;
@@ -0,0 +1,127 @@
+//! GLSL, and HLSL with the `hlsl` feature, read through naga front ends so ported shaders
+//! validate, reflect and cache like the WGSL ones.
+//!
+//! These files skip the `#include` preprocessor, their defines go to the language's own
+//! `#define`s instead.
+
+use std::path::Path;
+
+use snafu::ensure;
+
+use super::{
+    Defines, EntryPointNotFoundSnafu, Error, Preprocessed, Reflection, UnknownLanguageSnafu,
+};
+
+/// A GLSL or HLSL file and the entry point taken from it.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ForeignShader {
+    pub path: String,
+    pub stage: naga::ShaderStage,
+    /// The HLSL function to compile. GLSL always starts at `main`, which is renamed to this
+    /// so pipelines name their entry points the same whatever the language.
+    pub entry_point: String,
+}
+
+impl ForeignShader {
+    pub fn new(path: &str, stage: naga::ShaderStage, entry_point: &str) -> Self {
+        Self {
+            path: path.to_string(),
+            stage,
+            entry_point: entry_point.to_string(),
+        }
+    }
+
+    /// Parse with the front end of the extension, `.glsl`, `.vert`, `.frag` and `.comp` are
+    /// GLSL and `.hlsl` is HLSL, then validate.
+    pub(super) fn compile(
+        &self,
+        source: &Preprocessed,
+        defines: &Defines,
+    ) -> Result<Reflection, Error> {
+        let extension = Path::new(&self.path)
+            .extension()
+            .and_then(|i| i.to_str())
+            .unwrap_or_default();
+        let module: naga::Module = match extension {
+            #[cfg(feature = "glsl")]
+            "glsl" | "vert" | "frag" | "comp" => self.glsl(source, defines),
+            #[cfg(feature = "hlsl")]
+            "hlsl" => self.hlsl(source, defines),
+            _ => UnknownLanguageSnafu { path: &self.path }.fail(),
+        }?;
+        ensure!(
+            module
+                .entry_points
+                .iter()
+                .any(|i| i.name == self.entry_point && i.stage == self.stage),
+            EntryPointNotFoundSnafu {
+                name: &self.entry_point
+            }
+        );
+        source.reflect(module)
+    }
+
+    #[cfg(feature = "glsl")]
+    fn glsl(&self, source: &Preprocessed, defines: &Defines) -> Result<naga::Module, Error> {
+        let options = naga::front::glsl::Options {
+            stage: self.stage,
+            defines: defines
+                .iter()
+                .map(|i| (i.clone(), "1".to_string()))
+                .collect(),
+        };
+        let mut module = naga::front::glsl::Frontend::default()
+            .parse(&options, &source.source)
+            .map_err(|errors| {
+                let labels = errors
+                    .iter()
+                    .map(|err| (err.meta, err.kind.to_string()))
+                    .collect();
+                super::InvalidShaderSnafu {
+                    diagnostic: source.diagnostic("GLSL parse failed", labels),
+                }
+                .build()
+            })?;
+        for entry_point in &mut module.entry_points {
+            entry_point.name = self.entry_point.clone();
+        }
+        Ok(module)
+    }
+
+    /// naga has no HLSL front end, DXC compiles to SPIR-V for the SPIR-V one. Needs the
+    /// dxcompiler library at runtime.
+    #[cfg(feature = "hlsl")]
+    fn hlsl(&self, source: &Preprocessed, defines: &Defines) -> Result<naga::Module, Error> {
+        let profile = match self.stage {
+            naga::ShaderStage::Vertex => "vs_6_0",
+            naga::ShaderStage::Fragment => "ps_6_0",
+            naga::ShaderStage::Compute => "cs_6_0",
+        };
+        let defines: Vec<_> = defines.iter().map(|i| (i.as_str(), None)).collect();
+        let spirv = hassle_rs::compile_hlsl(
+            &self.path,
+            &source.source,
+            &self.entry_point,
+            profile,
+            &["-spirv"],
+            &defines,
+        )
+        .map_err(|err| {
+            super::InvalidShaderSnafu {
+                diagnostic: err.to_string(),
+            }
+            .build()
+        })?;
+        let options = naga::front::spv::Options {
+            // DXC keeps the D3D clip space, which is already the one wgpu uses
+            adjust_coordinate_space: false,
+            ..Default::default()
+        };
+        naga::front::spv::parse_u8_slice(&spirv, &options).map_err(|err| {
+            super::InvalidShaderSnafu {
+                diagnostic: format!("error: {err}\n  --> {}", self.path),
+            }
+            .build()
+        })
+    }
+}
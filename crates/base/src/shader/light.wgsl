@@ -0,0 +1,5 @@
+// A point light, each pass binds it at its own group
+struct Light {
+    position: vec3<f32>,
+    color: vec3<f32>,
+}
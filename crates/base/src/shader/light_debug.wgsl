@@ -1,16 +1,8 @@
 // Vertex shader
 
-struct Camera {
-    view_pos: vec4<f32>,
-    view_proj: mat4x4<f32>,
-}
-@group(0) @binding(0)
-var<uniform> camera: Camera;
+#include "gf/camera.wgsl"
+#include "gf/light.wgsl"
 
-struct Light {
-    position: vec3<f32>,
-    color: vec3<f32>,
-}
 @group(1) @binding(0)
 var<uniform> light: Light;
 
@@ -39,4 +31,4 @@ fn vs_main(
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
     return vec4<f32>(in.color, 1.0);
-}
\ No newline at end of file
+}
@@ -0,0 +1,114 @@
+// KHR_materials_* lobes on top of the Cook-Torrance BRDF,
+// volume_transmittance reads the `materials` array the includer binds
+#include "gf/brdf.wgsl"
+#include "gf/material.wgsl"
+
+// KHR_materials_* inputs of the lighting, textures already applied
+struct Lobes {
+    features: u32,
+    ior: f32,
+    clearcoat: f32,
+    clearcoat_roughness: f32,
+    sheen_color: vec3<f32>,
+    sheen_roughness: f32,
+    specular_color: vec3<f32>,
+    specular: f32,
+    transmission: f32,
+    // Beer-Lambert attenuation through the volume thickness
+    volume_transmittance: vec3<f32>,
+}
+
+fn default_lobes() -> Lobes {
+    var lobes: Lobes;
+    lobes.features = 0u;
+    lobes.ior = 1.5;
+    lobes.specular_color = vec3(1.0);
+    lobes.specular = 1.0;
+    lobes.volume_transmittance = vec3(1.0);
+    return lobes;
+}
+
+fn volume_transmittance(mat_id: u32) -> vec3<f32> {
+    if (materials[mat_id].features & FEATURE_VOLUME) == 0u {
+        return vec3(1.0);
+    }
+    // attenuation_distance defaults to infinity, which leaves the light untouched
+    let distance = materials[mat_id].thickness_factor / materials[mat_id].attenuation_distance;
+    return pow(materials[mat_id].attenuation_color, vec3(distance));
+}
+
+// Radiance of one light leaving towards V, Cook-Torrance plus the lobes enabled in lobes.features
+fn shade_light(
+    N: vec3<f32>,
+    V: vec3<f32>,
+    L: vec3<f32>,
+    radiance: vec3<f32>,
+    albedo: vec3<f32>,
+    metallic: f32,
+    roughness: f32,
+    lobes: Lobes,
+) -> vec3<f32> {
+    let H = normalize(V + L);
+    let NdotL = max(dot(N, L), 0.0);
+    let NdotV = max(dot(N, V), 0.0);
+    let NdotH = max(dot(N, H), 0.0);
+    let HdotV = max(dot(H, V), 0.0);
+
+    // calculate reflectance at normal incidence; dia-electrics get theirs from the ior
+    // (0.04 at the default 1.5) tinted by KHR_materials_specular, metals use the albedo
+    let dielectric_f0 = min(vec3(pow((lobes.ior - 1.0) / (lobes.ior + 1.0), 2.0)) * lobes.specular_color, vec3(1.0));
+    let F0 = mix(dielectric_f0, albedo, metallic);
+    let specular_weight = mix(lobes.specular, 1.0, metallic);
+
+    // Cook-Torrance BRDF
+    let NDF = DistributionGGX(N, H, roughness);
+    let G = GeometrySmith(N, V, L, roughness);
+    let F = fresnelSchlick(HdotV, F0) * specular_weight;
+    let specular = NDF * G * F / (4.0 * NdotV * NdotL + 0.0001);
+
+    // for energy conservation only the light not reflected is diffused,
+    // and pure metals have no diffuse light
+    let kD = (vec3(1.0) - F) * (1.0 - metallic);
+    var diffuse = albedo / PI * NdotL;
+    if (lobes.features & FEATURE_TRANSMISSION) != 0u {
+        let transmitted = transmission_lobe(N, V, L, albedo, F0, roughness, lobes.ior) * lobes.volume_transmittance;
+        diffuse = mix(diffuse, transmitted, lobes.transmission);
+    }
+    var color = (kD * diffuse + specular * NdotL) * radiance;
+
+    if (lobes.features & FEATURE_SHEEN) != 0u {
+        let sheen = lobes.sheen_color * D_Charlie(lobes.sheen_roughness, NdotH) * V_Neubelt(NdotV, NdotL);
+        color += sheen * NdotL * radiance;
+    }
+
+    // The coat sits on top and takes its share of the light first
+    if (lobes.features & FEATURE_CLEARCOAT) != 0u {
+        let alpha = max(lobes.clearcoat_roughness * lobes.clearcoat_roughness, 0.002);
+        let Fc = F_Schlick(HdotV, vec3(0.04)).x * lobes.clearcoat;
+        let coat = D_GGX(NdotH, alpha) * V_SmithGGXCorrelatedFast(NdotV + 1e-5, NdotL, alpha) * Fc;
+        color = color * (1.0 - Fc) + coat * NdotL * radiance;
+    }
+    return color;
+}
+
+// KHR_materials_transmission of a thin surface, after the glTF sample viewer:
+// light from behind is mirrored to the front and goes through a GGX lobe
+fn transmission_lobe(
+    N: vec3<f32>,
+    V: vec3<f32>,
+    L: vec3<f32>,
+    albedo: vec3<f32>,
+    F0: vec3<f32>,
+    roughness: f32,
+    ior: f32,
+) -> vec3<f32> {
+    let alpha = max(roughness * roughness * clamp(ior * 2.0 - 2.0, 0.0, 1.0), 0.002);
+    let l_mirror = normalize(L - 2.0 * N * dot(L, N));
+    let H = normalize(l_mirror + V);
+    let NoL = clamp(dot(N, l_mirror), 0.0, 1.0);
+    let NoV = abs(dot(N, V)) + 1e-5;
+    let D = D_GGX(clamp(dot(N, H), 0.0, 1.0), alpha);
+    let Vis = V_SmithGGXCorrelatedFast(NoV, NoL, alpha);
+    let F = F_Schlick(clamp(dot(V, H), 0.0, 1.0), F0);
+    return (vec3(1.0) - F) * albedo * D * Vis * NoL;
+}
@@ -0,0 +1,86 @@
+// Layout of GpuScene's material storage buffer
+struct Material {
+    base_color_factor: vec4<f32>,
+    base_color: u32,
+    base_color_sampler: u32,
+    normal: u32,
+    normal_sampler: u32,
+    metallic_roughness: u32,
+    metallic_roughness_sampler: u32,
+    metallic_factor: f32,
+    roughness_factor: f32,
+    emissive_factor: vec4<f32>,
+    emissive: u32,
+    emissive_sampler: u32,
+    occlusion: u32,
+    occlusion_sampler: u32,
+    normal_scale: f32,
+    occlusion_strength: f32,
+    alpha_cutoff: f32,
+    shading_model: u32,
+    base_color_transform: mat3x3<f32>,
+    normal_transform: mat3x3<f32>,
+    metallic_roughness_transform: mat3x3<f32>,
+    emissive_transform: mat3x3<f32>,
+    occlusion_transform: mat3x3<f32>,
+    clearcoat_transform: mat3x3<f32>,
+    clearcoat_roughness_transform: mat3x3<f32>,
+    sheen_color_transform: mat3x3<f32>,
+    sheen_roughness_transform: mat3x3<f32>,
+    transmission_transform: mat3x3<f32>,
+    specular_transform: mat3x3<f32>,
+    specular_color_transform: mat3x3<f32>,
+    features: u32,
+    ior: f32,
+    clearcoat_factor: f32,
+    clearcoat_roughness_factor: f32,
+    clearcoat: u32,
+    clearcoat_sampler: u32,
+    clearcoat_roughness: u32,
+    clearcoat_roughness_sampler: u32,
+    sheen_color: u32,
+    sheen_color_sampler: u32,
+    sheen_roughness: u32,
+    sheen_roughness_sampler: u32,
+    transmission: u32,
+    transmission_sampler: u32,
+    specular: u32,
+    specular_sampler: u32,
+    specular_color: u32,
+    specular_color_sampler: u32,
+    transmission_factor: f32,
+    thickness_factor: f32,
+    sheen_color_factor: vec3<f32>,
+    sheen_roughness_factor: f32,
+    specular_color_factor: vec3<f32>,
+    specular_factor: f32,
+    attenuation_color: vec3<f32>,
+    attenuation_distance: f32,
+    base_color_tex_coord: u32,
+    normal_tex_coord: u32,
+    metallic_roughness_tex_coord: u32,
+    emissive_tex_coord: u32,
+    occlusion_tex_coord: u32,
+    clearcoat_tex_coord: u32,
+    clearcoat_roughness_tex_coord: u32,
+    sheen_color_tex_coord: u32,
+    sheen_roughness_tex_coord: u32,
+    transmission_tex_coord: u32,
+    specular_tex_coord: u32,
+    specular_color_tex_coord: u32,
+}
+
+// Values of Material.shading_model
+const SHADING_UNLIT: u32 = 1u;
+
+// Bits of Material.features
+const FEATURE_CLEARCOAT: u32 = 1u;
+const FEATURE_SHEEN: u32 = 2u;
+const FEATURE_TRANSMISSION: u32 = 4u;
+const FEATURE_SPECULAR: u32 = 8u;
+const FEATURE_VOLUME: u32 = 16u;
+
+// KHR_texture_transform
+fn uv_transform(transform: mat3x3<f32>, uv: vec2<f32>) -> vec2<f32> {
+    return (transform * vec3<f32>(uv, 1.0)).xy;
+}
@@ -0,0 +1,83 @@
+// Vertex stage of GpuScene::vertex_layouts with the instance transforms applied
+#include "gf/camera.wgsl"
+
+//Vertex Buffers
+struct VertexInput {
+    @location(0) position: vec3<f32>,
+};
+struct Normal {
+    @location(1) normal: vec3<f32>,
+}
+struct UV {
+    @location(2) uv0: vec2<f32>,
+    @location(5) uv1: vec2<f32>,
+}
+struct Tangent {
+    @location(3) tangent: vec4<f32>,
+}
+
+struct PerObjInput {
+    @location(8) material: u32,
+
+    @location(14) model_mat_0: vec4<f32>,
+    @location(15) model_mat_1: vec4<f32>,
+    @location(16) model_mat_2: vec4<f32>,
+    @location(17) model_mat_3: vec4<f32>,
+}
+
+//Vertex Output
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) pos: vec3<f32>,
+    @location(1) uv0: vec2<f32>,
+    @location(2) uv1: vec2<f32>,
+
+    @location(8) material: u32,
+    @location(14) a_normal: vec3<f32>,
+    @location(15) a_tangent: vec3<f32>,
+    @location(16) a_bi_tangent: vec3<f32>,
+
+    @location(30) debug_vec3: vec3<f32>,
+};
+
+@vertex
+fn vs_main(
+    vert: VertexInput,
+    obj: PerObjInput,
+    normal: Normal,
+    uv: UV,
+    tangent: Tangent,
+) -> VertexOutput {
+    var out: VertexOutput;
+
+    let model_mat = mat4x4<f32>(obj.model_mat_0, obj.model_mat_1, obj.model_mat_2, obj.model_mat_3);
+
+    let a_normal = normalize((model_mat * vec4<f32>(normal.normal, 1.0)).xyz);
+    var a_tangent = normalize((model_mat * tangent.tangent)).xyz;
+    let a_bi_tangent = normalize(cross(a_normal, a_tangent) * tangent.tangent.w);
+
+    let obj_pos = (model_mat * vec4<f32>(vert.position, 1.0));
+    out.clip_position = camera.view_proj * obj_pos;
+    out.pos = obj_pos.xyz;
+    out.uv0 = uv.uv0;
+    out.uv1 = uv.uv1;
+
+    out.material = obj.material;
+    out.a_tangent = a_tangent;
+    out.a_normal = a_normal;
+    out.a_bi_tangent = a_bi_tangent;
+
+    return out;
+}
+
+// Uv set picked by a Material `*_tex_coord`
+fn tex_coord(in: VertexOutput, uv_set: u32) -> vec2<f32> {
+    return select(in.uv0, in.uv1, uv_set == 1u);
+}
+
+// Tangent frame of the shaded side. Back faces only reach the fragment stage for
+// double-sided materials, which shade them with the reversed normal
+fn surface_tbn(in: VertexOutput, front_facing: bool) -> mat3x3<f32> {
+    let tbn = mat3x3<f32>(in.a_tangent, in.a_bi_tangent, in.a_normal);
+    return tbn * select(-1.0, 1.0, front_facing);
+}
@@ -0,0 +1,1051 @@
+//! Bind group layouts, vertex inputs and color targets read back from a validated module.
+//!
+//! The checks compare Rust side layouts and pipeline descriptors with what the shader
+//! declares, so a mismatch reads as an error naming the binding or location instead of
+//! a wgpu validation panic.
+
+use std::collections::BTreeMap;
+use std::num::NonZeroU32;
+
+use naga::{
+    AddressSpace, ArraySize, ImageClass, ImageDimension, Interpolation, Sampling, ScalarKind,
+    ShaderStage, TypeInner,
+};
+use snafu::{ensure, OptionExt};
+use wgpu::{BindGroupLayoutEntry, BindingType, ShaderStages, TextureViewDimension};
+
+use super::{
+    BindingConflictSnafu, BindingMismatchSnafu, ColorTargetMismatchSnafu, EntryPointNotFoundSnafu,
+    Error, StageMismatchSnafu, VertexMismatchSnafu,
+};
+
+/// A `@location` of an entry point, a vertex input or a fragment output.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InterfaceVariable {
+    pub location: u32,
+    pub name: Option<String>,
+    pub kind: ScalarKind,
+    pub components: u32,
+}
+
+impl InterfaceVariable {
+    /// The 32 bit vertex format of the same shape.
+    pub fn format(&self) -> Option<wgpu::VertexFormat> {
+        use wgpu::VertexFormat::*;
+        let formats = match self.kind {
+            ScalarKind::Float => [Float32, Float32x2, Float32x3, Float32x4],
+            ScalarKind::Uint => [Uint32, Uint32x2, Uint32x3, Uint32x4],
+            ScalarKind::Sint => [Sint32, Sint32x2, Sint32x3, Sint32x4],
+            ScalarKind::Bool => return None,
+        };
+        formats.get(self.components as usize - 1).copied()
+    }
+
+    fn describe(&self) -> String {
+        match &self.name {
+            Some(name) => format!("@location({}) {name}", self.location),
+            None => format!("@location({})", self.location),
+        }
+    }
+}
+
+/// An [`InterfaceVariable`] with its interpolation once the WGSL defaults are applied, which
+/// only matters between the vertex and the fragment stage.
+struct StageVariable {
+    variable: InterfaceVariable,
+    interpolation: Interpolation,
+    sampling: Option<Sampling>,
+}
+
+impl StageVariable {
+    fn describe_interpolation(&self) -> String {
+        match self.sampling {
+            Some(sampling) => format!("{:?} {:?}", self.interpolation, sampling),
+            None => format!("{:?}", self.interpolation),
+        }
+    }
+}
+
+/// A module with its validation info, from [`super::Preprocessed::validate`].
+pub struct Reflection {
+    pub module: naga::Module,
+    pub info: naga::valid::ModuleInfo,
+}
+
+impl Reflection {
+    /// One more than the highest `@group` in use.
+    pub fn bind_group_count(&self) -> u32 {
+        self.bindings()
+            .keys()
+            .map(|(group, _)| group + 1)
+            .max()
+            .unwrap_or_default()
+    }
+
+    /// Layout entries for the resources of `group` any entry point uses, visible to the
+    /// stages using them. Unsized binding arrays get a count of 1, set the real length
+    /// before creating the layout.
+    pub fn bind_group_layout_entries(&self, group: u32) -> Vec<BindGroupLayoutEntry> {
+        self.bindings()
+            .into_iter()
+            .filter(|((i, _), _)| *i == group)
+            .map(|(_, entry)| entry)
+            .collect()
+    }
+
+    /// Inputs of the vertex entry point `entry_point`, sorted by location.
+    pub fn vertex_inputs(&self, entry_point: &str) -> Result<Vec<InterfaceVariable>, Error> {
+        let inputs = self.stage_inputs(entry_point)?;
+        Ok(inputs.into_iter().map(|i| i.variable).collect())
+    }
+
+    /// Outputs of the fragment entry point `entry_point`, sorted by location.
+    pub fn color_targets(&self, entry_point: &str) -> Result<Vec<InterfaceVariable>, Error> {
+        let outputs = self.stage_outputs(entry_point)?;
+        Ok(outputs.into_iter().map(|i| i.variable).collect())
+    }
+
+    /// `entries` provide every resource of `group` the shader uses, with a matching type.
+    pub fn check_bind_group(
+        &self,
+        group: u32,
+        entries: &[BindGroupLayoutEntry],
+    ) -> Result<(), Error> {
+        for expected in self.bind_group_layout_entries(group) {
+            let binding = expected.binding;
+            let entry =
+                entries
+                    .iter()
+                    .find(|i| i.binding == binding)
+                    .context(BindingMismatchSnafu {
+                        group,
+                        binding,
+                        message: "the shader uses it but the layout has no entry",
+                    })?;
+            let message = match compatible(&expected, entry) {
+                Ok(()) => continue,
+                Err(message) => message,
+            };
+            return BindingMismatchSnafu {
+                group,
+                binding,
+                message,
+            }
+            .fail();
+        }
+        Ok(())
+    }
+
+    /// The vertex buffers and color targets of `desc` feed its entry points, assuming both
+    /// stages come from this module.
+    pub fn check_render_pipeline(
+        &self,
+        desc: &wgpu::RenderPipelineDescriptor,
+    ) -> Result<(), Error> {
+        self.check_vertex_buffers(desc.vertex.entry_point, desc.vertex.buffers)?;
+        match &desc.fragment {
+            Some(fragment) => self.check_color_targets(fragment.entry_point, fragment.targets),
+            None => Ok(()),
+        }
+    }
+
+    /// Every input of the vertex entry point `entry_point` is in one of `buffers`.
+    pub fn check_vertex_buffers(
+        &self,
+        entry_point: &str,
+        buffers: &[wgpu::VertexBufferLayout],
+    ) -> Result<(), Error> {
+        for input in self.vertex_inputs(entry_point)? {
+            let attribute = buffers
+                .iter()
+                .flat_map(|i| i.attributes)
+                .find(|i| i.shader_location == input.location)
+                .context(VertexMismatchSnafu {
+                    entry_point,
+                    message: format!("{} is not in any vertex buffer", input.describe()),
+                })?;
+            let provided = vertex_format_kind(attribute.format);
+            ensure!(
+                provided == input.kind,
+                VertexMismatchSnafu {
+                    entry_point,
+                    message: format!(
+                        "{} is {:?} in the shader but the buffer holds {:?}",
+                        input.describe(),
+                        input.kind,
+                        attribute.format
+                    ),
+                }
+            );
+        }
+        Ok(())
+    }
+
+    /// Every output of the fragment entry point `entry_point` has a target in `targets`
+    /// of the same scalar kind.
+    pub fn check_color_targets(
+        &self,
+        entry_point: &str,
+        targets: &[Option<wgpu::ColorTargetState>],
+    ) -> Result<(), Error> {
+        for output in self.color_targets(entry_point)? {
+            let target = targets
+                .get(output.location as usize)
+                .and_then(Option::as_ref)
+                .context(ColorTargetMismatchSnafu {
+                    entry_point,
+                    message: format!(
+                        "{} is written but the pipeline has {} color targets",
+                        output.describe(),
+                        targets.len()
+                    ),
+                })?;
+            let kind = texture_format_kind(target.format);
+            ensure!(
+                kind == Some(output.kind),
+                ColorTargetMismatchSnafu {
+                    entry_point,
+                    message: format!(
+                        "{} is {:?} in the shader but the target is {:?}",
+                        output.describe(),
+                        output.kind,
+                        target.format
+                    ),
+                }
+            );
+        }
+        Ok(())
+    }
+
+    /// Every input of the `fragment` entry point is written by `vertex` with the same type,
+    /// interpolation and sampling.
+    pub fn check_stage_interface(&self, vertex: &str, fragment: &str) -> Result<(), Error> {
+        let outputs = self.stage_outputs(vertex)?;
+        for input in self.stage_inputs(fragment)? {
+            let variable = &input.variable;
+            let output = outputs
+                .iter()
+                .find(|i| i.variable.location == variable.location)
+                .context(StageMismatchSnafu {
+                    vertex,
+                    fragment,
+                    message: format!("{} is not written by the vertex stage", variable.describe()),
+                })?;
+            ensure!(
+                output.variable.kind == variable.kind
+                    && output.variable.components == variable.components,
+                StageMismatchSnafu {
+                    vertex,
+                    fragment,
+                    message: format!(
+                        "{} is {:?}x{} in the vertex stage but {:?}x{} in the fragment stage",
+                        variable.describe(),
+                        output.variable.kind,
+                        output.variable.components,
+                        variable.kind,
+                        variable.components
+                    ),
+                }
+            );
+            ensure!(
+                output.interpolation == input.interpolation && output.sampling == input.sampling,
+                StageMismatchSnafu {
+                    vertex,
+                    fragment,
+                    message: format!(
+                        "{} is interpolated {} in the vertex stage but {} in the fragment stage",
+                        variable.describe(),
+                        output.describe_interpolation(),
+                        input.describe_interpolation()
+                    ),
+                }
+            );
+        }
+        Ok(())
+    }
+
+    /// Located arguments of `entry_point` with their interpolation, sorted by location.
+    fn stage_inputs(&self, entry_point: &str) -> Result<Vec<StageVariable>, Error> {
+        let function = &self.entry_point(entry_point)?.function;
+        let mut inputs = vec![];
+        for argument in &function.arguments {
+            self.interface(
+                argument.ty,
+                argument.binding.as_ref(),
+                &argument.name,
+                &mut inputs,
+            );
+        }
+        inputs.sort_by_key(|i| i.variable.location);
+        Ok(inputs)
+    }
+
+    /// Located results of `entry_point` with their interpolation, sorted by location.
+    fn stage_outputs(&self, entry_point: &str) -> Result<Vec<StageVariable>, Error> {
+        let function = &self.entry_point(entry_point)?.function;
+        let mut outputs = vec![];
+        if let Some(result) = &function.result {
+            self.interface(result.ty, result.binding.as_ref(), &None, &mut outputs);
+        }
+        outputs.sort_by_key(|i| i.variable.location);
+        Ok(outputs)
+    }
+
+    fn entry_point(&self, name: &str) -> Result<&naga::EntryPoint, Error> {
+        self.module
+            .entry_points
+            .iter()
+            .find(|i| i.name == name)
+            .context(EntryPointNotFoundSnafu { name })
+    }
+
+    /// Used resources by group and binding.
+    fn bindings(&self) -> BTreeMap<(u32, u32), BindGroupLayoutEntry> {
+        let mut bindings = BTreeMap::new();
+        for (handle, global) in self.module.global_variables.iter() {
+            let Some(binding) = &global.binding else {
+                continue;
+            };
+            let mut visibility = ShaderStages::NONE;
+            for (index, entry_point) in self.module.entry_points.iter().enumerate() {
+                if !self.info.get_entry_point(index)[handle].is_empty() {
+                    visibility |= match entry_point.stage {
+                        ShaderStage::Vertex => ShaderStages::VERTEX,
+                        ShaderStage::Fragment => ShaderStages::FRAGMENT,
+                        ShaderStage::Compute => ShaderStages::COMPUTE,
+                    };
+                }
+            }
+            if visibility.is_empty() {
+                continue;
+            }
+            let Some((ty, count)) = self.binding_type(global.space, global.ty) else {
+                continue;
+            };
+            bindings.insert(
+                (binding.group, binding.binding),
+                BindGroupLayoutEntry {
+                    binding: binding.binding,
+                    visibility,
+                    ty,
+                    count,
+                },
+            );
+        }
+        bindings
+    }
+
+    fn binding_type(
+        &self,
+        space: AddressSpace,
+        ty: naga::Handle<naga::Type>,
+    ) -> Option<(BindingType, Option<NonZeroU32>)> {
+        let buffer = |ty| BindingType::Buffer {
+            ty,
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        };
+        let inner = &self.module.types[ty].inner;
+        let ty = match (space, inner) {
+            (AddressSpace::Uniform, _) => buffer(wgpu::BufferBindingType::Uniform),
+            (AddressSpace::Storage { access }, _) => buffer(wgpu::BufferBindingType::Storage {
+                read_only: !access.contains(naga::StorageAccess::STORE),
+            }),
+            (AddressSpace::Handle, TypeInner::BindingArray { base, size }) => {
+                let count = match size {
+                    ArraySize::Constant(count) => *count,
+                    ArraySize::Dynamic => NonZeroU32::MIN,
+                };
+                let (ty, _) = self.binding_type(space, *base)?;
+                return Some((ty, Some(count)));
+            }
+            (AddressSpace::Handle, TypeInner::Sampler { comparison: true }) => {
+                BindingType::Sampler(wgpu::SamplerBindingType::Comparison)
+            }
+            (AddressSpace::Handle, TypeInner::Sampler { comparison: false }) => {
+                BindingType::Sampler(wgpu::SamplerBindingType::Filtering)
+            }
+            (
+                AddressSpace::Handle,
+                TypeInner::Image {
+                    dim,
+                    arrayed,
+                    class,
+                },
+            ) => {
+                let view_dimension = view_dimension(*dim, *arrayed);
+                match *class {
+                    ImageClass::Sampled { kind, multi } => BindingType::Texture {
+                        sample_type: match kind {
+                            ScalarKind::Sint => wgpu::TextureSampleType::Sint,
+                            ScalarKind::Uint => wgpu::TextureSampleType::Uint,
+                            _ => wgpu::TextureSampleType::Float { filterable: !multi },
+                        },
+                        view_dimension,
+                        multisampled: multi,
+                    },
+                    ImageClass::Depth { multi } => BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Depth,
+                        view_dimension,
+                        multisampled: multi,
+                    },
+                    ImageClass::Storage { format, access } => BindingType::StorageTexture {
+                        access: match (
+                            access.contains(naga::StorageAccess::LOAD),
+                            access.contains(naga::StorageAccess::STORE),
+                        ) {
+                            (true, true) => wgpu::StorageTextureAccess::ReadWrite,
+                            (true, false) => wgpu::StorageTextureAccess::ReadOnly,
+                            _ => wgpu::StorageTextureAccess::WriteOnly,
+                        },
+                        format: storage_format(format),
+                        view_dimension,
+                    },
+                }
+            }
+            _ => return None,
+        };
+        Some((ty, None))
+    }
+
+    /// Collect the located members of `ty`, recursing into the structs of the interface.
+    fn interface(
+        &self,
+        ty: naga::Handle<naga::Type>,
+        binding: Option<&naga::Binding>,
+        name: &Option<String>,
+        out: &mut Vec<StageVariable>,
+    ) {
+        let inner = &self.module.types[ty].inner;
+        if let TypeInner::Struct { members, .. } = inner {
+            for member in members {
+                self.interface(member.ty, member.binding.as_ref(), &member.name, out);
+            }
+            return;
+        }
+        let Some(naga::Binding::Location {
+            location,
+            interpolation,
+            sampling,
+            ..
+        }) = binding
+        else {
+            return;
+        };
+        let (kind, components) = match *inner {
+            TypeInner::Scalar { kind, .. } => (kind, 1),
+            TypeInner::Vector { size, kind, .. } => (kind, size as u32),
+            _ => return,
+        };
+        // WGSL interpolates floats perspective correct at the center unless told otherwise,
+        // integers are always flat
+        let interpolation = match kind {
+            ScalarKind::Float => interpolation.unwrap_or(Interpolation::Perspective),
+            _ => Interpolation::Flat,
+        };
+        let sampling = match interpolation {
+            Interpolation::Flat => None,
+            _ => Some(sampling.unwrap_or(Sampling::Center)),
+        };
+        out.push(StageVariable {
+            variable: InterfaceVariable {
+                location: *location,
+                name: name.clone(),
+                kind,
+                components,
+            },
+            interpolation,
+            sampling,
+        });
+    }
+}
+
+/// Entries of one bind group layout shared by several modules, each binding it at its own
+/// `@group`, with their visibility merged.
+pub fn merge_bind_group_layout_entries(
+    shaders: &[(&Reflection, u32)],
+) -> Result<Vec<BindGroupLayoutEntry>, Error> {
+    let mut merged: BTreeMap<u32, BindGroupLayoutEntry> = BTreeMap::new();
+    for (shader, group) in shaders {
+        for entry in shader.bind_group_layout_entries(*group) {
+            let Some(existing) = merged.get_mut(&entry.binding) else {
+                merged.insert(entry.binding, entry);
+                continue;
+            };
+            ensure!(
+                existing.ty == entry.ty && existing.count == entry.count,
+                BindingConflictSnafu {
+                    group: *group,
+                    binding: entry.binding,
+                    message: format!("declared as {:?} and as {:?}", existing.ty, entry.ty),
+                }
+            );
+            existing.visibility |= entry.visibility;
+        }
+    }
+    Ok(merged.into_values().collect())
+}
+
+/// Whether the layout `entry` can back what the shader `expected`.
+fn compatible(expected: &BindGroupLayoutEntry, entry: &BindGroupLayoutEntry) -> Result<(), String> {
+    if !entry.visibility.contains(expected.visibility) {
+        return Err(format!(
+            "used from {:?} but only visible to {:?}",
+            expected.visibility, entry.visibility
+        ));
+    }
+    if expected.count.is_some() != entry.count.is_some() {
+        return Err(match expected.count {
+            Some(_) => "the shader declares a binding_array, the layout entry has no count".into(),
+            None => "the layout entry has a count but the shader binding is not an array".into(),
+        });
+    }
+    let matches = match (expected.ty, entry.ty) {
+        (BindingType::Buffer { ty: shader, .. }, BindingType::Buffer { ty: layout, .. }) => {
+            use wgpu::BufferBindingType::*;
+            match (shader, layout) {
+                (Uniform, Uniform) => true,
+                (Storage { read_only: true }, Storage { .. }) => true,
+                (Storage { read_only: false }, Storage { read_only }) => !read_only,
+                _ => false,
+            }
+        }
+        (BindingType::Sampler(shader), BindingType::Sampler(layout)) => {
+            use wgpu::SamplerBindingType::Comparison;
+            (shader == Comparison) == (layout == Comparison)
+        }
+        (
+            BindingType::Texture {
+                sample_type: shader_sample,
+                view_dimension: shader_dimension,
+                multisampled: shader_multisampled,
+            },
+            BindingType::Texture {
+                sample_type,
+                view_dimension,
+                multisampled,
+            },
+        ) => {
+            use wgpu::TextureSampleType::*;
+            let sample_matches = matches!(
+                (shader_sample, sample_type),
+                (Float { .. }, Float { .. }) | (Depth, Depth) | (Sint, Sint) | (Uint, Uint)
+            );
+            sample_matches
+                && shader_dimension == view_dimension
+                && shader_multisampled == multisampled
+        }
+        (shader @ BindingType::StorageTexture { .. }, layout) => shader == layout,
+        _ => false,
+    };
+    match matches {
+        true => Ok(()),
+        false => Err(format!(
+            "the shader declares {:?}, the layout has {:?}",
+            expected.ty, entry.ty
+        )),
+    }
+}
+
+fn view_dimension(dim: ImageDimension, arrayed: bool) -> TextureViewDimension {
+    match (dim, arrayed) {
+        (ImageDimension::D1, _) => TextureViewDimension::D1,
+        (ImageDimension::D2, false) => TextureViewDimension::D2,
+        (ImageDimension::D2, true) => TextureViewDimension::D2Array,
+        (ImageDimension::D3, _) => TextureViewDimension::D3,
+        (ImageDimension::Cube, false) => TextureViewDimension::Cube,
+        (ImageDimension::Cube, true) => TextureViewDimension::CubeArray,
+    }
+}
+
+fn vertex_format_kind(format: wgpu::VertexFormat) -> ScalarKind {
+    use wgpu::VertexFormat::*;
+    match format {
+        Uint8x2 | Uint8x4 | Uint16x2 | Uint16x4 | Uint32 | Uint32x2 | Uint32x3 | Uint32x4 => {
+            ScalarKind::Uint
+        }
+        Sint8x2 | Sint8x4 | Sint16x2 | Sint16x4 | Sint32 | Sint32x2 | Sint32x3 | Sint32x4 => {
+            ScalarKind::Sint
+        }
+        _ => ScalarKind::Float,
+    }
+}
+
+fn texture_format_kind(format: wgpu::TextureFormat) -> Option<ScalarKind> {
+    match format.sample_type(None)? {
+        wgpu::TextureSampleType::Float { .. } | wgpu::TextureSampleType::Depth => {
+            Some(ScalarKind::Float)
+        }
+        wgpu::TextureSampleType::Sint => Some(ScalarKind::Sint),
+        wgpu::TextureSampleType::Uint => Some(ScalarKind::Uint),
+    }
+}
+
+fn storage_format(format: naga::StorageFormat) -> wgpu::TextureFormat {
+    use naga::StorageFormat as S;
+    use wgpu::TextureFormat as T;
+    match format {
+        S::R8Unorm => T::R8Unorm,
+        S::R8Snorm => T::R8Snorm,
+        S::R8Uint => T::R8Uint,
+        S::R8Sint => T::R8Sint,
+        S::R16Uint => T::R16Uint,
+        S::R16Sint => T::R16Sint,
+        S::R16Float => T::R16Float,
+        S::Rg8Unorm => T::Rg8Unorm,
+        S::Rg8Snorm => T::Rg8Snorm,
+        S::Rg8Uint => T::Rg8Uint,
+        S::Rg8Sint => T::Rg8Sint,
+        S::R32Uint => T::R32Uint,
+        S::R32Sint => T::R32Sint,
+        S::R32Float => T::R32Float,
+        S::Rg16Uint => T::Rg16Uint,
+        S::Rg16Sint => T::Rg16Sint,
+        S::Rg16Float => T::Rg16Float,
+        S::Rgba8Unorm => T::Rgba8Unorm,
+        S::Rgba8Snorm => T::Rgba8Snorm,
+        S::Rgba8Uint => T::Rgba8Uint,
+        S::Rgba8Sint => T::Rgba8Sint,
+        S::Bgra8Unorm => T::Bgra8Unorm,
+        S::Rgb10a2Uint => T::Rgb10a2Uint,
+        S::Rgb10a2Unorm => T::Rgb10a2Unorm,
+        S::Rg11b10Float => T::Rg11b10Float,
+        S::Rg32Uint => T::Rg32Uint,
+        S::Rg32Sint => T::Rg32Sint,
+        S::Rg32Float => T::Rg32Float,
+        S::Rgba16Uint => T::Rgba16Uint,
+        S::Rgba16Sint => T::Rgba16Sint,
+        S::Rgba16Float => T::Rgba16Float,
+        S::Rgba32Uint => T::Rgba32Uint,
+        S::Rgba32Sint => T::Rgba32Sint,
+        S::Rgba32Float => T::Rgba32Float,
+        S::R16Unorm => T::R16Unorm,
+        S::R16Snorm => T::R16Snorm,
+        S::Rg16Unorm => T::Rg16Unorm,
+        S::Rg16Snorm => T::Rg16Snorm,
+        S::Rgba16Unorm => T::Rgba16Unorm,
+        S::Rgba16Snorm => T::Rgba16Snorm,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use wgpu::{TextureFormat, VertexBufferLayout, VertexStepMode};
+
+    use super::super::Preprocessed;
+    use super::*;
+
+    const SHADER: &str = "
+struct Camera { view_proj: mat4x4<f32> }
+@group(0) @binding(0) var<uniform> camera: Camera;
+@group(1) @binding(0) var base_color: texture_2d<f32>;
+@group(1) @binding(1) var base_sampler: sampler;
+@group(1) @binding(2) var shadow: texture_depth_2d;
+@group(1) @binding(3) var shadow_sampler: sampler_comparison;
+@group(1) @binding(4) var unused: texture_2d<f32>;
+@group(2) @binding(0) var<storage, read> lights: array<vec4<f32>>;
+@group(2) @binding(1) var<storage, read_write> drawn: atomic<u32>;
+
+struct VertexInput {
+    @location(2) uv: vec2<f32>,
+    @location(0) position: vec3<f32>,
+    @builtin(vertex_index) index: u32,
+}
+
+struct VertexOutput {
+    @builtin(position) clip: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+    @location(1) @interpolate(flat) id: u32,
+}
+
+@vertex
+fn vs_main(in: VertexInput, @location(1) id: u32) -> VertexOutput {
+    var out: VertexOutput;
+    out.clip = camera.view_proj * vec4(in.position, 1.0);
+    out.uv = in.uv;
+    out.id = id;
+    return out;
+}
+
+struct FragmentOutput {
+    @location(0) color: vec4<f32>,
+    @location(1) id: u32,
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> FragmentOutput {
+    var out: FragmentOutput;
+    let lit = textureSampleCompare(shadow, shadow_sampler, in.uv, 0.5);
+    let color = textureSample(base_color, base_sampler, in.uv) * lit;
+    out.color = color * camera.view_proj[0] + lights[in.id];
+    out.id = atomicAdd(&drawn, 1u);
+    return out;
+}
+";
+
+    fn reflect(source: &str) -> Reflection {
+        let preprocessed = Preprocessed::verbatim("test.wgsl", source.to_string());
+        match preprocessed.validate() {
+            Ok(reflection) => reflection,
+            Err(err) => panic!("{err}"),
+        }
+    }
+
+    fn variable(location: u32, name: &str, kind: ScalarKind, components: u32) -> InterfaceVariable {
+        InterfaceVariable {
+            location,
+            name: Some(name.to_string()),
+            kind,
+            components,
+        }
+    }
+
+    fn texture(sample_type: wgpu::TextureSampleType) -> BindingType {
+        BindingType::Texture {
+            sample_type,
+            view_dimension: TextureViewDimension::D2,
+            multisampled: false,
+        }
+    }
+
+    fn storage(read_only: bool) -> BindingType {
+        BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Storage { read_only },
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        }
+    }
+
+    fn error<T: std::fmt::Debug>(result: Result<T, Error>) -> String {
+        result.unwrap_err().to_string()
+    }
+
+    #[test]
+    fn bind_group_layout_entries_skip_unused_bindings() {
+        let reflection = reflect(SHADER);
+        assert_eq!(reflection.bind_group_count(), 3);
+
+        let camera = reflection.bind_group_layout_entries(0);
+        assert_eq!(camera.len(), 1);
+        assert_eq!(camera[0].visibility, ShaderStages::VERTEX_FRAGMENT);
+        assert!(matches!(
+            camera[0].ty,
+            BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                ..
+            }
+        ));
+
+        let material: Vec<_> = reflection
+            .bind_group_layout_entries(1)
+            .into_iter()
+            .map(|i| (i.binding, i.visibility, i.ty, i.count))
+            .collect();
+        let fragment = ShaderStages::FRAGMENT;
+        assert_eq!(
+            material,
+            [
+                (
+                    0,
+                    fragment,
+                    texture(wgpu::TextureSampleType::Float { filterable: true }),
+                    None
+                ),
+                (
+                    1,
+                    fragment,
+                    BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    None
+                ),
+                (2, fragment, texture(wgpu::TextureSampleType::Depth), None),
+                (
+                    3,
+                    fragment,
+                    BindingType::Sampler(wgpu::SamplerBindingType::Comparison),
+                    None
+                ),
+            ]
+        );
+
+        let lights: Vec<_> = reflection
+            .bind_group_layout_entries(2)
+            .into_iter()
+            .map(|i| i.ty)
+            .collect();
+        assert_eq!(lights, [storage(true), storage(false)]);
+    }
+
+    #[test]
+    fn binding_arrays_get_a_count() {
+        let reflection = reflect(
+            "
+@group(0) @binding(0) var textures: binding_array<texture_2d<f32>, 4>;
+@group(0) @binding(1) var samplers: binding_array<sampler>;
+
+@fragment
+fn fs_main() -> @location(0) vec4<f32> {
+    return textureSample(textures[1], samplers[0], vec2(0.5));
+}
+",
+        );
+        let counts: Vec<_> = reflection
+            .bind_group_layout_entries(0)
+            .into_iter()
+            .map(|i| i.count.map(NonZeroU32::get))
+            .collect();
+        assert_eq!(counts, [Some(4), Some(1)]);
+    }
+
+    #[test]
+    fn vertex_inputs_are_sorted_without_builtins() {
+        let reflection = reflect(SHADER);
+        assert_eq!(
+            reflection.vertex_inputs("vs_main").unwrap(),
+            [
+                variable(0, "position", ScalarKind::Float, 3),
+                variable(1, "id", ScalarKind::Uint, 1),
+                variable(2, "uv", ScalarKind::Float, 2),
+            ]
+        );
+        assert_eq!(
+            reflection.color_targets("fs_main").unwrap(),
+            [
+                variable(0, "color", ScalarKind::Float, 4),
+                variable(1, "id", ScalarKind::Uint, 1),
+            ]
+        );
+        assert_eq!(
+            variable(0, "position", ScalarKind::Float, 3).format(),
+            Some(wgpu::VertexFormat::Float32x3)
+        );
+        assert_eq!(
+            error(reflection.vertex_inputs("main")),
+            "no entry point main"
+        );
+    }
+
+    #[test]
+    fn vertex_buffers_must_hold_every_input() {
+        let reflection = reflect(SHADER);
+        let check = |attributes: &[wgpu::VertexAttribute]| {
+            let buffers = [VertexBufferLayout {
+                array_stride: 64,
+                step_mode: VertexStepMode::Vertex,
+                attributes,
+            }];
+            reflection.check_vertex_buffers("vs_main", &buffers)
+        };
+        check(&wgpu::vertex_attr_array![0 => Float32x3, 1 => Uint32, 2 => Float32x2]).unwrap();
+        // the format only has to agree on the scalar kind
+        check(&wgpu::vertex_attr_array![0 => Float32x4, 1 => Uint32, 2 => Unorm16x2]).unwrap();
+
+        assert_eq!(
+            error(check(
+                &wgpu::vertex_attr_array![0 => Float32x3, 1 => Uint32]
+            )),
+            "vertex inputs of vs_main: @location(2) uv is not in any vertex buffer"
+        );
+        assert_eq!(
+            error(check(
+                &wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32, 2 => Float32x2]
+            )),
+            "vertex inputs of vs_main: @location(1) id is Uint in the shader but the buffer holds Float32"
+        );
+    }
+
+    #[test]
+    fn color_targets_must_match_every_output() {
+        let reflection = reflect(SHADER);
+        let check = |formats: &[Option<TextureFormat>]| {
+            let targets: Vec<_> = formats.iter().map(|i| i.map(Into::into)).collect();
+            reflection.check_color_targets("fs_main", &targets)
+        };
+        check(&[
+            Some(TextureFormat::Rgba8Unorm),
+            Some(TextureFormat::R32Uint),
+        ])
+        .unwrap();
+
+        assert_eq!(
+            error(check(&[Some(TextureFormat::Rgba8Unorm)])),
+            "color targets of fs_main: @location(1) id is written but the pipeline has 1 color targets"
+        );
+        assert_eq!(
+            error(check(&[Some(TextureFormat::Rgba8Unorm), None])),
+            "color targets of fs_main: @location(1) id is written but the pipeline has 2 color targets"
+        );
+        assert_eq!(
+            error(check(&[
+                Some(TextureFormat::Rgba8Unorm),
+                Some(TextureFormat::Rgba8Unorm)
+            ])),
+            "color targets of fs_main: @location(1) id is Uint in the shader but the target is Rgba8Unorm"
+        );
+    }
+
+    #[test]
+    fn bind_groups_must_cover_the_shader() {
+        let reflection = reflect(SHADER);
+        let entries = reflection.bind_group_layout_entries(1);
+        reflection.check_bind_group(1, &entries).unwrap();
+
+        assert_eq!(
+            error(reflection.check_bind_group(1, &entries[..3])),
+            "@group(1) @binding(3) doesn't match the layout: the shader uses it but the layout has no entry"
+        );
+
+        let mut vertex_only = reflection.bind_group_layout_entries(0);
+        vertex_only[0].visibility = ShaderStages::VERTEX;
+        let message = error(reflection.check_bind_group(0, &vertex_only));
+        assert!(
+            message.starts_with("@group(0) @binding(0) doesn't match the layout: used from"),
+            "{message}"
+        );
+
+        let mut read_only = reflection.bind_group_layout_entries(2);
+        read_only[1].ty = storage(true);
+        let message = error(reflection.check_bind_group(2, &read_only));
+        assert!(
+            message
+                .starts_with("@group(2) @binding(1) doesn't match the layout: the shader declares"),
+            "{message}"
+        );
+        // a writable layout entry can back a read only binding
+        let mut writable = reflection.bind_group_layout_entries(2);
+        writable[0].ty = storage(false);
+        reflection.check_bind_group(2, &writable).unwrap();
+    }
+
+    #[test]
+    fn merged_entries_combine_visibility() {
+        let vertex = reflect(
+            "
+@group(3) @binding(0) var<uniform> light: vec4<f32>;
+@vertex
+fn vs_main() -> @builtin(position) vec4<f32> { return light; }
+",
+        );
+        let fragment = reflect(
+            "
+@group(0) @binding(0) var<uniform> light: vec4<f32>;
+@fragment
+fn fs_main() -> @location(0) vec4<f32> { return light; }
+",
+        );
+        let merged = merge_bind_group_layout_entries(&[(&vertex, 3), (&fragment, 0)]).unwrap();
+        assert_eq!(merged.len(), 1);
+        assert_eq!(merged[0].visibility, ShaderStages::VERTEX_FRAGMENT);
+
+        let conflicting = reflect(
+            "
+@group(0) @binding(0) var light: texture_2d<f32>;
+@fragment
+fn fs_main() -> @location(0) vec4<f32> { return textureLoad(light, vec2(0), 0); }
+",
+        );
+        let message = error(merge_bind_group_layout_entries(&[
+            (&vertex, 3),
+            (&conflicting, 0),
+        ]));
+        assert!(
+            message.starts_with("@group(0) @binding(0): declared as"),
+            "{message}"
+        );
+    }
+
+    /// A vertex and a fragment stage passing `vertex_out` to `fragment_in`, both WGSL
+    /// struct members.
+    fn stages(vertex_out: &str, fragment_in: &str) -> Reflection {
+        reflect(&format!(
+            "
+struct VertexOutput {{
+    @builtin(position) clip: vec4<f32>,
+    {vertex_out}
+}}
+
+struct FragmentInput {{
+    {fragment_in}
+}}
+
+@vertex
+fn vs_main() -> VertexOutput {{
+    var out: VertexOutput;
+    return out;
+}}
+
+@fragment
+fn fs_main(in: FragmentInput) -> @location(0) vec4<f32> {{
+    return vec4(1.0);
+}}
+"
+        ))
+    }
+
+    fn check_stages(vertex_out: &str, fragment_in: &str) -> Result<(), Error> {
+        stages(vertex_out, fragment_in).check_stage_interface("vs_main", "fs_main")
+    }
+
+    #[test]
+    fn stage_interface_matches_types() {
+        reflect(SHADER)
+            .check_stage_interface("vs_main", "fs_main")
+            .unwrap();
+        check_stages(
+            "@location(0) uv: vec2<f32>, @location(1) extra: f32,",
+            "@location(0) uv: vec2<f32>,",
+        )
+        .unwrap();
+
+        assert_eq!(
+            error(check_stages(
+                "@location(0) uv: vec2<f32>,",
+                "@location(1) normal: vec3<f32>,"
+            )),
+            "vs_main -> fs_main: @location(1) normal is not written by the vertex stage"
+        );
+        assert_eq!(
+            error(check_stages(
+                "@location(0) uv: vec3<f32>,",
+                "@location(0) uv: vec2<f32>,"
+            )),
+            "vs_main -> fs_main: @location(0) uv is Floatx3 in the vertex stage but Floatx2 in the fragment stage"
+        );
+    }
+
+    #[test]
+    fn stage_interface_matches_interpolation() {
+        // spelling out the defaults is the same as leaving them out
+        check_stages(
+            "@location(0) @interpolate(perspective, center) uv: vec2<f32>,",
+            "@location(0) uv: vec2<f32>,",
+        )
+        .unwrap();
+        check_stages(
+            "@location(0) @interpolate(flat) id: u32,",
+            "@location(0) id: u32,",
+        )
+        .unwrap();
+
+        assert_eq!(
+            error(check_stages(
+                "@location(0) @interpolate(flat) uv: vec2<f32>,",
+                "@location(0) uv: vec2<f32>,"
+            )),
+            "vs_main -> fs_main: @location(0) uv is interpolated Flat in the vertex stage but Perspective Center in the fragment stage"
+        );
+        assert_eq!(
+            error(check_stages(
+                "@location(0) @interpolate(linear) uv: vec2<f32>,",
+                "@location(0) @interpolate(perspective) uv: vec2<f32>,"
+            )),
+            "vs_main -> fs_main: @location(0) uv is interpolated Linear Center in the vertex stage but Perspective Center in the fragment stage"
+        );
+        assert_eq!(
+            error(check_stages(
+                "@location(0) @interpolate(perspective, centroid) uv: vec2<f32>,",
+                "@location(0) uv: vec2<f32>,"
+            )),
+            "vs_main -> fs_main: @location(0) uv is interpolated Perspective Centroid in the vertex stage but Perspective Center in the fragment stage"
+        );
+    }
+}
@@ -0,0 +1,33 @@
+// GpuScene's textures, samplers and materials, bound at group 1
+#include "gf/material.wgsl"
+
+@group(1) @binding(0)
+var textures: binding_array<texture_2d<f32>>;
+@group(1) @binding(1)
+var samplers: binding_array<sampler>;
+@group(1) @binding(2)
+var<storage, read> materials: array<Material>;
+
+// Both uv sets with their screen space derivatives, taken in uniform control flow so the
+// extension textures can be sampled behind the feature bits
+struct ExtUv {
+    uv0: vec2<f32>,
+    uv1: vec2<f32>,
+    ddx0: vec2<f32>,
+    ddy0: vec2<f32>,
+    ddx1: vec2<f32>,
+    ddy1: vec2<f32>,
+}
+
+fn ext_uv(uv0: vec2<f32>, uv1: vec2<f32>) -> ExtUv {
+    return ExtUv(uv0, uv1, dpdx(uv0), dpdy(uv0), dpdx(uv1), dpdy(uv1));
+}
+
+// The gradients follow KHR_texture_transform without its offset
+fn sample_ext(texture: u32, sampler_id: u32, transform: mat3x3<f32>, uv_set: u32, uv: ExtUv) -> vec4<f32> {
+    let second = uv_set == 1u;
+    let coord = uv_transform(transform, select(uv.uv0, uv.uv1, second));
+    let ddx = (transform * vec3<f32>(select(uv.ddx0, uv.ddx1, second), 0.0)).xy;
+    let ddy = (transform * vec3<f32>(select(uv.ddy0, uv.ddy1, second), 0.0)).xy;
+    return textureSampleGrad(textures[texture], samplers[sampler_id], coord, ddx, ddy);
+}
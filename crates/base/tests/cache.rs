@@ -0,0 +1,154 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use gf_base::asset::cache::{self, BakeOption};
+use gf_base::asset::gltf::{load_gltf, GLTFBuffer, LoadOption, SceneView};
+use gf_base::wgpu::TextureFormat;
+
+mod common;
+use common::fixture;
+
+/// Copy fixtures into a directory of their own, the cache is written next to the glTF.
+fn scratch(test: &str, files: &[&str]) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("gf_cache_{test}_{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    for file in files {
+        fs::copy(fixture(file), dir.join(file)).unwrap();
+    }
+    dir.join(files[0])
+}
+
+fn bake(path: &Path, option: &LoadOption) {
+    cache::bake(path, option, &BakeOption::default()).unwrap();
+}
+
+fn uncached(path: &Path) -> (SceneView, GLTFBuffer) {
+    let option = LoadOption {
+        ignore_cache: true,
+        ..Default::default()
+    };
+    load_gltf(path, option).unwrap()
+}
+
+#[test]
+fn bake_then_load_round_trips() {
+    let path = scratch("round_trip", &["textured.gltf"]);
+    bake(&path, &LoadOption::default());
+    assert!(cache::cache_path(&path).exists());
+
+    let (view, buffer) = cache::load(&path, &LoadOption::default()).expect("fresh cache");
+    let (plain_view, plain_buffer) = uncached(&path);
+    assert_eq!(
+        format!("{:?}", view.nodes),
+        format!("{:?}", plain_view.nodes)
+    );
+    assert_eq!(
+        format!("{:?}", view.meshes),
+        format!("{:?}", plain_view.meshes)
+    );
+    assert_eq!(
+        format!("{:?}", view.materials),
+        format!("{:?}", plain_view.materials)
+    );
+    assert_eq!(view.samplers.len(), plain_view.samplers.len());
+    assert_eq!(buffer.positions, plain_buffer.positions);
+    assert_eq!(buffer.index, plain_buffer.index);
+    assert_eq!(buffer.texcoord, plain_buffer.texcoord);
+
+    // the 4x4 image is baked with its mip chain and without its PNG bytes
+    let image = view.images[0].decoded.as_ref().unwrap();
+    let plain_image = plain_view.images[0].decoded.as_ref().unwrap();
+    assert_eq!((image.width, image.height), (4, 4));
+    assert_eq!(image.format, TextureFormat::Rgba8UnormSrgb);
+    assert_eq!(image.mip_level_count, 3);
+    assert_eq!(image.data.len(), (16 + 4 + 1) * 4);
+    assert_eq!(image.data[..64], plain_image.data[..]);
+    assert!(buffer.shared_data.is_empty());
+
+    // load_gltf picks the cache up by itself
+    let (cached_view, _) = load_gltf(&path, LoadOption::default()).unwrap();
+    assert_eq!(
+        cached_view.images[0]
+            .decoded
+            .as_ref()
+            .unwrap()
+            .mip_level_count,
+        3
+    );
+}
+
+#[test]
+fn compressed_bakes_keep_bc_blocks() {
+    let path = scratch("compressed", &["textured.gltf"]);
+    cache::bake(
+        &path,
+        &LoadOption::default(),
+        &BakeOption { compress: true },
+    )
+    .unwrap();
+    let (view, _) = cache::load(&path, &LoadOption::default()).unwrap();
+    let image = view.images[0].decoded.as_ref().unwrap();
+    assert_eq!(image.format, TextureFormat::Bc1RgbaUnormSrgb);
+    // one block for each of the 4x4, 2x2 and 1x1 levels
+    assert_eq!(image.data.len(), 3 * 8);
+}
+
+#[test]
+fn editing_the_gltf_invalidates_the_cache() {
+    let path = scratch("gltf_edit", &["textured.gltf"]);
+    bake(&path, &LoadOption::default());
+    let mut gltf = fs::read(&path).unwrap();
+    gltf.push(b'\n');
+    fs::write(&path, gltf).unwrap();
+    assert!(cache::load(&path, &LoadOption::default()).is_none());
+}
+
+#[test]
+fn editing_a_dependency_invalidates_the_cache() {
+    let path = scratch("dependency_edit", &["interleaved.gltf", "interleaved.bin"]);
+    let bin = path.with_file_name("interleaved.bin");
+    bake(&path, &LoadOption::default());
+    assert!(cache::load(&path, &LoadOption::default()).is_some());
+
+    let mut bytes = fs::read(&bin).unwrap();
+    bytes[0] ^= 1;
+    fs::write(&bin, &bytes).unwrap();
+    assert!(cache::load(&path, &LoadOption::default()).is_none());
+
+    bake(&path, &LoadOption::default());
+    fs::remove_file(&bin).unwrap();
+    assert!(cache::load(&path, &LoadOption::default()).is_none());
+}
+
+#[test]
+fn only_content_options_invalidate_the_cache() {
+    let path = scratch("options", &["textured.gltf"]);
+    bake(&path, &LoadOption::default());
+
+    let mapped = LoadOption {
+        mmap: true,
+        ..Default::default()
+    };
+    assert!(cache::load(&path, &mapped).is_some());
+    let tbn = LoadOption {
+        gen_tbn: true,
+        ..Default::default()
+    };
+    assert!(cache::load(&path, &tbn).is_none());
+}
+
+#[test]
+fn broken_caches_are_ignored() {
+    let path = scratch("broken", &["textured.gltf"]);
+    assert!(cache::load(&path, &LoadOption::default()).is_none());
+
+    bake(&path, &LoadOption::default());
+    let cache = cache::cache_path(&path);
+    let bytes = fs::read(&cache).unwrap();
+    fs::write(&cache, &bytes[..bytes.len() / 2]).unwrap();
+    assert!(cache::load(&path, &LoadOption::default()).is_none());
+
+    fs::write(&cache, b"not a cache").unwrap();
+    assert!(cache::load(&path, &LoadOption::default()).is_none());
+}
@@ -0,0 +1,11 @@
+use std::path::PathBuf;
+
+/// A file of `tests/fixtures`. `nodes.gltf` is a node hierarchy sharing one triangle,
+/// `textured.gltf` a quad with a 4x4 PNG base color texture and `interleaved.gltf` a
+/// triangle whose positions and uvs share one strided buffer view.
+pub fn fixture(name: &str) -> PathBuf {
+    PathBuf::from(format!(
+        "{}/tests/fixtures/{name}",
+        env!("CARGO_MANIFEST_DIR")
+    ))
+}
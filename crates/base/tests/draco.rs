@@ -0,0 +1,96 @@
+#![cfg(feature = "draco")]
+
+use std::path::PathBuf;
+
+use gf_base::asset::gltf::{check_and_cast, load_gltf, GLTFBuffer, SceneView};
+
+fn sample_model(name: &str, variant: &str) -> PathBuf {
+    PathBuf::from(format!(
+        "{}/../../assets/gltf/glTF-Sample-Models/2.0/{name}/{variant}/{name}.gltf",
+        env!("CARGO_MANIFEST_DIR")
+    ))
+}
+
+fn bounds(scene_view: &SceneView, buffer: &GLTFBuffer) -> ([f32; 3], [f32; 3]) {
+    let mut min = [f32::MAX; 3];
+    let mut max = [f32::MIN; 3];
+    for mesh in scene_view.meshes.values() {
+        let positions: Vec<[f32; 3]> = check_and_cast(&buffer.positions, &mesh.positions);
+        for pos in positions {
+            for i in 0..3 {
+                min[i] = min[i].min(pos[i]);
+                max[i] = max[i].max(pos[i]);
+            }
+        }
+    }
+    (min, max)
+}
+
+/// Decode the draco variant and compare it against the uncompressed one,
+/// draco may reorder and dedup vertices so only index counts and bounds must match.
+fn compare_with_plain(name: &str) {
+    let draco_path = sample_model(name, "glTF-Draco");
+    assert!(
+        draco_path.exists(),
+        "{} is missing, check out the glTF-Sample-Models submodule",
+        draco_path.display()
+    );
+    let (draco_view, draco_buffer) = load_gltf(&draco_path, Default::default()).unwrap();
+    let (plain_view, plain_buffer) =
+        load_gltf(sample_model(name, "glTF"), Default::default()).unwrap();
+
+    assert_eq!(draco_view.nodes.len(), plain_view.nodes.len());
+    for (id, node) in &draco_view.nodes {
+        let plain_node = &plain_view.nodes[id];
+        assert_eq!(node.meshes, plain_node.meshes);
+        for (mesh, plain_mesh) in draco_view
+            .node_meshes(node)
+            .zip(plain_view.node_meshes(plain_node))
+        {
+            assert_eq!(mesh.index.count, plain_mesh.index.count);
+            assert!(mesh.vertex_count > 0);
+            assert_eq!(
+                mesh.positions.len(),
+                mesh.vertex_count * mesh.vertex_type_size
+            );
+            assert_eq!(mesh.normals.is_some(), plain_mesh.normals.is_some());
+            assert_eq!(mesh.uv0.is_some(), plain_mesh.uv0.is_some());
+
+            let indices: Vec<[u32; 1]> = check_and_cast(&draco_buffer.index, &mesh.index.indices);
+            assert!(indices.iter().all(|i| (i[0] as usize) < mesh.vertex_count));
+        }
+    }
+
+    // Positions are quantized by the encoder, allow a small error
+    let (draco_min, draco_max) = bounds(&draco_view, &draco_buffer);
+    let (plain_min, plain_max) = bounds(&plain_view, &plain_buffer);
+    for i in 0..3 {
+        let tolerance = (plain_max[i] - plain_min[i]).abs() * 0.01 + 1e-4;
+        assert!((draco_min[i] - plain_min[i]).abs() <= tolerance);
+        assert!((draco_max[i] - plain_max[i]).abs() <= tolerance);
+    }
+}
+
+#[test]
+#[ignore = "needs the glTF-Sample-Models submodule, run with --features draco -- --ignored"]
+fn draco_box() {
+    compare_with_plain("Box");
+}
+
+#[test]
+#[ignore = "needs the glTF-Sample-Models submodule, run with --features draco -- --ignored"]
+fn draco_duck() {
+    compare_with_plain("Duck");
+}
+
+#[test]
+#[ignore = "needs the glTF-Sample-Models submodule, run with --features draco -- --ignored"]
+fn draco_cesium_milk_truck() {
+    compare_with_plain("CesiumMilkTruck");
+}
+
+#[test]
+#[ignore = "needs the glTF-Sample-Models submodule, run with --features draco -- --ignored"]
+fn draco_2cylinder_engine() {
+    compare_with_plain("2CylinderEngine");
+}
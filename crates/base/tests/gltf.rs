@@ -0,0 +1,113 @@
+use std::f32::consts::FRAC_PI_2;
+
+use gf_base::asset::gltf::{check_and_cast, load_gltf, LoadOption, MaterialKey, SceneView};
+use glam::{Mat4, Quat, Vec3};
+
+mod common;
+use common::fixture;
+
+/// `parent` (0) holds `child` (1), `instanced` (2) and `camera` (3), `root` (4) sits next to it.
+/// Every node with a mesh draws the same triangle.
+fn load_nodes() -> SceneView {
+    let option = LoadOption {
+        ignore_cache: true,
+        ..Default::default()
+    };
+    load_gltf(fixture("nodes.gltf"), option).unwrap().0
+}
+
+fn assert_near(a: Mat4, b: Mat4) {
+    assert!(a.abs_diff_eq(b, 1e-5), "{a} != {b}");
+}
+
+#[test]
+fn shared_meshes_are_stored_once() {
+    let scene_view = load_nodes();
+    assert_eq!(scene_view.meshes.len(), 1);
+    for node in [1, 2, 4] {
+        assert_eq!(scene_view.nodes[&node].meshes, [(0, 0)]);
+    }
+    assert!(scene_view.nodes[&0].meshes.is_empty());
+}
+
+#[test]
+fn gpu_instancing_reads_instance_transforms() {
+    let scene_view = load_nodes();
+    let instances = &scene_view.nodes[&2].instances;
+    assert_eq!(instances.len(), 2);
+    assert_near(instances[0], Mat4::IDENTITY);
+    assert_near(
+        instances[1],
+        Mat4::from_rotation_translation(Quat::from_rotation_z(FRAC_PI_2), Vec3::X * 5.0),
+    );
+
+    // nodes without the extension are drawn once with their own transform
+    let root = &scene_view.nodes[&4];
+    assert!(root.instances.is_empty());
+    assert_eq!(
+        root.instance_transforms(),
+        [Mat4::from_scale(Vec3::splat(2.0))]
+    );
+}
+
+#[test]
+fn nested_nodes_apply_the_parent_transform_first() {
+    let scene_view = load_nodes();
+    let position = |node: usize| {
+        scene_view.nodes[&node]
+            .per_node_info
+            .transform
+            .transform_point3(Vec3::ZERO)
+    };
+    // `parent` moves to z 10 and turns +Z towards +X
+    assert!(position(1).abs_diff_eq(Vec3::new(0.0, 0.0, 9.0), 1e-5));
+    assert!(position(2).abs_diff_eq(Vec3::new(0.0, 2.0, 10.0), 1e-5));
+
+    let instances = scene_view.nodes[&2].instance_transforms();
+    let instance = instances[1].transform_point3(Vec3::ZERO);
+    assert!(instance.abs_diff_eq(Vec3::new(0.0, 2.0, 5.0), 1e-5));
+}
+
+#[test]
+fn cameras_take_the_world_transform_of_nested_nodes() {
+    let scene_view = load_nodes();
+    assert_eq!(scene_view.cameras.len(), 1);
+    let camera = &scene_view.cameras[0];
+    assert_eq!(camera.node_id, 3);
+    assert_eq!(camera.name.as_deref(), Some("camera"));
+    assert_near(
+        camera.transform,
+        Mat4::from_rotation_translation(Quat::from_rotation_y(FRAC_PI_2), Vec3::Z * 10.0)
+            * Mat4::from_translation(Vec3::Z * 5.0),
+    );
+    let position = camera.transform.transform_point3(Vec3::ZERO);
+    assert!(position.abs_diff_eq(Vec3::new(5.0, 0.0, 10.0), 1e-5));
+    let forward = camera.transform.transform_vector3(Vec3::NEG_Z);
+    assert!(forward.abs_diff_eq(Vec3::NEG_X, 1e-5));
+}
+
+#[test]
+fn second_uv_set_is_loaded_for_the_textures_using_it() {
+    let option = LoadOption {
+        ignore_cache: true,
+        ..Default::default()
+    };
+    let (scene_view, buffer) = load_gltf(fixture("textured.gltf"), option).unwrap();
+    let mesh = &scene_view.meshes[&(0, 0)];
+    let uv0: Vec<[f32; 2]> = check_and_cast(&buffer.texcoord[0], mesh.uv0.as_ref().unwrap());
+    let uv1: Vec<[f32; 2]> = check_and_cast(&buffer.texcoord[1], mesh.uv1.as_ref().unwrap());
+    assert_eq!(uv0.len(), 4);
+    assert_eq!(uv1, uv0);
+
+    let material = &scene_view.materials[0];
+    assert_eq!(material.get(&MaterialKey::BaseColor).unwrap().tex_coord, 0);
+    assert_eq!(material.get(&MaterialKey::Emissive).unwrap().tex_coord, 1);
+}
+
+#[test]
+fn missing_second_uv_set_stays_aligned_with_positions() {
+    let scene_view = load_nodes();
+    let mesh = &scene_view.meshes[&(0, 0)];
+    let uv1 = mesh.uv1.as_ref().unwrap();
+    assert_eq!(uv1.len(), mesh.vertex_count * 8);
+}
@@ -0,0 +1,80 @@
+use std::thread;
+use std::time::{Duration, Instant};
+
+use gf_base::asset::gltf::{LoadOption, LoadProgress};
+use gf_base::asset::loader::{GltfLoader, LoadEvent};
+
+mod common;
+use common::fixture;
+
+/// Poll until the loader is done, `Loaded` or `Failed` must be the last event.
+fn events(loader: &GltfLoader) -> Vec<LoadEvent> {
+    let start = Instant::now();
+    let mut events = vec![];
+    loop {
+        events.extend(loader.poll());
+        if matches!(
+            events.last(),
+            Some(LoadEvent::Loaded(_) | LoadEvent::Failed(_))
+        ) {
+            thread::sleep(Duration::from_millis(10));
+            assert!(loader.poll().next().is_none(), "events after the end");
+            return events;
+        }
+        assert!(start.elapsed() < Duration::from_secs(30), "loader hung");
+        thread::sleep(Duration::from_millis(1));
+    }
+}
+
+fn spawn(name: &str) -> GltfLoader {
+    let option = LoadOption {
+        ignore_cache: true,
+        ..Default::default()
+    };
+    GltfLoader::spawn(fixture(name), option)
+}
+
+#[test]
+fn progress_comes_before_loaded() {
+    let events = events(&spawn("textured.gltf"));
+    let (last, progress) = events.split_last().unwrap();
+    let LoadEvent::Loaded(loaded) = last else {
+        panic!("expected Loaded, got {last:?}");
+    };
+    assert!(loaded.0.images[0].decoded.is_some());
+
+    let progress: Vec<_> = progress
+        .iter()
+        .map(|i| match i {
+            LoadEvent::Progress(progress) => *progress,
+            other => panic!("{other:?} before the last event"),
+        })
+        .collect();
+    // files are read before any image is decoded, the byte count only grows
+    let decoding = progress
+        .iter()
+        .position(|i| matches!(i, LoadProgress::ImageDecoded { .. }))
+        .unwrap();
+    let bytes_read: Vec<_> = progress[..decoding]
+        .iter()
+        .map(|i| match i {
+            LoadProgress::BytesRead(bytes) => *bytes,
+            other => panic!("{other:?} while reading"),
+        })
+        .collect();
+    assert!(!bytes_read.is_empty());
+    assert!(bytes_read.windows(2).all(|i| i[0] < i[1]));
+    assert!(matches!(
+        progress[decoding..],
+        [LoadProgress::ImageDecoded {
+            decoded: 1,
+            total: 1
+        }]
+    ));
+}
+
+#[test]
+fn failure_is_the_last_event() {
+    let events = events(&spawn("missing.gltf"));
+    assert!(matches!(events.as_slice(), [LoadEvent::Failed(_)]));
+}
@@ -0,0 +1,70 @@
+use std::borrow::Cow;
+
+use gf_base::asset::gltf::{check_and_cast, load_gltf, GltfSource, LoadOption};
+
+mod common;
+use common::fixture;
+
+fn open(name: &str, mmap: bool) -> GltfSource {
+    GltfSource::open(fixture(name), mmap, &mut |_| {}).unwrap()
+}
+
+#[test]
+fn tight_accessors_are_borrowed() {
+    for mmap in [false, true] {
+        let source = open("textured.gltf", mmap);
+        let positions = source.accessor(0).unwrap();
+        assert!(matches!(positions, Cow::Borrowed(_)));
+        assert_eq!(positions.len(), 4 * 12);
+        assert_eq!(source.accessor(2).unwrap().len(), 6 * 2);
+    }
+}
+
+#[test]
+fn strided_accessors_drop_the_stride() {
+    for mmap in [false, true] {
+        let source = open("interleaved.gltf", mmap);
+        let positions = source.accessor(0).unwrap();
+        let uvs = source.accessor(1).unwrap();
+        assert!(matches!(positions, Cow::Owned(_)));
+        assert!(matches!(uvs, Cow::Owned(_)));
+
+        let positions: Vec<[f32; 3]> = check_and_cast(&positions, &(0..positions.len()));
+        let uvs: Vec<[f32; 2]> = check_and_cast(&uvs, &(0..uvs.len()));
+        assert_eq!(
+            positions,
+            [[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]]
+        );
+        assert_eq!(uvs, [[0.0, 0.0], [1.0, 0.0], [0.0, 1.0]]);
+    }
+}
+
+#[test]
+fn buffer_views_are_borrowed_whole() {
+    let source = open("interleaved.gltf", true);
+    assert_eq!(source.buffer_view(0).unwrap().len(), 3 * 20);
+    assert_eq!(source.buffer_view(1).unwrap(), [0, 0, 1, 0, 2, 0]);
+    assert!(source.buffer_view(2).is_err());
+}
+
+#[test]
+fn interleaved_meshes_load_like_packed_ones() {
+    let option = LoadOption {
+        mmap: true,
+        ignore_cache: true,
+        ..Default::default()
+    };
+    let (scene_view, buffer) = load_gltf(fixture("interleaved.gltf"), option).unwrap();
+    let mesh = &scene_view.meshes[&(0, 0)];
+    assert_eq!(mesh.vertex_count, 3);
+
+    let positions: Vec<[f32; 3]> = check_and_cast(&buffer.positions, &mesh.positions);
+    let uvs: Vec<[f32; 2]> = check_and_cast(&buffer.texcoord[0], mesh.uv0.as_ref().unwrap());
+    let indices: Vec<[u32; 1]> = check_and_cast(&buffer.index, &mesh.index.indices);
+    assert_eq!(
+        positions,
+        [[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]]
+    );
+    assert_eq!(uvs, [[0.0, 0.0], [1.0, 0.0], [0.0, 1.0]]);
+    assert_eq!(indices, [[0], [1], [2]]);
+}
@@ -24,17 +24,10 @@ fn vs_main(
 }
 
 // Fragment Uniform
-struct Camera {
-    view_pos: vec4<f32>,
-    view_proj: mat4x4<f32>,
-}
-@group(0) @binding(0)
-var<uniform> camera: Camera;
+#include "gf/camera.wgsl"
+#include "gf/material.wgsl"
+#include "gf/light.wgsl"
 
-struct Light {
-    position: vec3<f32>,
-    color: vec3<f32>,
-}
 @group(2) @binding(0)
 var<uniform> light: Light;
 
@@ -48,6 +41,8 @@ var normal_gb: texture_2d<f32>;
 var albedo_gb: texture_2d<f32>;
 @group(1) @binding(4)
 var s1: sampler;
+@group(1) @binding(5)
+var emissive_gb: texture_2d<f32>;
 
 struct FragOut {
     @location(0) color: vec4<f32>
@@ -56,12 +51,20 @@ struct FragOut {
 @fragment
 fn fs_main(in: VertexOutput) -> FragOut {
     var out: FragOut;
-    let pos = textureSample(pos_gb, s, in.uv).rgb;
+    let pos_ao = textureSample(pos_gb, s, in.uv);
+    let pos = pos_ao.rgb;
+    let ao = pos_ao.a;
     let normal = textureSample(normal_gb, s, in.uv).rgb;
     let albedo = textureSample(albedo_gb, s1, in.uv);
+    let emissive_shading = textureSample(emissive_gb, s, in.uv);
+    let emissive = emissive_shading.rgb;
+    if u32(emissive_shading.a) == SHADING_UNLIT {
+        out.color = vec4<f32>(albedo.rgb, 1.0);
+        return out;
+    }
 
     let ambient_strength = 0.005;
-    let ambient = light.color * ambient_strength;
+    let ambient = light.color * ambient_strength * ao;
 
     let light_direction = normalize(light.position - pos);
     let diffuse_strength = max(dot(normal, light_direction), 0.0);
@@ -74,8 +77,8 @@ fn fs_main(in: VertexOutput) -> FragOut {
 
     let specular = spec_strength * light.color;
 
-    let out_color = albedo.rgb * (ambient + diffuse + specular);
+    let out_color = albedo.rgb * (ambient + diffuse + specular) + emissive;
 
     out.color = vec4<f32>(out_color, 1.0);
     return out;
-}
\ No newline at end of file
+}
@@ -0,0 +1,77 @@
+//Weighted blended OIT pass for BLEND materials, composited over the deferred lighting
+
+#include "gf/mesh.wgsl"
+#include "gf/scene.wgsl"
+#include "gf/light.wgsl"
+
+@group(2) @binding(0)
+var<uniform> light: Light;
+
+struct OitOut {
+    @location(0) accum: vec4<f32>,
+    @location(1) revealage: f32,
+}
+
+@fragment
+fn fs_main(in: VertexOutput, @builtin(front_facing) front_facing: bool) -> OitOut {
+    let mat = materials[in.material];
+
+    let base_color = textureSample(
+        textures[mat.base_color],
+        samplers[mat.base_color_sampler],
+        uv_transform(mat.base_color_transform, tex_coord(in, mat.base_color_tex_coord))
+    ) * mat.base_color_factor;
+    let tex_color = base_color.rgb;
+
+    var normal = textureSample(
+        textures[mat.normal],
+        samplers[mat.normal_sampler],
+        uv_transform(mat.normal_transform, tex_coord(in, mat.normal_tex_coord))
+    ).rgb;
+
+    let emissive = textureSample(
+        textures[mat.emissive],
+        samplers[mat.emissive_sampler],
+        uv_transform(mat.emissive_transform, tex_coord(in, mat.emissive_tex_coord))
+    ).rgb * mat.emissive_factor.rgb;
+
+    let ao = textureSample(
+        textures[mat.occlusion],
+        samplers[mat.occlusion_sampler],
+        uv_transform(mat.occlusion_transform, tex_coord(in, mat.occlusion_tex_coord))
+    ).r;
+    let occlusion = 1.0 + mat.occlusion_strength * (ao - 1.0);
+
+    let tbn = surface_tbn(in, front_facing);
+    normal = (normal * 2.0 - 1.0) * vec3<f32>(mat.normal_scale, mat.normal_scale, 1.0);
+    normal = normalize(tbn * normal);
+
+    let ambient_strength = 0.005;
+    let ambient = light.color * ambient_strength * occlusion;
+
+    let light_direction = normalize(light.position - in.pos);
+    let diffuse_strength = max(dot(normal, light_direction), 0.0);
+    let diffuse = light.color * diffuse_strength;
+
+    let view_direction = normalize(camera.view_pos.rgb - in.pos);
+    let half_direction = normalize(view_direction + light_direction);
+    let shiness = 32.;
+    let spec_strength = pow(max(dot(normal, half_direction), 0.), shiness);
+
+    let specular = spec_strength * light.color;
+
+    var out_color = tex_color * (ambient + diffuse + specular) + emissive;
+    if mat.shading_model == SHADING_UNLIT {
+        out_color = tex_color;
+    }
+
+    // Weight nearer and more opaque fragments higher, clip_position.z is the depth
+    let alpha = base_color.a;
+    let z = in.clip_position.z;
+    let weight = clamp(pow(min(1.0, alpha * 10.0) + 0.01, 3.0) * 1e8 * pow(1.0 - z * 0.9, 3.0), 1e-2, 3e3);
+
+    var out: OitOut;
+    out.accum = vec4<f32>(out_color * alpha, alpha) * weight;
+    out.revealage = alpha;
+    return out;
+}
@@ -1,38 +1,47 @@
-use std::mem::size_of;
-use std::num::NonZeroU32;
 use std::time::Duration;
 
 use gf_base::{
-    asset::gltf::{load_gltf, LoadOption, MaterialKey, SInto},
+    asset::gltf::{load_gltf, LoadOption},
     downcast_mut,
     glam::{Mat3, Mat4},
-    image::GenericImageView,
+    oit::WeightedBlendedOit,
+    scene::GpuScene,
     snafu::{OptionExt, ResultExt},
     texture::{self},
     wgpu::{
         self,
-        util::{BufferInitDescriptor, DeviceExt, DrawIndexedIndirect},
+        util::{BufferInitDescriptor, DeviceExt},
         BindGroupDescriptor, BindGroupEntry, BindGroupLayout, BindGroupLayoutDescriptor,
         BindGroupLayoutEntry, DepthStencilState, Operations, PipelineLayoutDescriptor,
-        RenderPassDepthStencilAttachment, ShaderStages, TextureDescriptor,
-        VertexFormat::*,
+        RenderPassDepthStencilAttachment, ShaderStages,
     },
-    winit, App, BaseState, Error, GLTFErrSnafu, ImageLoadErrSnafu, NoneErrSnafu, StateDynObj,
+    winit, App, BaseState, Error, GLTFErrSnafu, NoneErrSnafu, ShaderErrSnafu, StateDynObj,
     SurfaceErrSnafu,
 };
 
+/// Embedded as composer libraries, the sample doesn't hot reload them.
+const SHADERS: [(&str, &str); 3] = [
+    (
+        "deferred_render/gbuffer_shader.wgsl",
+        include_str!("gbuffer_shader.wgsl"),
+    ),
+    (
+        "deferred_render/deferred_shader.wgsl",
+        include_str!("deferred_shader.wgsl"),
+    ),
+    (
+        "deferred_render/forward_shader.wgsl",
+        include_str!("forward_shader.wgsl"),
+    ),
+];
+
 struct State {
     gbuffer_pipeline: wgpu::RenderPipeline,
-    index: wgpu::Buffer,
-    vertices: wgpu::Buffer,
-    normal: wgpu::Buffer,
-    uv0: wgpu::Buffer,
-    tangent: wgpu::Buffer,
-    bi_tangent: wgpu::Buffer,
-    obj_count: usize,
-    obj_buf: wgpu::Buffer,
-    indirect_buf: wgpu::Buffer,
-    tex_bind_group: wgpu::BindGroup,
+    gbuffer_double_sided_pipeline: wgpu::RenderPipeline,
+    forward_pipeline: wgpu::RenderPipeline,
+    forward_double_sided_pipeline: wgpu::RenderPipeline,
+    oit: WeightedBlendedOit,
+    scene: GpuScene,
 
     emissive_pipeline: wgpu::RenderPipeline,
     light_buf: wgpu::Buffer,
@@ -48,23 +57,11 @@ struct State {
     position_gb: texture::Texture,
     normal_gb: texture::Texture,
     albedo_gb: texture::Texture,
+    emissive_gb: texture::Texture,
 }
 
 impl StateDynObj for State {}
 
-#[repr(C, align(16))]
-#[derive(Copy, Clone, Debug, Default, bytemuck::Pod, bytemuck::Zeroable)]
-struct PerObjData {
-    base_color: u32,
-    sampler: u32,
-    normal: u32,
-    normal_sampler: u32,
-    metallic: u32,
-    metallic_sampler: u32,
-    pub transform: [[f32; 4]; 4],
-    _padding: [u32; 2],
-}
-
 #[repr(C)]
 #[derive(Copy, Clone, Debug, Default, bytemuck::Pod, bytemuck::Zeroable)]
 struct PerObjB {
@@ -130,244 +127,16 @@ fn init(base_state: &mut BaseState) -> Result<(), Error> {
         env!("CARGO_MANIFEST_DIR")
     );
 
-    let (scene_view, scene_buffer) =
-        load_gltf(&path, LoadOption { gen_tbn: true }).context(GLTFErrSnafu)?;
-
-    //Load scene data to wgpu buffer
-    let vert_buf = device.create_buffer_init(&BufferInitDescriptor {
-        label: Some("vertex"),
-        contents: &scene_buffer.positions,
-        usage: wgpu::BufferUsages::VERTEX,
-    });
-    let index_buf = device.create_buffer_init(&BufferInitDescriptor {
-        label: Some("index"),
-        contents: &scene_buffer.index,
-        usage: wgpu::BufferUsages::INDEX,
-    });
-    let normal_buf = device.create_buffer_init(&BufferInitDescriptor {
-        label: Some("normal"),
-        contents: bytemuck::cast_slice(&scene_buffer.normal),
-        usage: wgpu::BufferUsages::VERTEX,
-    });
-    let uv0_buf = device.create_buffer_init(&BufferInitDescriptor {
-        label: Some("uv0"),
-        contents: bytemuck::cast_slice(scene_buffer.texcoord.get(0).context(NoneErrSnafu)?),
-        usage: wgpu::BufferUsages::VERTEX,
-    });
-    let tangent_buf = device.create_buffer_init(&BufferInitDescriptor {
-        label: Some("tangent"),
-        contents: bytemuck::cast_slice(&scene_buffer.tangent),
-        usage: wgpu::BufferUsages::VERTEX,
-    });
-    let bi_tangent_buf = device.create_buffer_init(&BufferInitDescriptor {
-        label: Some("bi_tangent"),
-        contents: bytemuck::cast_slice(&scene_buffer.bi_tangent),
-        usage: wgpu::BufferUsages::VERTEX,
-    });
-
-    //Collect Indirect commands&generate per object buffer
-    let mut indirect = Vec::new();
-    let mut obj_count = 0;
-    let mut per_obj_data = vec![];
-    for node in &scene_view.nodes {
-        let node = node.1;
-        for mesh in &node.meshes {
-            indirect.push(DrawIndexedIndirect {
-                vertex_count: mesh.index.count as u32,
-                instance_count: 1,
-                base_index: (mesh.index.indices.start / mesh.index.type_size) as u32,
-                vertex_offset: (mesh.positions.start / mesh.vertex_type_size) as i32,
-                base_instance: obj_count,
-            });
-            let gltf_mat = &scene_view.materials[mesh.mat.context(NoneErrSnafu)?];
-            let base_color = gltf_mat
-                .get(&MaterialKey::BaseColor)
-                .context(NoneErrSnafu)?;
-            let normal_tex = gltf_mat.get(&MaterialKey::Normal).context(NoneErrSnafu)?;
-            let metallic_tex = gltf_mat
-                .get(&MaterialKey::MetallicRoughness)
-                .context(NoneErrSnafu)?;
-
-            let per_obj = PerObjData {
-                transform: node.per_node_info.transform.to_cols_array_2d(),
-                base_color: base_color.image_id.context(NoneErrSnafu)? as u32,
-                sampler: base_color.sampler as u32,
-                normal: normal_tex.image_id.context(NoneErrSnafu)? as u32,
-                normal_sampler: normal_tex.sampler as u32,
-                metallic: metallic_tex.image_id.context(NoneErrSnafu)? as u32,
-                metallic_sampler: metallic_tex.sampler as u32,
-                _padding: [0; 2],
-            };
-            per_obj_data.push(per_obj);
-
-            obj_count += 1;
-        }
-    }
-
-    let obj_buf = device.create_buffer_init(&BufferInitDescriptor {
-        label: Some("Per obj buffer"),
-        contents: bytemuck::cast_slice(&per_obj_data),
-        usage: wgpu::BufferUsages::VERTEX,
-    });
-
-    //scene texture&sampler
-    let mut texture_view_vec = vec![];
-    for img_info in &scene_view.images {
-        let color_source_data = &scene_buffer.shared_data[img_info.range.clone()];
-        let dyn_img =
-            gf_base::image::load_from_memory(color_source_data).context(ImageLoadErrSnafu)?;
-        let img_dimensions = dyn_img.dimensions();
-        let img_rgb = dyn_img.to_rgba8();
-
-        let tex = device.create_texture_with_data(
-            queue,
-            &TextureDescriptor {
-                label: img_info.name.as_deref(),
-                size: wgpu::Extent3d {
-                    width: img_dimensions.0,
-                    height: img_dimensions.1,
-                    depth_or_array_layers: 1,
-                },
-                mip_level_count: 1,
-                sample_count: 1,
-                dimension: wgpu::TextureDimension::D2,
-                format: img_info.target_format,
-                usage: wgpu::TextureUsages::TEXTURE_BINDING,
-                view_formats: &[],
-            },
-            &img_rgb,
-        );
-        let tex_view = tex.create_view(&wgpu::TextureViewDescriptor::default());
-        texture_view_vec.push(tex_view);
-    }
-
-    let mut samplers = vec![];
-    for sampler in &scene_view.samplers {
-        let desc: wgpu::SamplerDescriptor<'_> = sampler.t_into();
-        let wgpu_sampler = device.create_sampler(&desc);
-        samplers.push(wgpu_sampler);
-    }
-    if samplers.is_empty() {
-        let desc = wgpu::SamplerDescriptor {
-            label: Some("Default Sampler"),
+    let (scene_view, scene_buffer) = load_gltf(
+        &path,
+        LoadOption {
+            gen_tbn: true,
             ..Default::default()
-        };
-        let wgpu_sampler = device.create_sampler(&desc);
-        samplers.push(wgpu_sampler);
-    }
-
-    let indirect: Vec<u8> = indirect
-        .iter()
-        .map(|i| i.as_bytes())
-        .flat_map(|i| i.iter())
-        .copied()
-        .collect();
-
-    let indirect_buf = device.create_buffer_init(&BufferInitDescriptor {
-        label: Some("indirect"),
-        contents: &indirect,
-        usage: wgpu::BufferUsages::INDIRECT,
-    });
-
-    // Vertex Layout
-    let vertex_layout = wgpu::VertexBufferLayout {
-        array_stride: Float32x3.size(),
-        step_mode: wgpu::VertexStepMode::Vertex,
-        attributes: &wgpu::vertex_attr_array![
-            0 => Float32x3,
-        ],
-    };
-    let normal_layout = wgpu::VertexBufferLayout {
-        array_stride: Float32x3.size(),
-        step_mode: wgpu::VertexStepMode::Vertex,
-        attributes: &wgpu::vertex_attr_array![
-            1 => Float32x3,
-        ],
-    };
-    let uv0_layout = wgpu::VertexBufferLayout {
-        array_stride: Float32x2.size(),
-        step_mode: wgpu::VertexStepMode::Vertex,
-        attributes: &wgpu::vertex_attr_array![
-            2 => Float32x2,
-        ],
-    };
-    let tangent_layout = wgpu::VertexBufferLayout {
-        array_stride: Float32x4.size(),
-        step_mode: wgpu::VertexStepMode::Vertex,
-        attributes: &wgpu::vertex_attr_array![
-            3 => Float32x4,
-        ],
-    };
-    let bi_tangent_layout = wgpu::VertexBufferLayout {
-        array_stride: Float32x3.size(),
-        step_mode: wgpu::VertexStepMode::Vertex,
-        attributes: &wgpu::vertex_attr_array![
-            4 => Float32x3,
-        ],
-    };
-
-    let object_layout = wgpu::VertexBufferLayout {
-        array_stride: size_of::<PerObjData>() as u64,
-        step_mode: wgpu::VertexStepMode::Instance,
-        attributes: &wgpu::vertex_attr_array![
-            8 => Uint32,
-            9 => Uint32,
-            10 => Uint32,
-            11 => Uint32,
-            12 => Uint32,
-            13 => Uint32,
-
-            14 => Float32x4,
-            15 => Float32x4,
-            16 => Float32x4,
-            17 => Float32x4,
-        ],
-    };
-    // bind group layout
-    let tex_bind_group_layout: wgpu::BindGroupLayout =
-        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-            label: None,
-            entries: &[
-                wgpu::BindGroupLayoutEntry {
-                    binding: 0,
-                    visibility: wgpu::ShaderStages::FRAGMENT,
-                    ty: wgpu::BindingType::Texture {
-                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
-                        view_dimension: wgpu::TextureViewDimension::D2,
-                        multisampled: false,
-                    },
-                    count: NonZeroU32::new(texture_view_vec.len() as u32),
-                },
-                wgpu::BindGroupLayoutEntry {
-                    binding: 1,
-                    visibility: wgpu::ShaderStages::FRAGMENT,
-                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
-                    count: NonZeroU32::new(samplers.len() as u32),
-                },
-            ],
-        });
+        },
+    )
+    .context(GLTFErrSnafu)?;
 
-    let tex_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-        label: None,
-        layout: &tex_bind_group_layout,
-        entries: &[
-            wgpu::BindGroupEntry {
-                binding: 0,
-                resource: wgpu::BindingResource::TextureViewArray(
-                    texture_view_vec
-                        .iter()
-                        .collect::<Vec<&wgpu::TextureView>>()
-                        .as_slice(),
-                ),
-            },
-            wgpu::BindGroupEntry {
-                binding: 1,
-                resource: wgpu::BindingResource::SamplerArray(
-                    samplers.iter().collect::<Vec<&wgpu::Sampler>>().as_slice(),
-                ),
-            },
-        ],
-    });
+    let scene = GpuScene::new(device, queue, &scene_view, &scene_buffer)?;
     let light_bind_group_layout =
         device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
             entries: &[wgpu::BindGroupLayoutEntry {
@@ -384,79 +153,103 @@ fn init(base_state: &mut BaseState) -> Result<(), Error> {
         });
 
     //GBuffer Attachments
-    let (position_gb, normal_gb, albedo_gb, gbuffer_bind_group_layout, gbuffer_bind_group) =
-        prepare_gbuffer_resource(base_state)?;
+    let (
+        position_gb,
+        normal_gb,
+        albedo_gb,
+        emissive_gb,
+        gbuffer_bind_group_layout,
+        gbuffer_bind_group,
+    ) = prepare_gbuffer_resource(base_state)?;
 
     //Gbuffer pipeline
 
-    let gbuffer_shader = device.create_shader_module(wgpu::include_wgsl!("gbuffer_shader.wgsl"));
+    for (name, source) in SHADERS {
+        base_state.shaders.add_library(name, source);
+    }
+    let gbuffer_shader = base_state
+        .shaders
+        .module(
+            device,
+            "deferred_render/gbuffer_shader.wgsl",
+            &["ALPHA_MASK", "HAS_NORMAL_MAP"],
+        )
+        .context(ShaderErrSnafu)?;
 
     let gbuffer_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
         label: Some("GBuffer Pipeline Layout"),
-        bind_group_layouts: &[&base_state.camera_bind_group_layout, &tex_bind_group_layout],
+        bind_group_layouts: &[
+            &base_state.camera_bind_group_layout,
+            &scene.tex_bind_group_layout,
+        ],
         push_constant_ranges: &[],
     });
 
-    let gbuffer_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-        label: Some("GBuffer Render Layout"),
-        layout: Some(&gbuffer_pipeline_layout),
-        vertex: wgpu::VertexState {
-            module: &gbuffer_shader,
-            entry_point: "vs_main",
-            buffers: &[
-                vertex_layout.clone(),
-                normal_layout,
-                uv0_layout.clone(),
-                tangent_layout,
-                object_layout,
-            ],
-        },
-        primitive: wgpu::PrimitiveState {
-            topology: wgpu::PrimitiveTopology::TriangleList,
-            strip_index_format: None,
-            front_face: wgpu::FrontFace::Ccw,
-            cull_mode: Some(wgpu::Face::Back),
-            unclipped_depth: false,
-            polygon_mode: wgpu::PolygonMode::Fill,
-            conservative: false,
-        },
-        depth_stencil: Some(DepthStencilState {
-            format: texture::Texture::DEPTH_FORMAT,
-            depth_write_enabled: true,
-            depth_compare: wgpu::CompareFunction::Less,
-            stencil: wgpu::StencilState::default(),
-            bias: wgpu::DepthBiasState::default(),
-        }),
-        multisample: wgpu::MultisampleState {
-            count: 1,
-            mask: !0,
-            alpha_to_coverage_enabled: false,
-        },
-        fragment: Some(wgpu::FragmentState {
-            module: &gbuffer_shader,
-            entry_point: "fs_main",
-            targets: &[
-                //Position
-                Some(wgpu::ColorTargetState {
-                    format: wgpu::TextureFormat::Rgba16Float,
-                    blend: Some(wgpu::BlendState::REPLACE),
-                    write_mask: wgpu::ColorWrites::ALL,
-                }),
-                //Normal
-                Some(wgpu::ColorTargetState {
-                    format: wgpu::TextureFormat::Rgba16Float,
-                    blend: Some(wgpu::BlendState::REPLACE),
-                    write_mask: wgpu::ColorWrites::ALL,
-                }),
-                Some(wgpu::ColorTargetState {
-                    format: wgpu::TextureFormat::Rgba16Float,
-                    blend: None,
-                    write_mask: wgpu::ColorWrites::ALL,
-                }),
-            ],
-        }),
-        multiview: None,
-    });
+    let create_gbuffer_pipeline = |cull_mode| {
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("GBuffer Render Layout"),
+            layout: Some(&gbuffer_pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &gbuffer_shader,
+                entry_point: "vs_main",
+                buffers: &GpuScene::vertex_layouts(),
+            },
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode,
+                unclipped_depth: false,
+                polygon_mode: wgpu::PolygonMode::Fill,
+                conservative: false,
+            },
+            depth_stencil: Some(DepthStencilState {
+                format: texture::Texture::DEPTH_FORMAT,
+                depth_write_enabled: true,
+                depth_compare: wgpu::CompareFunction::Less,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState {
+                count: 1,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &gbuffer_shader,
+                entry_point: "fs_main",
+                targets: &[
+                    //Position
+                    Some(wgpu::ColorTargetState {
+                        format: wgpu::TextureFormat::Rgba16Float,
+                        blend: Some(wgpu::BlendState::REPLACE),
+                        write_mask: wgpu::ColorWrites::ALL,
+                    }),
+                    //Normal
+                    Some(wgpu::ColorTargetState {
+                        format: wgpu::TextureFormat::Rgba16Float,
+                        blend: Some(wgpu::BlendState::REPLACE),
+                        write_mask: wgpu::ColorWrites::ALL,
+                    }),
+                    Some(wgpu::ColorTargetState {
+                        format: wgpu::TextureFormat::Rgba16Float,
+                        blend: None,
+                        write_mask: wgpu::ColorWrites::ALL,
+                    }),
+                    //Emissive
+                    Some(wgpu::ColorTargetState {
+                        format: wgpu::TextureFormat::Rgba16Float,
+                        blend: None,
+                        write_mask: wgpu::ColorWrites::ALL,
+                    }),
+                ],
+            }),
+            multiview: None,
+        })
+    };
+    let gbuffer_pipeline = create_gbuffer_pipeline(Some(wgpu::Face::Back));
+    // MASK materials discard in the shader, so double sided ones only differ in culling
+    let gbuffer_double_sided_pipeline = create_gbuffer_pipeline(None);
 
     //Deferred render
     let quad_vertices = [
@@ -497,7 +290,10 @@ fn init(base_state: &mut BaseState) -> Result<(), Error> {
         push_constant_ranges: &[],
     });
 
-    let deferred_shader = device.create_shader_module(wgpu::include_wgsl!("deferred_shader.wgsl"));
+    let deferred_shader = base_state
+        .shaders
+        .module(device, "deferred_render/deferred_shader.wgsl", &[])
+        .context(ShaderErrSnafu)?;
 
     let deferred_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
         label: Some("Deferred Pipeline"),
@@ -505,7 +301,7 @@ fn init(base_state: &mut BaseState) -> Result<(), Error> {
         vertex: wgpu::VertexState {
             module: &deferred_shader,
             entry_point: "vs_main",
-            buffers: &[vertex_layout.clone(), uv0_layout],
+            buffers: &[GpuScene::POSITION_LAYOUT, GpuScene::UV0_LAYOUT],
         },
         primitive: wgpu::PrimitiveState {
             topology: wgpu::PrimitiveTopology::TriangleList,
@@ -559,23 +355,68 @@ fn init(base_state: &mut BaseState) -> Result<(), Error> {
         label: None,
     });
 
+    //OIT pass for BLEND materials
+    let oit = WeightedBlendedOit::new(device, &base_state.config);
+    let forward_pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+        label: Some("Forward Pipeline layout"),
+        bind_group_layouts: &[
+            &base_state.camera_bind_group_layout,
+            &scene.tex_bind_group_layout,
+            &light_bind_group_layout,
+        ],
+        push_constant_ranges: &[],
+    });
+    let forward_shader = base_state
+        .shaders
+        .module(device, "deferred_render/forward_shader.wgsl", &[])
+        .context(ShaderErrSnafu)?;
+    let create_forward_pipeline = |cull_mode| {
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Forward Pipeline"),
+            layout: Some(&forward_pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &forward_shader,
+                entry_point: "vs_main",
+                buffers: &GpuScene::vertex_layouts(),
+            },
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode,
+                unclipped_depth: false,
+                polygon_mode: wgpu::PolygonMode::Fill,
+                conservative: false,
+            },
+            depth_stencil: Some(WeightedBlendedOit::depth_stencil()),
+            multisample: wgpu::MultisampleState {
+                count: 1,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &forward_shader,
+                entry_point: "fs_main",
+                targets: &WeightedBlendedOit::color_targets(),
+            }),
+            multiview: None,
+        })
+    };
+    let forward_pipeline = create_forward_pipeline(Some(wgpu::Face::Back));
+    let forward_double_sided_pipeline = create_forward_pipeline(None);
+
     let cube_path = format!("{}/../../assets/gltf/cube.glb", env!("CARGO_MANIFEST_DIR"));
 
     let (scene_view, scene_buffer) =
         load_gltf(cube_path, Default::default()).context(GLTFErrSnafu)?;
-    let cube_pos = &scene_buffer.positions[scene_view.nodes.get(&0).context(NoneErrSnafu)?.meshes
-        [0]
-    .positions
-    .clone()];
+    let cube = &scene_view.meshes[&scene_view.nodes.get(&0).context(NoneErrSnafu)?.meshes[0]];
+    let cube_pos = &scene_buffer.positions[cube.positions.clone()];
     let cube_buffer = device.create_buffer_init(&BufferInitDescriptor {
         label: Some("light debug cube pos"),
         contents: cube_pos,
         usage: wgpu::BufferUsages::VERTEX,
     });
-    let cube_ind = &scene_buffer.index[scene_view.nodes.get(&0).context(NoneErrSnafu)?.meshes[0]
-        .index
-        .indices
-        .clone()];
+    let cube_ind = &scene_buffer.index[cube.index.indices.clone()];
     let cube_ind_buffer = device.create_buffer_init(&BufferInitDescriptor {
         label: Some("light debug cube index"),
         contents: cube_ind,
@@ -590,7 +431,10 @@ fn init(base_state: &mut BaseState) -> Result<(), Error> {
         ],
         push_constant_ranges: &[],
     });
-    let light_debug_shader = device.create_shader_module(wgpu::include_wgsl!("light_debug.wgsl"));
+    let light_debug_shader = base_state
+        .shaders
+        .module(device, "gf/light_debug.wgsl", &[])
+        .context(ShaderErrSnafu)?;
 
     let emissive_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
         label: Some("emissive Pipeline"),
@@ -598,7 +442,7 @@ fn init(base_state: &mut BaseState) -> Result<(), Error> {
         vertex: wgpu::VertexState {
             module: &light_debug_shader,
             entry_point: "vs_main",
-            buffers: &[vertex_layout],
+            buffers: &[GpuScene::POSITION_LAYOUT],
         },
         fragment: Some(wgpu::FragmentState {
             module: &light_debug_shader,
@@ -643,28 +487,22 @@ fn init(base_state: &mut BaseState) -> Result<(), Error> {
 
     let state = Box::new(State {
         gbuffer_pipeline,
+        gbuffer_double_sided_pipeline,
+        forward_pipeline,
+        forward_double_sided_pipeline,
+        oit,
         deferred_pipeline,
-        vertices: vert_buf,
-        normal: normal_buf,
-        uv0: uv0_buf,
-        index: index_buf,
-        obj_count: obj_count as usize,
-        obj_buf,
-        indirect_buf,
-        tex_bind_group,
+        scene,
         light_buf,
         light_bind_group,
         emissive_pipeline,
         cube_buf: cube_buffer,
         cube_ind: cube_ind_buffer,
-        cube_ind_count: scene_view.nodes.get(&0).context(NoneErrSnafu)?.meshes[0]
-            .index
-            .count,
-        tangent: tangent_buf,
-        bi_tangent: bi_tangent_buf,
+        cube_ind_count: cube.index.count,
         position_gb,
         normal_gb,
         albedo_gb,
+        emissive_gb,
         gbuffer_bind_group,
         quad_buf,
         quad_uv_buf,
@@ -681,6 +519,7 @@ fn prepare_gbuffer_resource(
         texture::Texture,
         texture::Texture,
         texture::Texture,
+        texture::Texture,
         BindGroupLayout,
         wgpu::BindGroup,
     ),
@@ -736,6 +575,22 @@ fn prepare_gbuffer_resource(
         view_formats: &[],
     };
     let mut albedo_tex = texture::Texture::create_texture(device, albedo_tex_desc, true);
+    let emissive_tex_desc = wgpu::TextureDescriptor {
+        label: Some("Gbuffer Emissive"),
+        size: wgpu::Extent3d {
+            width: base_state.size.width,
+            height: base_state.size.height,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: wgpu::TextureFormat::Rgba16Float,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT // 3.
+        | wgpu::TextureUsages::TEXTURE_BINDING,
+        view_formats: &[],
+    };
+    let emissive_tex = texture::Texture::create_texture(device, emissive_tex_desc, false);
     let gbuffer_bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
         label: Some("GBuffer Bind Group Layout"),
         entries: &[
@@ -786,6 +641,17 @@ fn prepare_gbuffer_resource(
                 ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
                 count: None,
             },
+            //Emissive Buffer
+            BindGroupLayoutEntry {
+                binding: 5,
+                visibility: ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    multisampled: false,
+                },
+                count: None,
+            },
         ],
     });
     let gbuffer_bind_group = device.create_bind_group(&BindGroupDescriptor {
@@ -816,12 +682,17 @@ fn prepare_gbuffer_resource(
                     &albedo_tex.sampler.take().context(NoneErrSnafu)?,
                 ),
             },
+            BindGroupEntry {
+                binding: 5,
+                resource: wgpu::BindingResource::TextureView(&emissive_tex.view),
+            },
         ],
     });
     Ok((
         position_tex,
         normal_tex,
         albedo_tex,
+        emissive_tex,
         gbuffer_bind_group_layout,
         gbuffer_bind_group,
     ))
@@ -893,6 +764,14 @@ fn render(base_state: &mut BaseState, _dt: Duration) -> Result<(), Error> {
                         store: wgpu::StoreOp::Store,
                     },
                 }),
+                Some(wgpu::RenderPassColorAttachment {
+                    view: &state.emissive_gb.view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                        store: wgpu::StoreOp::Store,
+                    },
+                }),
             ],
             depth_stencil_attachment: Some(RenderPassDepthStencilAttachment {
                 view: &base_state.depth.view,
@@ -906,22 +785,13 @@ fn render(base_state: &mut BaseState, _dt: Duration) -> Result<(), Error> {
             occlusion_query_set: None,
         });
 
-        gbuffer_render_pass.set_pipeline(&state.gbuffer_pipeline);
         gbuffer_render_pass.set_bind_group(0, &base_state.camera_bind_group, &[]);
-        gbuffer_render_pass.set_bind_group(1, &state.tex_bind_group, &[]);
+        gbuffer_render_pass.set_bind_group(1, &state.scene.tex_bind_group, &[]);
 
-        gbuffer_render_pass.set_vertex_buffer(0, state.vertices.slice(..));
-        gbuffer_render_pass.set_vertex_buffer(1, state.normal.slice(..));
-        gbuffer_render_pass.set_vertex_buffer(2, state.uv0.slice(..));
-        gbuffer_render_pass.set_vertex_buffer(3, state.tangent.slice(..));
-        gbuffer_render_pass.set_vertex_buffer(4, state.obj_buf.slice(..));
-
-        gbuffer_render_pass.set_index_buffer(state.index.slice(..), wgpu::IndexFormat::Uint32);
-
-        gbuffer_render_pass.multi_draw_indexed_indirect(
-            &state.indirect_buf,
-            0,
-            state.obj_count as u32,
+        state.scene.draw_opaque(
+            &mut gbuffer_render_pass,
+            &state.gbuffer_pipeline,
+            &state.gbuffer_double_sided_pipeline,
         );
     }
 
@@ -974,7 +844,7 @@ fn render(base_state: &mut BaseState, _dt: Duration) -> Result<(), Error> {
                 view: &base_state.depth.view,
                 depth_ops: Some(Operations {
                     load: wgpu::LoadOp::Load,
-                    store: wgpu::StoreOp::Discard,
+                    store: wgpu::StoreOp::Store,
                 }),
                 stencil_ops: None,
             }),
@@ -990,6 +860,23 @@ fn render(base_state: &mut BaseState, _dt: Duration) -> Result<(), Error> {
         emissive_render_pass.draw_indexed(0..state.cube_ind_count as u32, 0, 0..1);
     }
 
+    // Blend materials in any order, then resolve them over the lit scene
+    {
+        let mut oit_pass = state.oit.begin_pass(&mut encoder, &base_state.depth);
+
+        oit_pass.set_bind_group(0, &base_state.camera_bind_group, &[]);
+        oit_pass.set_bind_group(1, &state.scene.tex_bind_group, &[]);
+        oit_pass.set_bind_group(2, &state.light_bind_group, &[]);
+
+        state.scene.draw_blend(
+            &mut oit_pass,
+            base_state.camera.position,
+            &state.forward_pipeline,
+            &state.forward_double_sided_pipeline,
+        );
+    }
+    state.oit.composite(&mut encoder, &view);
+
     // submit will accept anything that implements IntoIter
     base_state.queue.submit(std::iter::once(encoder.finish()));
     output.present();
@@ -1001,8 +888,14 @@ fn resize(
     base_state: &mut BaseState,
     new_size: winit::dpi::PhysicalSize<u32>,
 ) -> Result<(), Error> {
-    let (position_gb, normal_gb, albedo_gb, gbuffer_bind_group_layout, gbuffer_bind_group) =
-        prepare_gbuffer_resource(base_state)?;
+    let (
+        position_gb,
+        normal_gb,
+        albedo_gb,
+        emissive_gb,
+        gbuffer_bind_group_layout,
+        gbuffer_bind_group,
+    ) = prepare_gbuffer_resource(base_state)?;
     let state_long_live = base_state.extra_state.as_mut().context(NoneErrSnafu)?;
     let state = downcast_mut::<State>(state_long_live).context(NoneErrSnafu)?;
 
@@ -1010,6 +903,8 @@ fn resize(
     state.position_gb = position_gb;
     state.normal_gb = normal_gb;
     state.albedo_gb = albedo_gb;
+    state.emissive_gb = emissive_gb;
+    state.oit.resize(&base_state.device, &base_state.config);
     Ok(())
 }
 
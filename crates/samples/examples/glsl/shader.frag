@@ -0,0 +1,13 @@
+#version 450
+
+layout(location = 0) in vec3 v_color;
+
+layout(location = 0) out vec4 f_color;
+
+void main() {
+    vec3 color = v_color;
+#ifdef WARM
+    color.x += 0.2;
+#endif
+    f_color = vec4(color, 1.0);
+}
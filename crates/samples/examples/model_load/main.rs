@@ -1,36 +1,26 @@
-use std::mem::size_of;
-use std::num::NonZeroU32;
 use std::time::Duration;
 
 use gf_base::{
-    asset::gltf::{load_gltf, LoadOption, MaterialKey, SInto},
+    asset::gltf::{load_gltf, LoadOption},
     downcast_mut,
     glam::{Mat3, Mat4},
-    image::GenericImageView,
+    scene::GpuScene,
     snafu::{OptionExt, ResultExt},
     texture,
     wgpu::{
         self,
-        util::{BufferInitDescriptor, DeviceExt, DrawIndexedIndirect},
-        DepthStencilState, Operations, RenderPassDepthStencilAttachment, TextureDescriptor,
-        VertexFormat::*,
+        util::{BufferInitDescriptor, DeviceExt},
+        DepthStencilState, Operations, RenderPassDepthStencilAttachment,
     },
-    App, BaseState, Error, GLTFErrSnafu, ImageLoadErrSnafu, NoneErrSnafu, StateDynObj,
-    SurfaceErrSnafu,
+    App, BaseState, Error, GLTFErrSnafu, NoneErrSnafu, StateDynObj, SurfaceErrSnafu,
 };
 
 struct State {
     render_pipeline: wgpu::RenderPipeline,
-    index: wgpu::Buffer,
-    vertices: wgpu::Buffer,
-    normal: wgpu::Buffer,
-    uv0: wgpu::Buffer,
-    tangent: wgpu::Buffer,
-    bi_tangent: wgpu::Buffer,
-    obj_count: usize,
-    obj_buf: wgpu::Buffer,
-    indirect_buf: wgpu::Buffer,
-    tex_bind_group: wgpu::BindGroup,
+    render_double_sided_pipeline: wgpu::RenderPipeline,
+    blend_pipeline: wgpu::RenderPipeline,
+    blend_double_sided_pipeline: wgpu::RenderPipeline,
+    scene: GpuScene,
     light_buf: wgpu::Buffer,
     light_bind_group: wgpu::BindGroup,
     light_pipeline: wgpu::RenderPipeline,
@@ -41,16 +31,6 @@ struct State {
 
 impl StateDynObj for State {}
 
-#[repr(C, align(16))]
-#[derive(Copy, Clone, Debug, Default, bytemuck::Pod, bytemuck::Zeroable)]
-struct PerObjData {
-    base_color: u32,
-    sampler: u32,
-    normal: u32,
-    normal_sampler: u32,
-    pub transform: [[f32; 4]; 4],
-}
-
 #[repr(C)]
 #[derive(Copy, Clone, Debug, Default, bytemuck::Pod, bytemuck::Zeroable)]
 struct PerObjB {
@@ -105,235 +85,16 @@ fn init(base_state: &mut BaseState) -> Result<(), Error> {
     //     env!("CARGO_MANIFEST_DIR")
     // );
 
-    let (scene_view, scene_buffer) =
-        load_gltf(&path, LoadOption { gen_tbn: true }).context(GLTFErrSnafu)?;
-
-    let vert_buf = device.create_buffer_init(&BufferInitDescriptor {
-        label: Some("vertex"),
-        contents: &scene_buffer.positions,
-        usage: wgpu::BufferUsages::VERTEX,
-    });
-    let index_buf = device.create_buffer_init(&BufferInitDescriptor {
-        label: Some("index"),
-        contents: &scene_buffer.index,
-        usage: wgpu::BufferUsages::INDEX,
-    });
-    let normal_buf = device.create_buffer_init(&BufferInitDescriptor {
-        label: Some("normal"),
-        contents: bytemuck::cast_slice(&scene_buffer.normal),
-        usage: wgpu::BufferUsages::VERTEX,
-    });
-    let uv0_buf = device.create_buffer_init(&BufferInitDescriptor {
-        label: Some("uv0"),
-        contents: bytemuck::cast_slice(scene_buffer.texcoord.get(0).context(NoneErrSnafu)?),
-        usage: wgpu::BufferUsages::VERTEX,
-    });
-    let tangent_buf = device.create_buffer_init(&BufferInitDescriptor {
-        label: Some("tangent"),
-        contents: bytemuck::cast_slice(&scene_buffer.tangent),
-        usage: wgpu::BufferUsages::VERTEX,
-    });
-    let bi_tangent_buf = device.create_buffer_init(&BufferInitDescriptor {
-        label: Some("bi_tangent"),
-        contents: bytemuck::cast_slice(&scene_buffer.bi_tangent),
-        usage: wgpu::BufferUsages::VERTEX,
-    });
-
-    let mut indirect = Vec::new();
-
-    let mut obj_count = 0;
-    let mut per_obj_data = vec![];
-    for node in &scene_view.nodes {
-        let node = node.1;
-        for mesh in &node.meshes {
-            indirect.push(DrawIndexedIndirect {
-                vertex_count: mesh.index.count as u32,
-                instance_count: 1,
-                base_index: (mesh.index.indices.start / mesh.index.type_size) as u32,
-                vertex_offset: (mesh.positions.start / mesh.vertex_type_size) as i32,
-                base_instance: obj_count,
-            });
-            let gltf_mat = &scene_view.materials[mesh.mat.context(NoneErrSnafu)?];
-            let base_color = gltf_mat
-                .get(&MaterialKey::BaseColor)
-                .context(NoneErrSnafu)?;
-            let normal_tex = gltf_mat.get(&MaterialKey::Normal).context(NoneErrSnafu)?;
-
-            let per_obj = PerObjData {
-                transform: node.per_node_info.transform.to_cols_array_2d(),
-                base_color: base_color.image_id.context(NoneErrSnafu)? as u32,
-                sampler: base_color.sampler as u32,
-                normal: normal_tex.image_id.context(NoneErrSnafu)? as u32,
-                normal_sampler: normal_tex.sampler as u32,
-            };
-            per_obj_data.push(per_obj);
-
-            obj_count += 1;
-        }
-    }
-
-    let obj_buf = device.create_buffer_init(&BufferInitDescriptor {
-        label: Some("Per obj buffer"),
-        contents: bytemuck::cast_slice(&per_obj_data),
-        usage: wgpu::BufferUsages::VERTEX,
-    });
-
-    //TODO normal texture, need refactor
-    let mut texture_view_vec = vec![];
-    for img_info in &scene_view.images {
-        let color_source_data = &scene_buffer.shared_data[img_info.range.clone()];
-        let dyn_img =
-            gf_base::image::load_from_memory(color_source_data).context(ImageLoadErrSnafu)?;
-        let img_dimensions = dyn_img.dimensions();
-        let img_rgb = dyn_img.to_rgba8();
-
-        let tex = device.create_texture_with_data(
-            queue,
-            &TextureDescriptor {
-                label: None,
-                size: wgpu::Extent3d {
-                    width: img_dimensions.0,
-                    height: img_dimensions.1,
-                    depth_or_array_layers: 1,
-                },
-                mip_level_count: 1,
-                sample_count: 1,
-                dimension: wgpu::TextureDimension::D2,
-                format: img_info.target_format,
-                usage: wgpu::TextureUsages::TEXTURE_BINDING,
-                view_formats: &[],
-            },
-            &img_rgb,
-        );
-        let tex_view = tex.create_view(&wgpu::TextureViewDescriptor::default());
-        texture_view_vec.push(tex_view);
-    }
-
-    let mut samplers = vec![];
-    for sampler in &scene_view.samplers {
-        let desc: wgpu::SamplerDescriptor<'_> = sampler.clone().t_into();
-        let wgpu_sampler = device.create_sampler(&desc);
-        samplers.push(wgpu_sampler);
-    }
-    if samplers.is_empty() {
-        let desc = wgpu::SamplerDescriptor {
-            label: Some("Default Sampler"),
+    let (scene_view, scene_buffer) = load_gltf(
+        &path,
+        LoadOption {
+            gen_tbn: true,
             ..Default::default()
-        };
-        let wgpu_sampler = device.create_sampler(&desc);
-        samplers.push(wgpu_sampler);
-    }
-
-    let indirect: Vec<u8> = indirect
-        .iter()
-        .map(|i| i.as_bytes())
-        .flat_map(|i| i.iter())
-        .copied()
-        .collect();
-
-    let indirect_buf = device.create_buffer_init(&BufferInitDescriptor {
-        label: Some("indirect"),
-        contents: &indirect,
-        usage: wgpu::BufferUsages::INDIRECT,
-    });
-
-    // Vertex Layout
-    let vertex_layout = wgpu::VertexBufferLayout {
-        array_stride: Float32x3.size(),
-        step_mode: wgpu::VertexStepMode::Vertex,
-        attributes: &wgpu::vertex_attr_array![
-            0 => Float32x3,
-        ],
-    };
-    let normal_layout = wgpu::VertexBufferLayout {
-        array_stride: Float32x3.size(),
-        step_mode: wgpu::VertexStepMode::Vertex,
-        attributes: &wgpu::vertex_attr_array![
-            1 => Float32x3,
-        ],
-    };
-    let uv0_layout = wgpu::VertexBufferLayout {
-        array_stride: Float32x2.size(),
-        step_mode: wgpu::VertexStepMode::Vertex,
-        attributes: &wgpu::vertex_attr_array![
-            2 => Float32x2,
-        ],
-    };
-    let tangent_layout = wgpu::VertexBufferLayout {
-        array_stride: Float32x4.size(),
-        step_mode: wgpu::VertexStepMode::Vertex,
-        attributes: &wgpu::vertex_attr_array![
-            3 => Float32x4,
-        ],
-    };
-    let bi_tangent_layout = wgpu::VertexBufferLayout {
-        array_stride: Float32x3.size(),
-        step_mode: wgpu::VertexStepMode::Vertex,
-        attributes: &wgpu::vertex_attr_array![
-            4 => Float32x3,
-        ],
-    };
-
-    let object_layout = wgpu::VertexBufferLayout {
-        array_stride: size_of::<PerObjData>() as u64,
-        step_mode: wgpu::VertexStepMode::Instance,
-        attributes: &wgpu::vertex_attr_array![
-            8 => Uint32,
-            9 => Uint32,
-            10 => Uint32,
-            11 => Uint32,
-
-            14 => Float32x4,
-            15 => Float32x4,
-            16 => Float32x4,
-            17 => Float32x4,
-        ],
-    };
-    // bind group layout
-    let tex_bind_group_layout: wgpu::BindGroupLayout =
-        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-            label: None,
-            entries: &[
-                wgpu::BindGroupLayoutEntry {
-                    binding: 0,
-                    visibility: wgpu::ShaderStages::FRAGMENT,
-                    ty: wgpu::BindingType::Texture {
-                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
-                        view_dimension: wgpu::TextureViewDimension::D2,
-                        multisampled: false,
-                    },
-                    count: NonZeroU32::new(texture_view_vec.len() as u32),
-                },
-                wgpu::BindGroupLayoutEntry {
-                    binding: 1,
-                    visibility: wgpu::ShaderStages::FRAGMENT,
-                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
-                    count: NonZeroU32::new(samplers.len() as u32),
-                },
-            ],
-        });
+        },
+    )
+    .context(GLTFErrSnafu)?;
 
-    let tex_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-        label: None,
-        layout: &tex_bind_group_layout,
-        entries: &[
-            wgpu::BindGroupEntry {
-                binding: 0,
-                resource: wgpu::BindingResource::TextureViewArray(
-                    texture_view_vec
-                        .iter()
-                        .collect::<Vec<&wgpu::TextureView>>()
-                        .as_slice(),
-                ),
-            },
-            wgpu::BindGroupEntry {
-                binding: 1,
-                resource: wgpu::BindingResource::SamplerArray(
-                    samplers.iter().collect::<Vec<&wgpu::Sampler>>().as_slice(),
-                ),
-            },
-        ],
-    });
+    let scene = GpuScene::new(device, queue, &scene_view, &scene_buffer)?;
 
     // Generate light data
     let light_data = LightBuffer {
@@ -379,78 +140,82 @@ fn init(base_state: &mut BaseState) -> Result<(), Error> {
         label: Some("Render Pipeline Layout"),
         bind_group_layouts: &[
             &base_state.camera_bind_group_layout,
-            &tex_bind_group_layout,
+            &scene.tex_bind_group_layout,
             &light_bind_group_layout,
         ],
         push_constant_ranges: &[],
     });
 
-    let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-        label: Some("Render Pipeline Layout"),
-        layout: Some(&render_pipeline_layout),
-        vertex: wgpu::VertexState {
-            module: &shader,
-            entry_point: "vs_main",
-            buffers: &[
-                vertex_layout.clone(),
-                object_layout,
-                normal_layout,
-                uv0_layout,
-                tangent_layout,
-                bi_tangent_layout,
-            ],
-        },
-        primitive: wgpu::PrimitiveState {
-            topology: wgpu::PrimitiveTopology::TriangleList,
-            strip_index_format: None,
-            front_face: wgpu::FrontFace::Ccw,
-            cull_mode: Some(wgpu::Face::Back),
-            unclipped_depth: false,
-            polygon_mode: wgpu::PolygonMode::Fill,
-            conservative: false,
-        },
-        depth_stencil: Some(DepthStencilState {
-            format: texture::Texture::DEPTH_FORMAT,
-            depth_write_enabled: true,
-            depth_compare: wgpu::CompareFunction::Less,
-            stencil: wgpu::StencilState::default(),
-            bias: wgpu::DepthBiasState::default(),
-        }),
-        multisample: wgpu::MultisampleState {
-            count: 1,
-            mask: !0,
-            alpha_to_coverage_enabled: false,
-        },
-        fragment: Some(wgpu::FragmentState {
-            module: &shader,
-            entry_point: "fs_main",
-            targets: &[Some(wgpu::ColorTargetState {
-                // 4.
-                format: base_state.config.format,
-                blend: Some(wgpu::BlendState::REPLACE),
-                write_mask: wgpu::ColorWrites::ALL,
-            })],
-        }),
-        multiview: None,
-    });
+    // BLEND materials test depth without writing it and blend over what's drawn,
+    // the opaque variants keep the cleared alpha
+    let create_render_pipeline = |cull_mode, blend: bool| {
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Render Pipeline Layout"),
+            layout: Some(&render_pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &GpuScene::vertex_layouts(),
+            },
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode,
+                unclipped_depth: false,
+                polygon_mode: wgpu::PolygonMode::Fill,
+                conservative: false,
+            },
+            depth_stencil: Some(DepthStencilState {
+                format: texture::Texture::DEPTH_FORMAT,
+                depth_write_enabled: !blend,
+                depth_compare: wgpu::CompareFunction::Less,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState {
+                count: 1,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    // 4.
+                    format: base_state.config.format,
+                    blend: Some(if blend {
+                        wgpu::BlendState::ALPHA_BLENDING
+                    } else {
+                        wgpu::BlendState::REPLACE
+                    }),
+                    write_mask: if blend {
+                        wgpu::ColorWrites::ALL
+                    } else {
+                        wgpu::ColorWrites::COLOR
+                    },
+                })],
+            }),
+            multiview: None,
+        })
+    };
+    let render_pipeline = create_render_pipeline(Some(wgpu::Face::Back), false);
+    let render_double_sided_pipeline = create_render_pipeline(None, false);
+    let blend_pipeline = create_render_pipeline(Some(wgpu::Face::Back), true);
+    let blend_double_sided_pipeline = create_render_pipeline(None, true);
 
     let cube_path = format!("{}/../../assets/gltf/cube.glb", env!("CARGO_MANIFEST_DIR"));
 
     let (scene_view, scene_buffer) =
         load_gltf(cube_path, Default::default()).context(GLTFErrSnafu)?;
-    let cube_pos = &scene_buffer.positions[scene_view.nodes.get(&0).context(NoneErrSnafu)?.meshes
-        [0]
-    .positions
-    .clone()];
+    let cube = &scene_view.meshes[&scene_view.nodes.get(&0).context(NoneErrSnafu)?.meshes[0]];
+    let cube_pos = &scene_buffer.positions[cube.positions.clone()];
     let cube_buffer = device.create_buffer_init(&BufferInitDescriptor {
         label: Some("light debug cube pos"),
         contents: cube_pos,
         usage: wgpu::BufferUsages::VERTEX,
     });
-    let cube_ind = &scene_buffer.index[scene_view.nodes.get(&0).context(NoneErrSnafu)?.meshes[0]
-        .index
-        .indices
-        .clone()];
+    let cube_ind = &scene_buffer.index[cube.index.indices.clone()];
     let cube_ind_buffer = device.create_buffer_init(&BufferInitDescriptor {
         label: Some("light debug cube index"),
         contents: cube_ind,
@@ -476,7 +241,7 @@ fn init(base_state: &mut BaseState) -> Result<(), Error> {
         vertex: wgpu::VertexState {
             module: &shader,
             entry_point: "vs_main",
-            buffers: &[vertex_layout],
+            buffers: &[GpuScene::POSITION_LAYOUT],
         },
         fragment: Some(wgpu::FragmentState {
             module: &shader,
@@ -521,24 +286,16 @@ fn init(base_state: &mut BaseState) -> Result<(), Error> {
 
     let state = Box::new(State {
         render_pipeline,
-        vertices: vert_buf,
-        normal: normal_buf,
-        uv0: uv0_buf,
-        index: index_buf,
-        obj_count: obj_count as usize,
-        obj_buf,
-        indirect_buf,
-        tex_bind_group,
+        render_double_sided_pipeline,
+        blend_pipeline,
+        blend_double_sided_pipeline,
+        scene,
         light_buf,
         light_bind_group,
         light_pipeline,
         cube_buf: cube_buffer,
         cube_ind: cube_ind_buffer,
-        cube_ind_count: scene_view.nodes.get(&0).context(NoneErrSnafu)?.meshes[0]
-            .index
-            .count,
-        tangent: tangent_buf,
-        bi_tangent: bi_tangent_buf,
+        cube_ind_count: cube.index.count,
     });
     base_state.extra_state = Some(state);
 
@@ -590,22 +347,15 @@ fn render(base_state: &mut BaseState, _dt: Duration) -> Result<(), Error> {
 
         let state_long_live = base_state.extra_state.as_mut().context(NoneErrSnafu)?;
         let state = downcast_mut::<State>(state_long_live).context(NoneErrSnafu)?;
-        let pipeline = &state.render_pipeline;
-        render_pass.set_pipeline(pipeline);
         render_pass.set_bind_group(0, &base_state.camera_bind_group, &[]);
-        render_pass.set_bind_group(1, &state.tex_bind_group, &[]);
+        render_pass.set_bind_group(1, &state.scene.tex_bind_group, &[]);
         render_pass.set_bind_group(2, &state.light_bind_group, &[]);
 
-        render_pass.set_vertex_buffer(0, state.vertices.slice(..));
-        render_pass.set_vertex_buffer(1, state.obj_buf.slice(..));
-        render_pass.set_vertex_buffer(2, state.normal.slice(..));
-        render_pass.set_vertex_buffer(3, state.uv0.slice(..));
-        render_pass.set_vertex_buffer(4, state.tangent.slice(..));
-        render_pass.set_vertex_buffer(5, state.bi_tangent.slice(..));
-
-        render_pass.set_index_buffer(state.index.slice(..), wgpu::IndexFormat::Uint32);
-
-        render_pass.multi_draw_indexed_indirect(&state.indirect_buf, 0, state.obj_count as u32);
+        state.scene.draw_opaque(
+            &mut render_pass,
+            &state.render_pipeline,
+            &state.render_double_sided_pipeline,
+        );
 
         // Render light debug cube
         render_pass.set_pipeline(&state.light_pipeline);
@@ -614,6 +364,16 @@ fn render(base_state: &mut BaseState, _dt: Duration) -> Result<(), Error> {
         render_pass.set_vertex_buffer(0, state.cube_buf.slice(..));
         render_pass.set_index_buffer(state.cube_ind.slice(..), wgpu::IndexFormat::Uint32);
         render_pass.draw_indexed(0..state.cube_ind_count as u32, 0, 0..1);
+
+        // Blend materials last, sorted back to front
+        render_pass.set_bind_group(1, &state.scene.tex_bind_group, &[]);
+        render_pass.set_bind_group(2, &state.light_bind_group, &[]);
+        state.scene.draw_blend(
+            &mut render_pass,
+            base_state.camera.position,
+            &state.blend_pipeline,
+            &state.blend_double_sided_pipeline,
+        );
     }
 
     // submit will accept anything that implements IntoIter
@@ -657,7 +417,7 @@ fn test_gltf_loader() {
 
     let positions: Vec<[f32; 3]> = check_and_cast(
         &scene_buffer.positions,
-        &scene_view.nodes[1].meshes[0].positions,
+        &scene_view.meshes[&scene_view.nodes[1].meshes[0]].positions,
     );
 
     // match scene_view.nodes[1].meshes[0].index.r#type {
@@ -695,11 +455,11 @@ fn test_gltf_loader() {
 
     let positions: Vec<[f32; 3]> = check_and_cast(
         &scene_buffer.positions,
-        &scene_view.nodes[0].meshes[0].positions,
+        &scene_view.meshes[&scene_view.nodes[0].meshes[0]].positions,
     );
     let positions: Vec<[f32; 3]> = check_and_cast(
         &scene_buffer.positions,
-        &scene_view.nodes[1].meshes[0].positions,
+        &scene_view.meshes[&scene_view.nodes[1].meshes[0]].positions,
     );
     // match scene_view.nodes[1].meshes[0].index.r#type {
     //     gf_base::asset::gltf::IndexType::U16 => {
@@ -20,6 +20,7 @@ struct Normal {
 }
 struct UV {
     @location(2) uv0: vec2<f32>,
+    @location(5) uv1: vec2<f32>,
 }
 
 struct Tangent {
@@ -31,10 +32,7 @@ struct BiTangent {
 }
 
 struct PerObjInput {
-    @location(8) base_color: u32,
-    @location(9) base_color_sampler: u32,
-    @location(10) normal_map: u32,
-    @location(11) normal_sampler: u32,
+    @location(8) material: u32,
 
     @location(14) model_mat_0: vec4<f32>,
     @location(15) model_mat_1: vec4<f32>,
@@ -49,11 +47,9 @@ struct VertexOutput {
     @location(1) tangent_world_pos: vec3<f32>,
     @location(2) tangent_view_pos: vec3<f32>,
     @location(3) tangent_light_pos: vec3<f32>,
+    @location(4) uv1: vec2<f32>,
 
-    @location(8) base_color: u32,
-    @location(9) base_color_sampler: u32,
-    @location(10) normal_map: u32,
-    @location(11) normal_sampler: u32,
+    @location(8) material: u32,
 
     @location(30) debug_vec3: vec3<f32>,
 };
@@ -83,10 +79,8 @@ fn vs_main(
     out.tangent_light_pos = tbn * light.position;
 
     out.uv0 = uv.uv0;
-    out.base_color = obj.base_color;
-    out.base_color_sampler = obj.base_color_sampler;
-    out.normal_map = obj.normal_map;
-    out.normal_sampler = obj.normal_sampler;
+    out.uv1 = uv.uv1;
+    out.material = obj.material;
 
     out.debug_vec3 = a_tangent.xyz;
     return out;
@@ -99,25 +93,130 @@ var textures: binding_array<texture_2d<f32>>;
 @group(1) @binding(1)
 var samplers: binding_array<sampler>;
 
+struct Material {
+    base_color_factor: vec4<f32>,
+    base_color: u32,
+    base_color_sampler: u32,
+    normal: u32,
+    normal_sampler: u32,
+    metallic_roughness: u32,
+    metallic_roughness_sampler: u32,
+    metallic_factor: f32,
+    roughness_factor: f32,
+    emissive_factor: vec4<f32>,
+    emissive: u32,
+    emissive_sampler: u32,
+    occlusion: u32,
+    occlusion_sampler: u32,
+    normal_scale: f32,
+    occlusion_strength: f32,
+    alpha_cutoff: f32,
+    shading_model: u32,
+    base_color_transform: mat3x3<f32>,
+    normal_transform: mat3x3<f32>,
+    metallic_roughness_transform: mat3x3<f32>,
+    emissive_transform: mat3x3<f32>,
+    occlusion_transform: mat3x3<f32>,
+    clearcoat_transform: mat3x3<f32>,
+    clearcoat_roughness_transform: mat3x3<f32>,
+    sheen_color_transform: mat3x3<f32>,
+    sheen_roughness_transform: mat3x3<f32>,
+    transmission_transform: mat3x3<f32>,
+    specular_transform: mat3x3<f32>,
+    specular_color_transform: mat3x3<f32>,
+    features: u32,
+    ior: f32,
+    clearcoat_factor: f32,
+    clearcoat_roughness_factor: f32,
+    clearcoat: u32,
+    clearcoat_sampler: u32,
+    clearcoat_roughness: u32,
+    clearcoat_roughness_sampler: u32,
+    sheen_color: u32,
+    sheen_color_sampler: u32,
+    sheen_roughness: u32,
+    sheen_roughness_sampler: u32,
+    transmission: u32,
+    transmission_sampler: u32,
+    specular: u32,
+    specular_sampler: u32,
+    specular_color: u32,
+    specular_color_sampler: u32,
+    transmission_factor: f32,
+    thickness_factor: f32,
+    sheen_color_factor: vec3<f32>,
+    sheen_roughness_factor: f32,
+    specular_color_factor: vec3<f32>,
+    specular_factor: f32,
+    attenuation_color: vec3<f32>,
+    attenuation_distance: f32,
+    base_color_tex_coord: u32,
+    normal_tex_coord: u32,
+    metallic_roughness_tex_coord: u32,
+    emissive_tex_coord: u32,
+    occlusion_tex_coord: u32,
+    clearcoat_tex_coord: u32,
+    clearcoat_roughness_tex_coord: u32,
+    sheen_color_tex_coord: u32,
+    sheen_roughness_tex_coord: u32,
+    transmission_tex_coord: u32,
+    specular_tex_coord: u32,
+    specular_color_tex_coord: u32,
+}
+@group(1) @binding(2)
+var<storage, read> materials: array<Material>;
+
+// Values of Material.shading_model
+const SHADING_UNLIT: u32 = 1u;
+
+// KHR_texture_transform
+fn uv_transform(transform: mat3x3<f32>, uv: vec2<f32>) -> vec2<f32> {
+    return (transform * vec3<f32>(uv, 1.0)).xy;
+}
+
+// Uv set picked by a Material `*_tex_coord`
+fn tex_coord(in: VertexOutput, uv_set: u32) -> vec2<f32> {
+    return select(in.uv0, in.uv1, uv_set == 1u);
+}
+
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
-    let tex_color = textureSampleLevel(
-        textures[in.base_color],
-        samplers[in.base_color_sampler],
-        in.uv0,
-        0.0
-    ).rgb;
-
-    var normal = textureSampleLevel(
-        textures[in.normal_map],
-        samplers[in.normal_sampler],
-        in.uv0,
-        0.0
+    let mat = materials[in.material];
+
+    let base_color = textureSample(
+        textures[mat.base_color],
+        samplers[mat.base_color_sampler],
+        uv_transform(mat.base_color_transform, tex_coord(in, mat.base_color_tex_coord))
+    ) * mat.base_color_factor;
+    // alpha_cutoff is 0 unless the material is MASK
+    if base_color.a < mat.alpha_cutoff {
+        discard;
+    }
+    let tex_color = base_color.rgb;
+
+    var normal = textureSample(
+        textures[mat.normal],
+        samplers[mat.normal_sampler],
+        uv_transform(mat.normal_transform, tex_coord(in, mat.normal_tex_coord))
     ).rgb;
-    normal = normalize(normal * 2.0 - 1.0);
+    normal = normal * 2.0 - 1.0;
+    normal = normalize(normal * vec3<f32>(mat.normal_scale, mat.normal_scale, 1.0));
+
+    let emissive = textureSample(
+        textures[mat.emissive],
+        samplers[mat.emissive_sampler],
+        uv_transform(mat.emissive_transform, tex_coord(in, mat.emissive_tex_coord))
+    ).rgb * mat.emissive_factor.rgb;
+
+    let ao = textureSample(
+        textures[mat.occlusion],
+        samplers[mat.occlusion_sampler],
+        uv_transform(mat.occlusion_transform, tex_coord(in, mat.occlusion_tex_coord))
+    ).r;
+    let occlusion = 1.0 + mat.occlusion_strength * (ao - 1.0);
 
     let ambient_strength = 0.01;
-    let ambient = light.color * ambient_strength;
+    let ambient = light.color * ambient_strength * occlusion;
 
     let light_direction = normalize(in.tangent_light_pos - in.tangent_world_pos);
     let diffuse_strength = max(dot(normal, light_direction), 0.0);
@@ -130,7 +229,10 @@ fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
 
     let specular = spec_strength * light.color;
 
-    let out_color = tex_color * (ambient + diffuse + specular);
+    var out_color = tex_color * (ambient + diffuse + specular) + emissive;
+    if mat.shading_model == SHADING_UNLIT {
+        out_color = tex_color;
+    }
 
-    return vec4<f32>(out_color, 1.0);
+    return vec4<f32>(out_color, base_color.a);
 }
\ No newline at end of file
@@ -1,6 +1,3 @@
-//Const
-const PI = 3.1415926;
-
 //Vertex Buffer
 struct VertexInput {
     @location(0) position: vec3<f32>,
@@ -27,17 +24,10 @@ fn vs_main(
 }
 
 // Fragment Uniform
-struct Camera {
-    view_pos: vec4<f32>,
-    view_proj: mat4x4<f32>,
-}
-@group(0) @binding(0)
-var<uniform> camera: Camera;
+#include "gf/camera.wgsl"
+#include "gf/lobes.wgsl"
+#include "gf/light.wgsl"
 
-struct Light {
-    position: vec3<f32>,
-    color: vec3<f32>,
-}
 @group(2) @binding(0)
 var<uniform> light: Light;
 
@@ -51,6 +41,13 @@ var normal_gb: texture_2d<f32>;
 var albedo_gb: texture_2d<f32>;
 @group(1) @binding(4)
 var s1: sampler;
+@group(1) @binding(5)
+var emissive_gb: texture_2d<f32>;
+@group(1) @binding(6)
+var material_gb: texture_2d<u32>;
+
+@group(3) @binding(2)
+var<storage, read> materials: array<Material>;
 
 struct FragOut {
     @location(0) color: vec4<f32>
@@ -62,64 +59,46 @@ fn fs_main(in: VertexOutput) -> FragOut {
 
     let albedo_metallic = textureSample(albedo_gb, s1, in.uv);
     let albedo = pow(albedo_metallic.rgb, vec3<f32>(2.2));
-    let pos = textureSample(pos_gb, s, in.uv).rgb;
+    let pos_ao = textureSample(pos_gb, s, in.uv);
+    let pos = pos_ao.rgb;
+    let ao = pos_ao.a;
     let normal_rough = textureSample(normal_gb, s, in.uv);
     let normal = normal_rough.rgb;
     let metallic = albedo_metallic.a;
     let roughness = normal_rough.a;
-
-
-    let N = normal;
-    let V = normalize(camera.view_pos.xyz - pos);
-
-    // calculate reflectance at normal incidence; if dia-electric (like plastic) use F0 
-    // of 0.04 and if it's a metal, use the albedo color as F0 (metallic workflow)    
-    var F0 = vec3(0.04);
-    F0 = mix(F0, albedo, metallic);
-
-    // reflectance equation
-    var Lo = vec3(0.0);
-
-        {    
-        // calculate per-light radiance
-        let L = normalize(light.position - pos);
-        let H = normalize(V + L);
-        let distance = length(light.position - pos);
-        let attenuation = 1.0 / (distance * distance);
-        let radiance = light.color * attenuation;
-
-        // Cook-Torrance BRDF
-        let NDF = DistributionGGX(N, H, roughness);
-        let G = GeometrySmith(N, V, L, roughness);
-        let F = fresnelSchlick(max(dot(H, V), 0.0), F0);
-
-        let numerator = NDF * G * F;
-        let denominator = 4.0 * max(dot(N, V), 0.0) * max(dot(N, L), 0.0) + 0.0001; // + 0.0001 to prevent divide by zero
-        let specular = numerator / denominator;
-        
-        // kS is equal to Fresnel
-        let kS = F;
-        // for energy conservation, the diffuse and specular light can't
-        // be above 1.0 (unless the surface emits light); to preserve this
-        // relationship the diffuse component (kD) should equal 1.0 - kS.
-        var kD = vec3(1.0) - kS;
-        // multiply kD by the inverse metalness such that only non-metals 
-        // have diffuse lighting, or a linear blend if partly metal (pure metals
-        // have no diffuse light).
-        kD *= 1.0 - metallic;
-
-        // scale light by NdotL
-        let NdotL = max(dot(N, L), 0.0);        
-
-        // add to outgoing radiance Lo
-        Lo += (kD * albedo / PI + specular) * radiance * NdotL;  // note that we already multiplied the BRDF by the Fresnel (kS) so we won't multiply by kS again
+    let emissive_transmission = textureSample(emissive_gb, s, in.uv);
+    let emissive = emissive_transmission.rgb;
+    let material = textureLoad(material_gb, vec2<i32>(in.clip_position.xy), 0);
+
+    var color: vec3<f32>;
+    if (material.x >> 24u) == SHADING_UNLIT {
+        // the inverse of the tonemapping below, so the base color comes out as authored
+        color = albedo / max(vec3(1.0) - albedo, vec3(0.0001));
+    } else {
+        let lobes = load_lobes(material, emissive_transmission.a);
+
+        let N = normal;
+        let V = normalize(camera.view_pos.xyz - pos);
+
+        // reflectance equation
+        var Lo = vec3(0.0);
+
+        {
+            // calculate per-light radiance
+            let L = normalize(light.position - pos);
+            let distance = length(light.position - pos);
+            let attenuation = 1.0 / (distance * distance);
+            let radiance = light.color * attenuation;
+
+            Lo += shade_light(N, V, L, radiance, albedo, metallic, roughness, lobes);
+        }
+
+        // ambient lighting (note that the next IBL tutorial will replace 
+        // this ambient lighting with environment lighting).
+        let ambient = vec3(0.0006) * albedo * ao;
+
+        color = ambient + Lo + emissive;
     }
-    
-    // ambient lighting (note that the next IBL tutorial will replace 
-    // this ambient lighting with environment lighting).
-    let ambient = vec3(0.0006) * albedo;//TODO * ao;
-
-    var color = ambient + Lo;
 
     // HDR tonemapping
     color = color / (color + vec3(1.0));
@@ -130,84 +109,27 @@ fn fs_main(in: VertexOutput) -> FragOut {
     return out;
 }
 
-// Learn OpenGL
-fn DistributionGGX(N: vec3<f32>, H: vec3<f32>, roughness: f32) -> f32 {
-    let a2 = roughness * roughness;
-    let NdotH = max(dot(N, H), 0.0);
-    let NdotH2 = NdotH * NdotH;
-
-    let nom = a2;
-    var denom = (NdotH2 * (a2 - 1.0) + 1.0);
-    denom = PI * denom * denom;
-
-    return nom / denom;
-}
-
-fn GeometrySchlickGGX(NdotV: f32, roughness: f32) -> f32 {
-    let r = (roughness + 1.0);
-    let k = (r * r) / 8.0;
-
-    let nom = NdotV;
-    let denom = NdotV * (1.0 - k) + k;
-
-    return nom / denom;
-}
-fn GeometrySmith(N: vec3<f32>, V: vec3<f32>, L: vec3<f32>, roughness: f32) -> f32 {
-    let NdotV = max(dot(N, V), 0.0);
-    let NdotL = max(dot(N, L), 0.0);
-    let ggx2 = GeometrySchlickGGX(NdotV, roughness);
-    let ggx1 = GeometrySchlickGGX(NdotL, roughness);
-
-    return ggx1 * ggx2;
-}
-
-fn fresnelSchlick(cosTheta: f32, F0: vec3<f32>) -> vec3<f32> {
-    return F0 + (1.0 - F0) * pow(clamp(1.0 - cosTheta, 0.0, 1.0), 5.0);
-}
-
-// filament 
-fn D_GGX(NoH: f32, a: f32) -> f32 {
-    let a2 = a * a;
-    let f = (NoH * a2 - NoH) * NoH + 1.0;
-    return a2 / (PI * f * f);
-}
-
-fn F_Schlick(u: f32, f0: vec3<f32>) -> vec3<f32> {
-    return f0 + (vec3(1.0) - f0) * pow(1.0 - u, 5.0);
-}
-
-
-fn V_SmithGGXCorrelatedFast(NoV: f32, NoL: f32, a: f32) -> f32 {
-    let a2 = a * a;
-    let GGXL = NoV * sqrt((-NoL * a2 + NoL) * NoL + a2);
-    let GGXV = NoL * sqrt((-NoV * a2 + NoV) * NoV + a2);
-    return 0.5 / (GGXV + GGXL);
-}
-
-fn Fd_Lambert() -> f32 {
-    return 1.0 / PI;
+// Unpack the material gbuffer, the low 24 bits of x are the material index + 1
+// and 0 where nothing was drawn, the high 8 bits the shading model
+fn load_lobes(packed: vec4<u32>, transmission: f32) -> Lobes {
+    var lobes = default_lobes();
+    let mat_index = packed.x & 0xffffffu;
+    if mat_index == 0u {
+        return lobes;
+    }
+    let mat_id = mat_index - 1u;
+    lobes.features = materials[mat_id].features;
+    lobes.ior = materials[mat_id].ior;
+    let clearcoat = unpack2x16unorm(packed.y);
+    lobes.clearcoat = clearcoat.x;
+    lobes.clearcoat_roughness = clearcoat.y;
+    let sheen = unpack4x8unorm(packed.z);
+    lobes.sheen_color = sheen.rgb;
+    lobes.sheen_roughness = sheen.a;
+    let specular = unpack4x8unorm(packed.w);
+    lobes.specular_color = specular.rgb;
+    lobes.specular = specular.a;
+    lobes.transmission = transmission;
+    lobes.volume_transmittance = volume_transmittance(mat_id);
+    return lobes;
 }
-
-fn BRDF(v: vec3<f32>, l: vec3<f32>, n: vec3<f32>, a: f32, f0: vec3<f32>, perceptualRoughness: f32, diffuseColor: vec3<f32>) {
-    let h = normalize(v + l);
-
-    let NoV = abs(dot(n, v)) + 1e-5;
-    let NoL = clamp(dot(n, l), 0.0, 1.0);
-    let NoH = clamp(dot(n, h), 0.0, 1.0);
-    let LoH = clamp(dot(l, h), 0.0, 1.0);
-
-    // perceptually linear roughness to roughness (see parameterization)
-    let roughness = perceptualRoughness * perceptualRoughness;
-
-    let D = D_GGX(NoH, a);
-    let F = F_Schlick(LoH, f0);
-    let V = V_SmithGGXCorrelatedFast(NoV, NoL, roughness);
-
-    // specular BRDF
-    let Fr = (D * V) * F;
-
-    // diffuse BRDF
-    let Fd = diffuseColor * Fd_Lambert();
-
-    // apply lighting...
-}
\ No newline at end of file
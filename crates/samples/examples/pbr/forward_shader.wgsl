@@ -0,0 +1,110 @@
+//Forward pass for BLEND materials, drawn back to front after deferred lighting
+
+#include "gf/mesh.wgsl"
+#include "gf/scene.wgsl"
+#include "gf/lobes.wgsl"
+#include "gf/light.wgsl"
+
+@group(2) @binding(0)
+var<uniform> light: Light;
+
+@fragment
+fn fs_main(in: VertexOutput, @builtin(front_facing) front_facing: bool) -> @location(0) vec4<f32> {
+    let mat = materials[in.material];
+
+    let base_color = textureSample(
+        textures[mat.base_color],
+        samplers[mat.base_color_sampler],
+        uv_transform(mat.base_color_transform, tex_coord(in, mat.base_color_tex_coord))
+    ) * mat.base_color_factor;
+    let tex_color = base_color.rgb;
+
+    var normal = textureSample(
+        textures[mat.normal],
+        samplers[mat.normal_sampler],
+        uv_transform(mat.normal_transform, tex_coord(in, mat.normal_tex_coord))
+    ).rgb;
+
+    let metallic_roughness = textureSample(
+        textures[mat.metallic_roughness],
+        samplers[mat.metallic_roughness_sampler],
+        uv_transform(mat.metallic_roughness_transform, tex_coord(in, mat.metallic_roughness_tex_coord))
+    ).rgb * vec3<f32>(1.0, mat.roughness_factor, mat.metallic_factor);
+
+    let emissive = textureSample(
+        textures[mat.emissive],
+        samplers[mat.emissive_sampler],
+        uv_transform(mat.emissive_transform, tex_coord(in, mat.emissive_tex_coord))
+    ).rgb * mat.emissive_factor.rgb;
+
+    let ao = textureSample(
+        textures[mat.occlusion],
+        samplers[mat.occlusion_sampler],
+        uv_transform(mat.occlusion_transform, tex_coord(in, mat.occlusion_tex_coord))
+    ).r;
+    let occlusion = 1.0 + mat.occlusion_strength * (ao - 1.0);
+
+    let tbn = surface_tbn(in, front_facing);
+    normal = (normal * 2.0 - 1.0) * vec3<f32>(mat.normal_scale, mat.normal_scale, 1.0);
+    normal = normalize(tbn * normal);
+
+    let albedo = pow(tex_color, vec3<f32>(2.2));
+    let metallic = metallic_roughness.b;
+    let roughness = metallic_roughness.g;
+    let pos = in.pos;
+
+    let N = normal;
+    let V = normalize(camera.view_pos.xyz - pos);
+
+    // Extension lobes, only sampled for the materials using them
+    let uv = ext_uv(in.uv0, in.uv1);
+    var lobes = default_lobes();
+    lobes.features = mat.features;
+    lobes.ior = mat.ior;
+    if (mat.features & FEATURE_CLEARCOAT) != 0u {
+        lobes.clearcoat = mat.clearcoat_factor * sample_ext(mat.clearcoat, mat.clearcoat_sampler, mat.clearcoat_transform, mat.clearcoat_tex_coord, uv).r;
+        lobes.clearcoat_roughness = mat.clearcoat_roughness_factor * sample_ext(mat.clearcoat_roughness, mat.clearcoat_roughness_sampler, mat.clearcoat_roughness_transform, mat.clearcoat_roughness_tex_coord, uv).g;
+    }
+    if (mat.features & FEATURE_SHEEN) != 0u {
+        lobes.sheen_color = mat.sheen_color_factor * sample_ext(mat.sheen_color, mat.sheen_color_sampler, mat.sheen_color_transform, mat.sheen_color_tex_coord, uv).rgb;
+        lobes.sheen_roughness = mat.sheen_roughness_factor * sample_ext(mat.sheen_roughness, mat.sheen_roughness_sampler, mat.sheen_roughness_transform, mat.sheen_roughness_tex_coord, uv).a;
+    }
+    if (mat.features & FEATURE_SPECULAR) != 0u {
+        lobes.specular_color = mat.specular_color_factor * sample_ext(mat.specular_color, mat.specular_color_sampler, mat.specular_color_transform, mat.specular_color_tex_coord, uv).rgb;
+        lobes.specular = mat.specular_factor * sample_ext(mat.specular, mat.specular_sampler, mat.specular_transform, mat.specular_tex_coord, uv).a;
+    }
+    if (mat.features & FEATURE_TRANSMISSION) != 0u {
+        lobes.transmission = mat.transmission_factor * sample_ext(mat.transmission, mat.transmission_sampler, mat.transmission_transform, mat.transmission_tex_coord, uv).r;
+    }
+    lobes.volume_transmittance = volume_transmittance(in.material);
+
+    // reflectance equation
+    var Lo = vec3(0.0);
+
+    {
+        // calculate per-light radiance
+        let L = normalize(light.position - pos);
+        let distance = length(light.position - pos);
+        let attenuation = 1.0 / (distance * distance);
+        let radiance = light.color * attenuation;
+
+        Lo += shade_light(N, V, L, radiance, albedo, metallic, roughness, lobes);
+    }
+
+    // ambient lighting (note that the next IBL tutorial will replace 
+    // this ambient lighting with environment lighting).
+    let ambient = vec3(0.0006) * albedo * occlusion;
+
+    var color = ambient + Lo + emissive;
+    if mat.shading_model == SHADING_UNLIT {
+        // the inverse of the tonemapping below, so the base color comes out as authored
+        color = albedo / max(vec3(1.0) - albedo, vec3(0.0001));
+    }
+
+    // HDR tonemapping
+    color = color / (color + vec3(1.0));
+    // gamma correct
+    color = pow(color, vec3(1.0 / 2.2));
+
+    return vec4<f32>(color, base_color.a);
+}
@@ -1,68 +1,212 @@
-use std::mem::size_of;
-use std::num::NonZeroU32;
+use std::ops::Range;
+use std::sync::Arc;
 use std::time::Duration;
 
 use gf_base::{
-    asset::gltf::{load_gltf, LoadOption, MaterialKey, self, SInto},
+    asset::{
+        gltf::LoadOption,
+        server::{Handle, SceneAsset},
+    },
     downcast_mut,
     glam::{Mat3, Mat4},
-    image::GenericImageView,
+    hot_reload::{self, FileWatcher},
+    log,
+    scene::GpuScene,
+    shader::{self, Reflection, ShaderComposer},
     snafu::{OptionExt, ResultExt},
     texture::{self},
     wgpu::{
         self,
-        util::{BufferInitDescriptor, DeviceExt, DrawIndexedIndirect, RenderEncoder},
+        util::{BufferInitDescriptor, DeviceExt},
         BindGroupDescriptor, BindGroupEntry, BindGroupLayout, BindGroupLayoutDescriptor,
         BindGroupLayoutEntry, DepthStencilState, Operations, PipelineLayoutDescriptor,
-        RenderPassDepthStencilAttachment, ShaderStages, TextureDescriptor,
-        VertexFormat::*,
+        RenderPassDepthStencilAttachment, ShaderStages,
     },
-    winit, App, BaseState, Error, GLTFErrSnafu, ImageLoadErrSnafu, NoneErrSnafu, StateDynObj,
-    SurfaceErrSnafu,
+    winit, App, BaseState, Error, NoneErrSnafu, ShaderErrSnafu, StateDynObj, SurfaceErrSnafu,
 };
 
+/// Debug builds read the shaders next to this file so edits hot reload, release builds
+/// embed them as composer libraries.
+#[cfg(debug_assertions)]
+macro_rules! shader_file {
+    ($name:literal) => {
+        concat!(env!("CARGO_MANIFEST_DIR"), "/examples/pbr/", $name)
+    };
+}
+#[cfg(not(debug_assertions))]
+macro_rules! shader_file {
+    ($name:literal) => {
+        concat!("pbr/", $name)
+    };
+}
+
+const GBUFFER_SHADER: &str = shader_file!("gbuffer_shader.wgsl");
+const DEFERRED_SHADER: &str = shader_file!("deferred_shader.wgsl");
+const FORWARD_SHADER: &str = shader_file!("forward_shader.wgsl");
+#[cfg(not(debug_assertions))]
+const EMBEDDED_SHADERS: [(&str, &str); 3] = [
+    (GBUFFER_SHADER, include_str!("gbuffer_shader.wgsl")),
+    (DEFERRED_SHADER, include_str!("deferred_shader.wgsl")),
+    (FORWARD_SHADER, include_str!("forward_shader.wgsl")),
+];
+
 struct State {
-    gbuffer_pipeline: wgpu::RenderPipeline,
-    index: wgpu::Buffer,
-    vertices: wgpu::Buffer,
-    normal: wgpu::Buffer,
-    uv0: wgpu::Buffer,
-    tangent: wgpu::Buffer,
-    bi_tangent: wgpu::Buffer,
-    obj_count: usize,
-    obj_buf: wgpu::Buffer,
-    indirect_buf: wgpu::Buffer,
-    tex_bind_group: wgpu::BindGroup,
+    pipelines: ScenePipelines,
+    shaders: Shaders,
+    scene: Handle<SceneAsset>,
+    watcher: FileWatcher,
+    light_bind_group_layout: BindGroupLayout,
+    gbuffer_bind_group_layout: BindGroupLayout,
 
     emissive_pipeline: wgpu::RenderPipeline,
     light_buf: wgpu::Buffer,
     light_bind_group: wgpu::BindGroup,
-    cube_buf: wgpu::Buffer,
-    cube_ind: wgpu::Buffer,
-    cube_ind_count: usize,
+    cube: Handle<SceneAsset>,
+    cube_indices: Range<u32>,
+    cube_base_vertex: i32,
 
-    deferred_pipeline: wgpu::RenderPipeline,
     quad_buf: wgpu::Buffer,
     quad_uv_buf: wgpu::Buffer,
     gbuffer_bind_group: wgpu::BindGroup,
     position_gb: texture::Texture,
     normal_gb: texture::Texture,
     albedo_gb: texture::Texture,
+    emissive_gb: texture::Texture,
+    material_gb: texture::Texture,
 }
 
 impl StateDynObj for State {}
 
-#[repr(C, align(16))]
-#[derive(Copy, Clone, Debug, Default, bytemuck::Pod, bytemuck::Zeroable)]
-struct PerObjData {
-    base_color: u32,
-    sampler: u32,
-    normal: u32,
-    normal_sampler: u32,
-    metallic: u32,
-    metallic_sampler: u32,
-    pub transform: [[f32; 4]; 4],
-    _padding: [u32; 2],
+struct Shader {
+    module: Arc<wgpu::ShaderModule>,
+    reflection: Arc<Reflection>,
+}
+
+impl Shader {
+    fn load(
+        device: &wgpu::Device,
+        composer: &mut ShaderComposer,
+        entry: &str,
+        defines: &[&str],
+    ) -> Result<Self, shader::Error> {
+        Ok(Self {
+            module: composer.module(device, entry, defines)?,
+            reflection: composer.reflection(device, entry, defines)?,
+        })
+    }
+}
+
+struct Shaders {
+    gbuffer: Shader,
+    deferred: Shader,
+    forward: Shader,
+    light_debug: Shader,
+}
+
+impl Shaders {
+    /// All of them at once, so one broken file keeps the others at their last good version too.
+    fn load(device: &wgpu::Device, composer: &mut ShaderComposer) -> Result<Self, shader::Error> {
+        Ok(Self {
+            // Every material goes through the MASK test and has a normal map or the flat fallback
+            gbuffer: Shader::load(
+                device,
+                composer,
+                GBUFFER_SHADER,
+                &["ALPHA_MASK", "HAS_NORMAL_MAP"],
+            )?,
+            deferred: Shader::load(device, composer, DEFERRED_SHADER, &[])?,
+            forward: Shader::load(device, composer, FORWARD_SHADER, &[])?,
+            light_debug: Shader::load(device, composer, "gf/light_debug.wgsl", &[])?,
+        })
+    }
+
+    /// The light uniform layout, as the lit passes and the light markers bind it.
+    fn light_layout_entries(&self) -> Result<Vec<BindGroupLayoutEntry>, shader::Error> {
+        shader::merge_bind_group_layout_entries(&[
+            (&self.deferred.reflection, 2),
+            (&self.forward.reflection, 2),
+            (&self.light_debug.reflection, 1),
+        ])
+    }
+}
+
+/// Pipelines built against the scene's texture layout, rebuilt when it or a shader reloads.
+struct ScenePipelines {
+    gbuffer: wgpu::RenderPipeline,
+    gbuffer_double_sided: wgpu::RenderPipeline,
+    deferred: wgpu::RenderPipeline,
+    forward: wgpu::RenderPipeline,
+    forward_double_sided: wgpu::RenderPipeline,
+}
+
+impl ScenePipelines {
+    fn new(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        camera_bind_group_layout: &BindGroupLayout,
+        tex_bind_group_layout: &BindGroupLayout,
+        gbuffer_bind_group_layout: &BindGroupLayout,
+        light_bind_group_layout: &BindGroupLayout,
+        shaders: &Shaders,
+    ) -> Result<Self, shader::Error> {
+        let gbuffer_pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("GBuffer Pipeline Layout"),
+            bind_group_layouts: &[camera_bind_group_layout, tex_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let deferred_pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("Deferred Pipeline layout"),
+            bind_group_layouts: &[
+                camera_bind_group_layout,
+                gbuffer_bind_group_layout,
+                light_bind_group_layout,
+                // Materials for the extension lobes
+                tex_bind_group_layout,
+            ],
+            push_constant_ranges: &[],
+        });
+        //Forward pass for BLEND materials
+        let forward_pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("Forward Pipeline layout"),
+            bind_group_layouts: &[
+                camera_bind_group_layout,
+                tex_bind_group_layout,
+                light_bind_group_layout,
+            ],
+            push_constant_ranges: &[],
+        });
+
+        let gbuffer = |cull_mode| {
+            create_gbuffer_pipeline(
+                device,
+                &gbuffer_pipeline_layout,
+                &shaders.gbuffer,
+                cull_mode,
+            )
+        };
+        let forward = |cull_mode| {
+            create_forward_pipeline(
+                device,
+                &forward_pipeline_layout,
+                &shaders.forward,
+                format,
+                cull_mode,
+            )
+        };
+        Ok(Self {
+            gbuffer: gbuffer(Some(wgpu::Face::Back))?,
+            // MASK materials discard in the shader, so double sided ones only differ in culling
+            gbuffer_double_sided: gbuffer(None)?,
+            deferred: create_deferred_pipeline(
+                device,
+                &deferred_pipeline_layout,
+                &shaders.deferred,
+                format,
+            )?,
+            forward: forward(Some(wgpu::Face::Back))?,
+            forward_double_sided: forward(None)?,
+        })
+    }
 }
 
 #[repr(C)]
@@ -135,333 +279,52 @@ fn init(base_state: &mut BaseState) -> Result<(), Error> {
     //     env!("CARGO_MANIFEST_DIR")
     // );
 
-    let (scene_view, scene_buffer) =
-        load_gltf(&path, LoadOption { gen_tbn: true }).context(GLTFErrSnafu)?;
-
-    //Load scene data to wgpu buffer
-    let vert_buf = device.create_buffer_init(&BufferInitDescriptor {
-        label: Some("vertex"),
-        contents: &scene_buffer.positions,
-        usage: wgpu::BufferUsages::VERTEX,
-    });
-    let index_buf = device.create_buffer_init(&BufferInitDescriptor {
-        label: Some("index"),
-        contents: &scene_buffer.index,
-        usage: wgpu::BufferUsages::INDEX,
-    });
-    let normal_buf = device.create_buffer_init(&BufferInitDescriptor {
-        label: Some("normal"),
-        contents: bytemuck::cast_slice(&scene_buffer.normal),
-        usage: wgpu::BufferUsages::VERTEX,
-    });
-    let uv0_buf = device.create_buffer_init(&BufferInitDescriptor {
-        label: Some("uv0"),
-        contents: bytemuck::cast_slice(scene_buffer.texcoord.get(0).context(NoneErrSnafu)?),
-        usage: wgpu::BufferUsages::VERTEX,
-    });
-    let tangent_buf = device.create_buffer_init(&BufferInitDescriptor {
-        label: Some("tangent"),
-        contents: bytemuck::cast_slice(&scene_buffer.tangent),
-        usage: wgpu::BufferUsages::VERTEX,
-    });
-    let bi_tangent_buf = device.create_buffer_init(&BufferInitDescriptor {
-        label: Some("bi_tangent"),
-        contents: bytemuck::cast_slice(&scene_buffer.bi_tangent),
-        usage: wgpu::BufferUsages::VERTEX,
-    });
-
-    //Collect Indirect commands&generate per object buffer
-    let mut indirect = Vec::new();
-    let mut obj_count = 0;
-    let mut per_obj_data = vec![];
-    for node in &scene_view.nodes {
-        let node = node.1;
-        for mesh in &node.meshes {
-            indirect.push(DrawIndexedIndirect {
-                vertex_count: mesh.index.count as u32,
-                instance_count: 1,
-                base_index: (mesh.index.indices.start / mesh.index.type_size) as u32,
-                vertex_offset: (mesh.positions.start / mesh.vertex_type_size) as i32,
-                base_instance: 0,
-            });
-            let gltf_mat = &scene_view.materials[mesh.mat.context(NoneErrSnafu)?];
-            let base_color = gltf_mat
-                .get(&MaterialKey::BaseColor)
-                .context(NoneErrSnafu)?;
-            let normal_tex = gltf_mat.get(&MaterialKey::Normal).context(NoneErrSnafu)?;
-            let metallic_tex = gltf_mat
-                .get(&MaterialKey::MetallicRoughness)
-                .context(NoneErrSnafu)?;
-
-            let per_obj = PerObjData {
-                transform: node.per_node_info.transform.to_cols_array_2d(),
-                base_color: base_color.image_id.context(NoneErrSnafu)? as u32,
-                sampler: base_color.sampler as u32,
-                normal: normal_tex.image_id.context(NoneErrSnafu)? as u32,
-                normal_sampler: normal_tex.sampler as u32,
-                metallic: metallic_tex.image_id.context(NoneErrSnafu)? as u32,
-                metallic_sampler: metallic_tex.sampler as u32,
-                _padding: [0; 2],
-            };
-            per_obj_data.push(per_obj);
-
-            obj_count += 1;
-        }
-    }
-
-    let obj_buf = device.create_buffer_init(&BufferInitDescriptor {
-        label: Some("Per obj buffer"),
-        contents: bytemuck::cast_slice(&per_obj_data),
-        usage: wgpu::BufferUsages::VERTEX,
-    });
-
-    //scene texture&sampler
-    let mut texture_view_vec = vec![];
-    for img_info in &scene_view.images {
-        let color_source_data = &scene_buffer.shared_data[img_info.range.clone()];
-        let dyn_img =
-            gf_base::image::load_from_memory(color_source_data).context(ImageLoadErrSnafu)?;
-        let img_dimensions = dyn_img.dimensions();
-        let img_rgb = dyn_img.to_rgba8();
-
-        let tex = device.create_texture_with_data(
-            queue,
-            &TextureDescriptor {
-                label: img_info.name.as_deref(),
-                size: wgpu::Extent3d {
-                    width: img_dimensions.0,
-                    height: img_dimensions.1,
-                    depth_or_array_layers: 1,
-                },
-                mip_level_count: 1,
-                sample_count: 1,
-                dimension: wgpu::TextureDimension::D2,
-                format: img_info.target_format,
-                usage: wgpu::TextureUsages::TEXTURE_BINDING,
-                view_formats: &[],
-            },
-            &img_rgb,
-        );
-        let tex_view = tex.create_view(&wgpu::TextureViewDescriptor::default());
-        texture_view_vec.push(tex_view);
-    }
-
-    let mut samplers = vec![];
-    for sampler in &scene_view.samplers {
-        let desc: wgpu::SamplerDescriptor<'_> = sampler.t_into();
-        let wgpu_sampler = device.create_sampler(&desc);
-        samplers.push(wgpu_sampler);
-    }
-    if samplers.is_empty() {
-        let desc = wgpu::SamplerDescriptor {
-            label: Some("Default Sampler"),
+    let scene = base_state.assets.load_scene(
+        device,
+        queue,
+        &path,
+        LoadOption {
+            gen_tbn: true,
             ..Default::default()
-        };
-        let wgpu_sampler = device.create_sampler(&desc);
-        samplers.push(wgpu_sampler);
+        },
+    )?;
+    #[cfg(not(debug_assertions))]
+    for (name, source) in EMBEDDED_SHADERS {
+        base_state.shaders.add_library(name, source);
     }
-
-    let indirect: Vec<u8> = indirect
-        .iter()
-        .map(|i| i.as_bytes())
-        .flat_map(|i| i.iter())
-        .copied()
-        .collect();
-
-    let indirect_buf = device.create_buffer_init(&BufferInitDescriptor {
-        label: Some("indirect"),
-        contents: &indirect,
-        usage: wgpu::BufferUsages::INDIRECT,
-    });
-
-    // Vertex Layout
-    let vertex_layout = wgpu::VertexBufferLayout {
-        array_stride: Float32x3.size(),
-        step_mode: wgpu::VertexStepMode::Vertex,
-        attributes: &wgpu::vertex_attr_array![
-            0 => Float32x3,
-        ],
-    };
-    let normal_layout = wgpu::VertexBufferLayout {
-        array_stride: Float32x3.size(),
-        step_mode: wgpu::VertexStepMode::Vertex,
-        attributes: &wgpu::vertex_attr_array![
-            1 => Float32x3,
-        ],
-    };
-    let uv0_layout = wgpu::VertexBufferLayout {
-        array_stride: Float32x2.size(),
-        step_mode: wgpu::VertexStepMode::Vertex,
-        attributes: &wgpu::vertex_attr_array![
-            2 => Float32x2,
-        ],
-    };
-    let tangent_layout = wgpu::VertexBufferLayout {
-        array_stride: Float32x4.size(),
-        step_mode: wgpu::VertexStepMode::Vertex,
-        attributes: &wgpu::vertex_attr_array![
-            3 => Float32x4,
-        ],
-    };
-    let bi_tangent_layout = wgpu::VertexBufferLayout {
-        array_stride: Float32x3.size(),
-        step_mode: wgpu::VertexStepMode::Vertex,
-        attributes: &wgpu::vertex_attr_array![
-            4 => Float32x3,
-        ],
-    };
-
-    let object_layout = wgpu::VertexBufferLayout {
-        array_stride: size_of::<PerObjData>() as u64,
-        step_mode: wgpu::VertexStepMode::Instance,
-        attributes: &wgpu::vertex_attr_array![
-            8 => Uint32,
-            9 => Uint32,
-            10 => Uint32,
-            11 => Uint32,
-            12 => Uint32,
-            13 => Uint32,
-
-            14 => Float32x4,
-            15 => Float32x4,
-            16 => Float32x4,
-            17 => Float32x4,
-        ],
-    };
-    // bind group layout
-    let tex_bind_group_layout: wgpu::BindGroupLayout =
-        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-            label: None,
-            entries: &[
-                wgpu::BindGroupLayoutEntry {
-                    binding: 0,
-                    visibility: wgpu::ShaderStages::FRAGMENT,
-                    ty: wgpu::BindingType::Texture {
-                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
-                        view_dimension: wgpu::TextureViewDimension::D2,
-                        multisampled: false,
-                    },
-                    count: NonZeroU32::new(texture_view_vec.len() as u32),
-                },
-                wgpu::BindGroupLayoutEntry {
-                    binding: 1,
-                    visibility: wgpu::ShaderStages::FRAGMENT,
-                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
-                    count: NonZeroU32::new(samplers.len() as u32),
-                },
-            ],
-        });
-
-    let tex_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-        label: None,
-        layout: &tex_bind_group_layout,
-        entries: &[
-            wgpu::BindGroupEntry {
-                binding: 0,
-                resource: wgpu::BindingResource::TextureViewArray(
-                    texture_view_vec
-                        .iter()
-                        .collect::<Vec<&wgpu::TextureView>>()
-                        .as_slice(),
-                ),
-            },
-            wgpu::BindGroupEntry {
-                binding: 1,
-                resource: wgpu::BindingResource::SamplerArray(
-                    samplers.iter().collect::<Vec<&wgpu::Sampler>>().as_slice(),
-                ),
-            },
-        ],
-    });
+    // Reloaded in tick when the files change in debug builds
+    let shaders = Shaders::load(device, &mut base_state.shaders).context(ShaderErrSnafu)?;
     let light_bind_group_layout =
         device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-            entries: &[wgpu::BindGroupLayoutEntry {
-                binding: 0,
-                visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
-                ty: wgpu::BindingType::Buffer {
-                    ty: wgpu::BufferBindingType::Uniform,
-                    has_dynamic_offset: false,
-                    min_binding_size: None,
-                },
-                count: None,
-            }],
+            entries: &shaders.light_layout_entries().context(ShaderErrSnafu)?,
             label: None,
         });
 
     //GBuffer Attachments
-    let (position_gb, normal_gb, albedo_gb, gbuffer_bind_group_layout, gbuffer_bind_group) =
-        prepare_gbuffer_resource(base_state)?;
-
-    //Gbuffer pipeline
-
-    let gbuffer_shader = device.create_shader_module(wgpu::include_wgsl!("gbuffer_shader.wgsl"));
-
-    let gbuffer_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-        label: Some("GBuffer Pipeline Layout"),
-        bind_group_layouts: &[&base_state.camera_bind_group_layout, &tex_bind_group_layout],
-        push_constant_ranges: &[],
-    });
-
-    let gbuffer_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-        label: Some("GBuffer Render Layout"),
-        layout: Some(&gbuffer_pipeline_layout),
-        vertex: wgpu::VertexState {
-            module: &gbuffer_shader,
-            entry_point: "vs_main",
-            buffers: &[
-                vertex_layout.clone(),
-                normal_layout,
-                uv0_layout.clone(),
-                tangent_layout,
-                object_layout,
-            ],
-        },
-        primitive: wgpu::PrimitiveState {
-            topology: wgpu::PrimitiveTopology::TriangleList,
-            strip_index_format: None,
-            front_face: wgpu::FrontFace::Ccw,
-            cull_mode: Some(wgpu::Face::Back),
-            unclipped_depth: false,
-            polygon_mode: wgpu::PolygonMode::Fill,
-            conservative: false,
-        },
-        depth_stencil: Some(DepthStencilState {
-            format: texture::Texture::DEPTH_FORMAT,
-            depth_write_enabled: true,
-            depth_compare: wgpu::CompareFunction::Less,
-            stencil: wgpu::StencilState::default(),
-            bias: wgpu::DepthBiasState::default(),
-        }),
-        multisample: wgpu::MultisampleState {
-            count: 1,
-            mask: !0,
-            alpha_to_coverage_enabled: false,
-        },
-        fragment: Some(wgpu::FragmentState {
-            module: &gbuffer_shader,
-            entry_point: "fs_main",
-            targets: &[
-                //Position
-                Some(wgpu::ColorTargetState {
-                    format: wgpu::TextureFormat::Rgba16Float,
-                    blend: Some(wgpu::BlendState::REPLACE),
-                    write_mask: wgpu::ColorWrites::ALL,
-                }),
-                //Normal
-                Some(wgpu::ColorTargetState {
-                    format: wgpu::TextureFormat::Rgba16Float,
-                    blend: Some(wgpu::BlendState::REPLACE),
-                    write_mask: wgpu::ColorWrites::ALL,
-                }),
-                Some(wgpu::ColorTargetState {
-                    format: wgpu::TextureFormat::Rgba16Float,
-                    blend: None,
-                    write_mask: wgpu::ColorWrites::ALL,
-                }),
-            ],
-        }),
-        multiview: None,
-    });
+    let (
+        position_gb,
+        normal_gb,
+        albedo_gb,
+        emissive_gb,
+        material_gb,
+        gbuffer_bind_group_layout,
+        gbuffer_bind_group,
+    ) = prepare_gbuffer_resource(base_state)?;
+
+    let pipelines = ScenePipelines::new(
+        device,
+        base_state.config.format,
+        &base_state.camera_bind_group_layout,
+        &scene.gpu.tex_bind_group_layout,
+        &gbuffer_bind_group_layout,
+        &light_bind_group_layout,
+        &shaders,
+    )
+    .context(ShaderErrSnafu)?;
+
+    let mut watcher = FileWatcher::new();
+    watch_shaders(&mut watcher, &base_state.shaders);
+    watcher.watch(scene.path());
 
     //Deferred render
     let quad_vertices = [
@@ -492,53 +355,6 @@ fn init(base_state: &mut BaseState) -> Result<(), Error> {
         usage: wgpu::BufferUsages::VERTEX,
     });
 
-    let deferred_pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
-        label: Some("Deferred Pipeline layout"),
-        bind_group_layouts: &[
-            &base_state.camera_bind_group_layout,
-            &gbuffer_bind_group_layout,
-            &light_bind_group_layout,
-        ],
-        push_constant_ranges: &[],
-    });
-
-    let deferred_shader = device.create_shader_module(wgpu::include_wgsl!("deferred_shader.wgsl"));
-
-    let deferred_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-        label: Some("Deferred Pipeline"),
-        layout: Some(&deferred_pipeline_layout),
-        vertex: wgpu::VertexState {
-            module: &deferred_shader,
-            entry_point: "vs_main",
-            buffers: &[vertex_layout.clone(), uv0_layout],
-        },
-        primitive: wgpu::PrimitiveState {
-            topology: wgpu::PrimitiveTopology::TriangleList,
-            strip_index_format: None,
-            front_face: wgpu::FrontFace::Ccw,
-            cull_mode: Some(wgpu::Face::Back),
-            unclipped_depth: false,
-            polygon_mode: wgpu::PolygonMode::Fill,
-            conservative: false,
-        },
-        depth_stencil: None,
-        multisample: wgpu::MultisampleState {
-            count: 1,
-            mask: !0,
-            alpha_to_coverage_enabled: false,
-        },
-        fragment: Some(wgpu::FragmentState {
-            module: &deferred_shader,
-            entry_point: "fs_main",
-            targets: &[Some(wgpu::ColorTargetState {
-                format: base_state.config.format,
-                blend: Some(wgpu::BlendState::REPLACE),
-                write_mask: wgpu::ColorWrites::ALL,
-            })],
-        }),
-        multiview: None,
-    });
-
     //Light debug
     // Generate light data
     let light_data = LightBuffer {
@@ -566,26 +382,14 @@ fn init(base_state: &mut BaseState) -> Result<(), Error> {
 
     let cube_path = format!("{}/../../assets/gltf/cube.glb", env!("CARGO_MANIFEST_DIR"));
 
-    let (scene_view, scene_buffer) =
-        load_gltf(cube_path, Default::default()).context(GLTFErrSnafu)?;
-    let cube_pos = &scene_buffer.positions[scene_view.nodes.get(&0).context(NoneErrSnafu)?.meshes
-        [0]
-    .positions
-    .clone()];
-    let cube_buffer = device.create_buffer_init(&BufferInitDescriptor {
-        label: Some("light debug cube pos"),
-        contents: cube_pos,
-        usage: wgpu::BufferUsages::VERTEX,
-    });
-    let cube_ind = &scene_buffer.index[scene_view.nodes.get(&0).context(NoneErrSnafu)?.meshes[0]
-        .index
-        .indices
-        .clone()];
-    let cube_ind_buffer = device.create_buffer_init(&BufferInitDescriptor {
-        label: Some("light debug cube index"),
-        contents: cube_ind,
-        usage: wgpu::BufferUsages::INDEX,
-    });
+    let cube = base_state
+        .assets
+        .load_scene(device, queue, cube_path, Default::default())?;
+    // The gizmo draws the cube's first primitive straight from the shared scene buffers
+    let cube_mesh = &cube.view.meshes[&cube.view.nodes.get(&0).context(NoneErrSnafu)?.meshes[0]];
+    let first_index = (cube_mesh.index.indices.start / cube_mesh.index.type_size) as u32;
+    let cube_indices = first_index..first_index + cube_mesh.index.count as u32;
+    let cube_base_vertex = (cube_mesh.positions.start / cube_mesh.vertex_type_size) as i32;
 
     let emissive_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
         label: Some("Light Pipeline Layout"),
@@ -595,18 +399,17 @@ fn init(base_state: &mut BaseState) -> Result<(), Error> {
         ],
         push_constant_ranges: &[],
     });
-    let light_debug_shader = device.create_shader_module(wgpu::include_wgsl!("light_debug.wgsl"));
-
-    let emissive_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+    let light_debug_shader = &shaders.light_debug;
+    let emissive_pipeline_desc = wgpu::RenderPipelineDescriptor {
         label: Some("emissive Pipeline"),
         layout: Some(&emissive_pipeline_layout),
         vertex: wgpu::VertexState {
-            module: &light_debug_shader,
+            module: &light_debug_shader.module,
             entry_point: "vs_main",
-            buffers: &[vertex_layout],
+            buffers: &[GpuScene::POSITION_LAYOUT],
         },
         fragment: Some(wgpu::FragmentState {
-            module: &light_debug_shader,
+            module: &light_debug_shader.module,
             entry_point: "fs_main",
             targets: &[Some(wgpu::ColorTargetState {
                 format: base_state.config.format,
@@ -644,53 +447,231 @@ fn init(base_state: &mut BaseState) -> Result<(), Error> {
         // If the pipeline will be used with a multiview render pass, this
         // indicates how many array layers the attachments will have.
         multiview: None,
-    });
+    };
+    light_debug_shader
+        .reflection
+        .check_render_pipeline(&emissive_pipeline_desc)
+        .context(ShaderErrSnafu)?;
+    let emissive_pipeline = device.create_render_pipeline(&emissive_pipeline_desc);
 
+    let first_camera = scene.view.cameras.first().cloned();
     let state = Box::new(State {
-        gbuffer_pipeline,
-        deferred_pipeline,
-        vertices: vert_buf,
-        normal: normal_buf,
-        uv0: uv0_buf,
-        index: index_buf,
-        obj_count: obj_count as usize,
-        obj_buf,
-        indirect_buf,
-        tex_bind_group,
+        pipelines,
+        shaders,
+        scene,
+        watcher,
+        light_bind_group_layout,
+        gbuffer_bind_group_layout,
         light_buf,
         light_bind_group,
         emissive_pipeline,
-        cube_buf: cube_buffer,
-        cube_ind: cube_ind_buffer,
-        cube_ind_count: scene_view.nodes.get(&0).context(NoneErrSnafu)?.meshes[0]
-            .index
-            .count,
-        tangent: tangent_buf,
-        bi_tangent: bi_tangent_buf,
+        cube,
+        cube_indices,
+        cube_base_vertex,
         position_gb,
         normal_gb,
         albedo_gb,
+        emissive_gb,
+        material_gb,
         gbuffer_bind_group,
         quad_buf,
         quad_uv_buf,
     });
     base_state.extra_state = Some(state);
 
+    // Start from the first authored camera when the scene has one
+    if let Some(camera) = first_camera {
+        let (width, height) = (base_state.size.width, base_state.size.height);
+        base_state.camera.jump_to(&camera, width, height);
+    }
+
     Ok(())
 }
 
-fn prepare_gbuffer_resource(
-    base_state: &BaseState,
-) -> Result<
-    (
-        texture::Texture,
-        texture::Texture,
-        texture::Texture,
-        BindGroupLayout,
-        wgpu::BindGroup,
-    ),
-    Error,
-> {
+fn create_gbuffer_pipeline(
+    device: &wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    shader: &Shader,
+    cull_mode: Option<wgpu::Face>,
+) -> Result<wgpu::RenderPipeline, shader::Error> {
+    let desc = wgpu::RenderPipelineDescriptor {
+        label: Some("GBuffer Render Layout"),
+        layout: Some(layout),
+        vertex: wgpu::VertexState {
+            module: &shader.module,
+            entry_point: "vs_main",
+            buffers: &GpuScene::vertex_layouts(),
+        },
+        primitive: wgpu::PrimitiveState {
+            topology: wgpu::PrimitiveTopology::TriangleList,
+            strip_index_format: None,
+            front_face: wgpu::FrontFace::Ccw,
+            cull_mode,
+            unclipped_depth: false,
+            polygon_mode: wgpu::PolygonMode::Fill,
+            conservative: false,
+        },
+        depth_stencil: Some(DepthStencilState {
+            format: texture::Texture::DEPTH_FORMAT,
+            depth_write_enabled: true,
+            depth_compare: wgpu::CompareFunction::Less,
+            stencil: wgpu::StencilState::default(),
+            bias: wgpu::DepthBiasState::default(),
+        }),
+        multisample: wgpu::MultisampleState {
+            count: 1,
+            mask: !0,
+            alpha_to_coverage_enabled: false,
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: &shader.module,
+            entry_point: "fs_main",
+            targets: &[
+                //Position
+                Some(wgpu::ColorTargetState {
+                    format: wgpu::TextureFormat::Rgba16Float,
+                    blend: Some(wgpu::BlendState::REPLACE),
+                    write_mask: wgpu::ColorWrites::ALL,
+                }),
+                //Normal
+                Some(wgpu::ColorTargetState {
+                    format: wgpu::TextureFormat::Rgba16Float,
+                    blend: Some(wgpu::BlendState::REPLACE),
+                    write_mask: wgpu::ColorWrites::ALL,
+                }),
+                Some(wgpu::ColorTargetState {
+                    format: wgpu::TextureFormat::Rgba16Float,
+                    blend: None,
+                    write_mask: wgpu::ColorWrites::ALL,
+                }),
+                //Emissive
+                Some(wgpu::ColorTargetState {
+                    format: wgpu::TextureFormat::Rgba16Float,
+                    blend: None,
+                    write_mask: wgpu::ColorWrites::ALL,
+                }),
+                //Material
+                Some(wgpu::ColorTargetState {
+                    format: wgpu::TextureFormat::Rgba32Uint,
+                    blend: None,
+                    write_mask: wgpu::ColorWrites::ALL,
+                }),
+            ],
+        }),
+        multiview: None,
+    };
+    shader.reflection.check_render_pipeline(&desc)?;
+    Ok(device.create_render_pipeline(&desc))
+}
+
+fn create_deferred_pipeline(
+    device: &wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    shader: &Shader,
+    format: wgpu::TextureFormat,
+) -> Result<wgpu::RenderPipeline, shader::Error> {
+    let desc = wgpu::RenderPipelineDescriptor {
+        label: Some("Deferred Pipeline"),
+        layout: Some(layout),
+        vertex: wgpu::VertexState {
+            module: &shader.module,
+            entry_point: "vs_main",
+            buffers: &[GpuScene::POSITION_LAYOUT, GpuScene::UV0_LAYOUT],
+        },
+        primitive: wgpu::PrimitiveState {
+            topology: wgpu::PrimitiveTopology::TriangleList,
+            strip_index_format: None,
+            front_face: wgpu::FrontFace::Ccw,
+            cull_mode: Some(wgpu::Face::Back),
+            unclipped_depth: false,
+            polygon_mode: wgpu::PolygonMode::Fill,
+            conservative: false,
+        },
+        depth_stencil: None,
+        multisample: wgpu::MultisampleState {
+            count: 1,
+            mask: !0,
+            alpha_to_coverage_enabled: false,
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: &shader.module,
+            entry_point: "fs_main",
+            targets: &[Some(wgpu::ColorTargetState {
+                format,
+                blend: Some(wgpu::BlendState::REPLACE),
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+        }),
+        multiview: None,
+    };
+    shader.reflection.check_render_pipeline(&desc)?;
+    Ok(device.create_render_pipeline(&desc))
+}
+
+fn create_forward_pipeline(
+    device: &wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    shader: &Shader,
+    format: wgpu::TextureFormat,
+    cull_mode: Option<wgpu::Face>,
+) -> Result<wgpu::RenderPipeline, shader::Error> {
+    let desc = wgpu::RenderPipelineDescriptor {
+        label: Some("Forward Pipeline"),
+        layout: Some(layout),
+        vertex: wgpu::VertexState {
+            module: &shader.module,
+            entry_point: "vs_main",
+            buffers: &GpuScene::vertex_layouts(),
+        },
+        primitive: wgpu::PrimitiveState {
+            topology: wgpu::PrimitiveTopology::TriangleList,
+            strip_index_format: None,
+            front_face: wgpu::FrontFace::Ccw,
+            cull_mode,
+            unclipped_depth: false,
+            polygon_mode: wgpu::PolygonMode::Fill,
+            conservative: false,
+        },
+        // Test against the opaque depth but don't write, the draws are sorted instead
+        depth_stencil: Some(DepthStencilState {
+            format: texture::Texture::DEPTH_FORMAT,
+            depth_write_enabled: false,
+            depth_compare: wgpu::CompareFunction::Less,
+            stencil: wgpu::StencilState::default(),
+            bias: wgpu::DepthBiasState::default(),
+        }),
+        multisample: wgpu::MultisampleState {
+            count: 1,
+            mask: !0,
+            alpha_to_coverage_enabled: false,
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: &shader.module,
+            entry_point: "fs_main",
+            targets: &[Some(wgpu::ColorTargetState {
+                format,
+                blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+        }),
+        multiview: None,
+    };
+    shader.reflection.check_render_pipeline(&desc)?;
+    Ok(device.create_render_pipeline(&desc))
+}
+
+/// Position, normal, albedo, emissive and material targets with their bind group.
+type GbufferResource = (
+    texture::Texture,
+    texture::Texture,
+    texture::Texture,
+    texture::Texture,
+    texture::Texture,
+    BindGroupLayout,
+    wgpu::BindGroup,
+);
+
+fn prepare_gbuffer_resource(base_state: &BaseState) -> Result<GbufferResource, Error> {
     let device = &base_state.device;
 
     let pos_tex_desc = wgpu::TextureDescriptor {
@@ -741,6 +722,38 @@ fn prepare_gbuffer_resource(
         view_formats: &[],
     };
     let mut albedo_tex = texture::Texture::create_texture(device, albedo_tex_desc, true);
+    let emissive_tex_desc = wgpu::TextureDescriptor {
+        label: Some("Gbuffer Emissive"),
+        size: wgpu::Extent3d {
+            width: base_state.size.width,
+            height: base_state.size.height,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: wgpu::TextureFormat::Rgba16Float,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT // 3.
+        | wgpu::TextureUsages::TEXTURE_BINDING,
+        view_formats: &[],
+    };
+    let emissive_tex = texture::Texture::create_texture(device, emissive_tex_desc, false);
+    let material_tex_desc = wgpu::TextureDescriptor {
+        label: Some("Gbuffer Material"),
+        size: wgpu::Extent3d {
+            width: base_state.size.width,
+            height: base_state.size.height,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: wgpu::TextureFormat::Rgba32Uint,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT // 3.
+        | wgpu::TextureUsages::TEXTURE_BINDING,
+        view_formats: &[],
+    };
+    let material_tex = texture::Texture::create_texture(device, material_tex_desc, false);
     let gbuffer_bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
         label: Some("GBuffer Bind Group Layout"),
         entries: &[
@@ -791,6 +804,28 @@ fn prepare_gbuffer_resource(
                 ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
                 count: None,
             },
+            //Emissive Buffer
+            BindGroupLayoutEntry {
+                binding: 5,
+                visibility: ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    multisampled: false,
+                },
+                count: None,
+            },
+            //Material Buffer
+            BindGroupLayoutEntry {
+                binding: 6,
+                visibility: ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Uint,
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    multisampled: false,
+                },
+                count: None,
+            },
         ],
     });
     let gbuffer_bind_group = device.create_bind_group(&BindGroupDescriptor {
@@ -821,22 +856,116 @@ fn prepare_gbuffer_resource(
                     &albedo_tex.sampler.take().context(NoneErrSnafu)?,
                 ),
             },
+            BindGroupEntry {
+                binding: 5,
+                resource: wgpu::BindingResource::TextureView(&emissive_tex.view),
+            },
+            BindGroupEntry {
+                binding: 6,
+                resource: wgpu::BindingResource::TextureView(&material_tex.view),
+            },
         ],
     });
     Ok((
         position_tex,
         normal_tex,
         albedo_tex,
+        emissive_tex,
+        material_tex,
         gbuffer_bind_group_layout,
         gbuffer_bind_group,
     ))
 }
 
-//TODO
-fn tick(_state: &mut BaseState, _dt: Duration) -> Result<(), Error> {
+fn tick(base_state: &mut BaseState, _dt: Duration) -> Result<(), Error> {
+    let BaseState {
+        device,
+        queue,
+        config,
+        camera_bind_group_layout,
+        assets,
+        shaders: composer,
+        extra_state,
+        ..
+    } = base_state;
+    let state =
+        downcast_mut::<State>(extra_state.as_mut().context(NoneErrSnafu)?).context(NoneErrSnafu)?;
+
+    let changed = state.watcher.changed();
+    if changed.is_empty() {
+        return Ok(());
+    }
+    let mut shaders_changed = false;
+    for path in changed
+        .iter()
+        .filter(|i| i.extension().is_some_and(|i| i == "wgsl"))
+    {
+        composer.invalidate(path);
+        shaders_changed = true;
+    }
+    let shaders = match shaders_changed
+        .then(|| Shaders::load(device, composer))
+        .transpose()
+    {
+        Ok(shaders) => shaders,
+        Err(err) => {
+            log::error!("{err}");
+            return Ok(());
+        }
+    };
+    let scene = match changed.iter().any(|i| i == state.scene.path()) {
+        true => match assets.reload_scene(device, queue, &state.scene) {
+            Ok(scene) => Some(scene),
+            Err(err) => {
+                log::error!("failed to reload {}: {err}", state.scene.path().display());
+                return Ok(());
+            }
+        },
+        false => None,
+    };
+
+    let pipelines = hot_reload::rebuild(device, || {
+        ScenePipelines::new(
+            device,
+            config.format,
+            camera_bind_group_layout,
+            &scene
+                .as_ref()
+                .unwrap_or(&state.scene)
+                .gpu
+                .tex_bind_group_layout,
+            &state.gbuffer_bind_group_layout,
+            &state.light_bind_group_layout,
+            shaders.as_ref().unwrap_or(&state.shaders),
+        )
+    });
+    match pipelines {
+        Ok(Ok(pipelines)) => {
+            state.pipelines = pipelines;
+            if let Some(shaders) = shaders {
+                state.shaders = shaders;
+                watch_shaders(&mut state.watcher, composer);
+            }
+            if let Some(scene) = scene {
+                state.scene = scene;
+            }
+            log::info!("reloaded {changed:?}");
+        }
+        // Caught by reflection before wgpu saw the pipeline
+        Ok(Err(err)) => log::error!("{err}, keeping the last good pipelines"),
+        Err(err) => log::error!("{err}, keeping the last good pipelines"),
+    }
     Ok(())
 }
 
+/// Every file the shaders were built from, an include added by an edit is only known
+/// once the edited shader built.
+fn watch_shaders(watcher: &mut FileWatcher, composer: &ShaderComposer) {
+    for file in composer.files() {
+        watcher.watch(file);
+    }
+}
+
 fn render(base_state: &mut BaseState, _dt: Duration) -> Result<(), Error> {
     let state_long_live = base_state.extra_state.as_mut().context(NoneErrSnafu)?;
     let state = downcast_mut::<State>(state_long_live).context(NoneErrSnafu)?;
@@ -898,6 +1027,23 @@ fn render(base_state: &mut BaseState, _dt: Duration) -> Result<(), Error> {
                         store: wgpu::StoreOp::Store,
                     },
                 }),
+                Some(wgpu::RenderPassColorAttachment {
+                    view: &state.emissive_gb.view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                        store: wgpu::StoreOp::Store,
+                    },
+                }),
+                // x is the material index + 1, 0 where nothing was drawn
+                Some(wgpu::RenderPassColorAttachment {
+                    view: &state.material_gb.view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                        store: wgpu::StoreOp::Store,
+                    },
+                }),
             ],
             depth_stencil_attachment: Some(RenderPassDepthStencilAttachment {
                 view: &base_state.depth.view,
@@ -911,22 +1057,13 @@ fn render(base_state: &mut BaseState, _dt: Duration) -> Result<(), Error> {
             occlusion_query_set: None,
         });
 
-        gbuffer_render_pass.set_pipeline(&state.gbuffer_pipeline);
         gbuffer_render_pass.set_bind_group(0, &base_state.camera_bind_group, &[]);
-        gbuffer_render_pass.set_bind_group(1, &state.tex_bind_group, &[]);
-
-        gbuffer_render_pass.set_vertex_buffer(0, state.vertices.slice(..));
-        gbuffer_render_pass.set_vertex_buffer(1, state.normal.slice(..));
-        gbuffer_render_pass.set_vertex_buffer(2, state.uv0.slice(..));
-        gbuffer_render_pass.set_vertex_buffer(3, state.tangent.slice(..));
-        gbuffer_render_pass.set_vertex_buffer(4, state.obj_buf.slice(..));
+        gbuffer_render_pass.set_bind_group(1, &state.scene.gpu.tex_bind_group, &[]);
 
-        gbuffer_render_pass.set_index_buffer(state.index.slice(..), wgpu::IndexFormat::Uint32);
-
-        gbuffer_render_pass.multi_draw_indexed_indirect(
-            &state.indirect_buf,
-            0,
-            state.obj_count as u32,
+        state.scene.gpu.draw_opaque(
+            &mut gbuffer_render_pass,
+            &state.pipelines.gbuffer,
+            &state.pipelines.gbuffer_double_sided,
         );
     }
 
@@ -952,10 +1089,11 @@ fn render(base_state: &mut BaseState, _dt: Duration) -> Result<(), Error> {
             occlusion_query_set: None,
         });
 
-        deferred_render_pass.set_pipeline(&state.deferred_pipeline);
+        deferred_render_pass.set_pipeline(&state.pipelines.deferred);
         deferred_render_pass.set_bind_group(0, &base_state.camera_bind_group, &[]);
         deferred_render_pass.set_bind_group(1, &state.gbuffer_bind_group, &[]);
         deferred_render_pass.set_bind_group(2, &state.light_bind_group, &[]);
+        deferred_render_pass.set_bind_group(3, &state.scene.gpu.tex_bind_group, &[]);
 
         deferred_render_pass.set_vertex_buffer(0, state.quad_buf.slice(..));
         deferred_render_pass.set_vertex_buffer(1, state.quad_uv_buf.slice(..));
@@ -979,7 +1117,7 @@ fn render(base_state: &mut BaseState, _dt: Duration) -> Result<(), Error> {
                 view: &base_state.depth.view,
                 depth_ops: Some(Operations {
                     load: wgpu::LoadOp::Load,
-                    store: wgpu::StoreOp::Discard,
+                    store: wgpu::StoreOp::Store,
                 }),
                 stencil_ops: None,
             }),
@@ -990,9 +1128,46 @@ fn render(base_state: &mut BaseState, _dt: Duration) -> Result<(), Error> {
         emissive_render_pass.set_pipeline(&state.emissive_pipeline);
         emissive_render_pass.set_bind_group(0, &base_state.camera_bind_group, &[]);
         emissive_render_pass.set_bind_group(1, &state.light_bind_group, &[]);
-        emissive_render_pass.set_vertex_buffer(0, state.cube_buf.slice(..));
-        emissive_render_pass.set_index_buffer(state.cube_ind.slice(..), wgpu::IndexFormat::Uint32);
-        emissive_render_pass.draw_indexed(0..state.cube_ind_count as u32, 0, 0..1);
+        emissive_render_pass.set_vertex_buffer(0, state.cube.gpu.positions.slice(..));
+        emissive_render_pass
+            .set_index_buffer(state.cube.gpu.index.slice(..), wgpu::IndexFormat::Uint32);
+        emissive_render_pass.draw_indexed(state.cube_indices.clone(), state.cube_base_vertex, 0..1);
+    }
+
+    // Blend materials on top of the lit scene
+    {
+        let mut forward_render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Forward Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: Some(RenderPassDepthStencilAttachment {
+                view: &base_state.depth.view,
+                depth_ops: Some(Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: wgpu::StoreOp::Discard,
+                }),
+                stencil_ops: None,
+            }),
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+
+        forward_render_pass.set_bind_group(0, &base_state.camera_bind_group, &[]);
+        forward_render_pass.set_bind_group(1, &state.scene.gpu.tex_bind_group, &[]);
+        forward_render_pass.set_bind_group(2, &state.light_bind_group, &[]);
+
+        state.scene.gpu.draw_blend(
+            &mut forward_render_pass,
+            base_state.camera.position,
+            &state.pipelines.forward,
+            &state.pipelines.forward_double_sided,
+        );
     }
 
     // submit will accept anything that implements IntoIter
@@ -1005,8 +1180,15 @@ fn resize(
     base_state: &mut BaseState,
     _new_size: winit::dpi::PhysicalSize<u32>,
 ) -> Result<(), Error> {
-    let (position_gb, normal_gb, albedo_gb, gbuffer_bind_group_layout, gbuffer_bind_group) =
-        prepare_gbuffer_resource(base_state)?;
+    let (
+        position_gb,
+        normal_gb,
+        albedo_gb,
+        emissive_gb,
+        material_gb,
+        gbuffer_bind_group_layout,
+        gbuffer_bind_group,
+    ) = prepare_gbuffer_resource(base_state)?;
     let state_long_live = base_state.extra_state.as_mut().context(NoneErrSnafu)?;
     let state = downcast_mut::<State>(state_long_live).context(NoneErrSnafu)?;
 
@@ -1014,6 +1196,8 @@ fn resize(
     state.position_gb = position_gb;
     state.normal_gb = normal_gb;
     state.albedo_gb = albedo_gb;
+    state.emissive_gb = emissive_gb;
+    state.material_gb = material_gb;
     Ok(())
 }
 
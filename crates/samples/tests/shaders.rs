@@ -0,0 +1,139 @@
+//! Every sample WGSL file in every permutation of its `#ifdef`s, parsed and validated with
+//! naga without a GPU, so a typo fails here instead of in wgpu when the sample runs.
+
+use std::path::{Path, PathBuf};
+
+use gf_base::naga::ShaderStage;
+use gf_base::shader::{Defines, ShaderComposer};
+
+fn wgsl_files(dir: &Path, files: &mut Vec<PathBuf>) {
+    for entry in std::fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.is_dir() {
+            wgsl_files(&path, files);
+        } else if path.extension().is_some_and(|i| i == "wgsl") {
+            files.push(path);
+        }
+    }
+}
+
+/// Every subset of the names the `#ifdef`s test, including those only met under other defines.
+fn permutations(composer: &ShaderComposer, entry: &str) -> Result<Vec<Defines>, String> {
+    let mut names = Defines::new();
+    loop {
+        let mut permutations = vec![Defines::new()];
+        for name in &names {
+            let with: Vec<_> = permutations
+                .iter()
+                .map(|i| {
+                    let mut defines = i.clone();
+                    defines.insert(name.clone());
+                    defines
+                })
+                .collect();
+            permutations.extend(with);
+        }
+        let mut found = names.clone();
+        for defines in &permutations {
+            let preprocessed = composer
+                .preprocess(entry, defines)
+                .map_err(|err| err.to_string())?;
+            found.extend(preprocessed.conditions);
+        }
+        if found == names {
+            return Ok(permutations);
+        }
+        names = found;
+    }
+}
+
+/// Failures of one permutation of `entry`.
+fn check(composer: &ShaderComposer, entry: &str, defines: &Defines) -> Result<(), String> {
+    let reflection = composer
+        .preprocess(entry, defines)
+        .and_then(|i| i.validate())
+        .map_err(|err| err.to_string())?;
+    let entry_points = &reflection.module.entry_points;
+    if entry_points.is_empty() {
+        return Err("no entry points".into());
+    }
+    let stage = |stage| {
+        entry_points
+            .iter()
+            .filter(move |i| i.stage == stage)
+            .map(|i| i.name.as_str())
+    };
+    let vertices: Vec<_> = stage(ShaderStage::Vertex).collect();
+    for fragment in stage(ShaderStage::Fragment) {
+        let errors: Vec<_> = vertices
+            .iter()
+            .filter_map(|vertex| reflection.check_stage_interface(vertex, fragment).err())
+            .map(|err| err.to_string())
+            .collect();
+        // Fine when any vertex stage of the module feeds it
+        if !vertices.is_empty() && errors.len() == vertices.len() {
+            return Err(errors.join("\n"));
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn sample_shaders_validate() {
+    let mut files = vec![];
+    wgsl_files(
+        Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/examples")),
+        &mut files,
+    );
+    files.sort();
+    assert!(!files.is_empty());
+
+    let composer = ShaderComposer::default();
+    let mut failures = vec![];
+    for path in &files {
+        let entry = path.to_string_lossy();
+        let permutations = match permutations(&composer, &entry) {
+            Ok(permutations) => permutations,
+            Err(err) => {
+                failures.push(format!("{entry}:\n{err}"));
+                continue;
+            }
+        };
+        for defines in permutations {
+            if let Err(err) = check(&composer, &entry, &defines) {
+                failures.push(format!("{entry} with {defines:?}:\n{err}"));
+            }
+        }
+    }
+    assert!(failures.is_empty(), "\n{}", failures.join("\n\n"));
+}
+
+#[test]
+fn mesh_fragment_stages_see_the_facing() {
+    let composer = ShaderComposer::default();
+    for shader in [
+        "pbr/gbuffer_shader.wgsl",
+        "pbr/forward_shader.wgsl",
+        "deferred_render/gbuffer_shader.wgsl",
+        "deferred_render/forward_shader.wgsl",
+    ] {
+        let entry = format!("{}/examples/{shader}", env!("CARGO_MANIFEST_DIR"));
+        let reflection = composer
+            .preprocess(&entry, &Defines::new())
+            .and_then(|i| i.validate())
+            .unwrap();
+        let fragment = reflection
+            .module
+            .entry_points
+            .iter()
+            .find(|i| i.stage == ShaderStage::Fragment)
+            .unwrap();
+        let facing = fragment.function.arguments.iter().any(|i| {
+            i.binding
+                == Some(gf_base::naga::Binding::BuiltIn(
+                    gf_base::naga::BuiltIn::FrontFacing,
+                ))
+        });
+        assert!(facing, "{shader} shades back faces with the front normal");
+    }
+}
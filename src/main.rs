@@ -1,3 +1,68 @@
-fn main() {
-    println!("Hello, world!");
+use std::path::Path;
+use std::process::ExitCode;
+
+use gf_base::asset::cache::{self, BakeOption};
+use gf_base::asset::gltf::{LoadOption, SceneSelect};
+
+const USAGE: &str = "\
+usage: gwarf-fortress bake [options] <model.gltf|model.glb>...
+
+Write a scene cache next to every model, load_gltf picks it up while the model
+and the load options stay the same.
+
+options:
+    --tbn             generate tangents, like LoadOption::gen_tbn
+    --scene <scene>   scene index or name, the default scene otherwise
+    --compress        block compress RGBA8 textures, needs TEXTURE_COMPRESSION_BC";
+
+fn main() -> ExitCode {
+    gf_base::env_logger::init();
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match args.split_first() {
+        Some((command, args)) if command == "bake" => bake(args),
+        _ => {
+            eprintln!("{USAGE}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn bake(args: &[String]) -> ExitCode {
+    let mut option = LoadOption::default();
+    let mut bake_option = BakeOption::default();
+    let mut models = vec![];
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--tbn" => option.gen_tbn = true,
+            "--compress" => bake_option.compress = true,
+            "--scene" => {
+                let Some(scene) = args.next() else {
+                    eprintln!("{USAGE}");
+                    return ExitCode::FAILURE;
+                };
+                option.scene = match scene.parse() {
+                    Ok(index) => SceneSelect::Index(index),
+                    Err(_) => SceneSelect::Name(scene.clone()),
+                };
+            }
+            model => models.push(model),
+        }
+    }
+    if models.is_empty() {
+        eprintln!("{USAGE}");
+        return ExitCode::FAILURE;
+    }
+
+    let mut status = ExitCode::SUCCESS;
+    for model in models {
+        match cache::bake(Path::new(model), &option, &bake_option) {
+            Ok(cache) => println!("{model} -> {}", cache.display()),
+            Err(err) => {
+                eprintln!("{model}: {err}");
+                status = ExitCode::FAILURE;
+            }
+        }
+    }
+    status
 }